@@ -55,7 +55,7 @@ impl Default for Credential {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 #[cfg_attr(
     feature = "serde_support",
     derive(serde::Serialize, serde::Deserialize)
@@ -113,7 +113,7 @@ impl TryFrom<&str> for Service {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 #[cfg_attr(
     feature = "serde_support",
     derive(serde::Serialize, serde::Deserialize)
@@ -227,6 +227,60 @@ impl CredentialType {
     }
 }
 
+/// Placeholder for secrets in debug output, so that they never end up in logs.
+const REDACTED: &str = "<redacted>";
+
+impl std::fmt::Debug for PrivilegeInformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrivilegeInformation")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for CredentialType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialType::UP {
+                username,
+                privilege,
+                ..
+            } => f
+                .debug_struct("UP")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .field("privilege", privilege)
+                .finish(),
+            CredentialType::USK {
+                username,
+                privilege,
+                ..
+            } => f
+                .debug_struct("USK")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .field("private_key", &REDACTED)
+                .field("privilege", privilege)
+                .finish(),
+            CredentialType::SNMP {
+                username,
+                auth_algorithm,
+                privacy_algorithm,
+                ..
+            } => f
+                .debug_struct("SNMP")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .field("community", &REDACTED)
+                .field("auth_algorithm", auth_algorithm)
+                .field("privacy_password", &REDACTED)
+                .field("privacy_algorithm", privacy_algorithm)
+                .finish(),
+        }
+    }
+}
+
 impl AsRef<str> for CredentialType {
     fn as_ref(&self) -> &str {
         match self {
@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::collections::HashMap;
+
+/// Calculates the base score of a CVSS v2 or v3.x vector.
+///
+/// Vectors starting with `CVSS:3.` are handled as CVSS v3, all other ones as
+/// CVSS v2. Returns None when the vector is incomplete or contains unknown values.
+pub fn cvss_base_score(vector: &str) -> Option<f32> {
+    let (v3, metrics) = match vector.strip_prefix("CVSS:3.") {
+        Some(rest) => (true, rest.split_once('/')?.1),
+        None => (false, vector),
+    };
+    let metrics = metrics
+        .split('/')
+        .map(|x| x.split_once(':'))
+        .collect::<Option<HashMap<_, _>>>()?;
+    let metric = |name: &str| metrics.get(name).copied();
+    if v3 {
+        cvss_v3_base_score(metric)
+    } else {
+        cvss_v2_base_score(metric)
+    }
+}
+
+fn cvss_v3_base_score<'a>(metric: impl Fn(&str) -> Option<&'a str>) -> Option<f32> {
+    let changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let av = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let ac = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let pr = match (metric("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let ui = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let cia = |name| match metric(name)? {
+        "H" => Some(0.56),
+        "L" => Some(0.22),
+        "N" => Some(0.0),
+        _ => None,
+    };
+    let iss: f64 = 1.0 - (1.0 - cia("C")?) * (1.0 - cia("I")?) * (1.0 - cia("A")?);
+    let impact = if changed {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15)
+    } else {
+        6.42 * iss
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * av * ac * pr * ui;
+    let score = if changed {
+        1.08 * (impact + exploitability)
+    } else {
+        impact + exploitability
+    };
+    Some(round_up(score.min(10.0)) as f32)
+}
+
+/// Round up as defined in appendix A of the CVSS v3.1 specification
+fn round_up(value: f64) -> f64 {
+    let int_input = (value * 100_000.0).round() as i64;
+    if int_input % 10_000 == 0 {
+        int_input as f64 / 100_000.0
+    } else {
+        ((int_input / 10_000) + 1) as f64 / 10.0
+    }
+}
+
+fn cvss_v2_base_score<'a>(metric: impl Fn(&str) -> Option<&'a str>) -> Option<f32> {
+    let av = match metric("AV")? {
+        "L" => 0.395,
+        "A" => 0.646,
+        "N" => 1.0,
+        _ => return None,
+    };
+    let ac = match metric("AC")? {
+        "H" => 0.35,
+        "M" => 0.61,
+        "L" => 0.71,
+        _ => return None,
+    };
+    let au = match metric("Au")? {
+        "M" => 0.45,
+        "S" => 0.56,
+        "N" => 0.704,
+        _ => return None,
+    };
+    let cia = |name| match metric(name)? {
+        "N" => Some(0.0),
+        "P" => Some(0.275),
+        "C" => Some(0.660),
+        _ => None,
+    };
+    let impact: f64 = 10.41 * (1.0 - (1.0 - cia("C")?) * (1.0 - cia("I")?) * (1.0 - cia("A")?));
+    if impact == 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 20.0 * av * ac * au;
+    let score = ((0.6 * impact) + (0.4 * exploitability) - 1.5) * 1.176;
+    Some(((score * 10.0).round() / 10.0) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cvss_base_score;
+
+    #[test]
+    fn cvss_v3() {
+        assert_eq!(
+            cvss_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"),
+            Some(9.8)
+        );
+        assert_eq!(
+            cvss_base_score("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:C/C:N/I:H/A:N"),
+            Some(6.8)
+        );
+        assert_eq!(
+            cvss_base_score("CVSS:3.0/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N"),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn cvss_v2() {
+        assert_eq!(cvss_base_score("AV:N/AC:L/Au:N/C:P/I:P/A:P"), Some(7.5));
+        assert_eq!(cvss_base_score("AV:N/AC:L/Au:N/C:N/I:N/A:N"), Some(0.0));
+    }
+
+    #[test]
+    fn invalid_vector() {
+        assert_eq!(cvss_base_score("AV:N/AC:L"), None);
+        assert_eq!(
+            cvss_base_score("CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"),
+            None
+        );
+        assert_eq!(cvss_base_score(""), None);
+    }
+}
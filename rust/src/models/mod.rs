@@ -4,6 +4,7 @@
 
 mod advisories;
 mod credential;
+mod cvss;
 mod host_info;
 mod parameter;
 mod port;
@@ -20,6 +21,7 @@ mod vt;
 
 pub use advisories::*;
 pub use credential::*;
+pub use cvss::*;
 pub use host_info::*;
 pub use parameter::*;
 pub use port::*;
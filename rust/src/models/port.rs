@@ -84,6 +84,85 @@ impl Display for Protocol {
     }
 }
 
+/// Errors of a malformed openvas port range, e.g. `T:1-1024,U:53`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PortRangeError {
+    #[error("Port range is empty")]
+    Empty,
+    #[error("Invalid protocol '{0}', expected T or U")]
+    InvalidProtocol(String),
+    #[error("Invalid port '{0}'")]
+    InvalidPort(String),
+    #[error("Port {0} is not within 1-65535")]
+    OutOfRange(usize),
+    #[error("Start port {0} is greater than end port {1}")]
+    InvalidRange(usize, usize),
+}
+
+/// Parses a port range in the openvas format, e.g. `T:1-1024,U:53`.
+///
+/// Ranges without a protocol prefix are TCP ranges. A prefix applies to all
+/// following ranges until the next prefix.
+pub fn parse_openvas_port_range(value: &str) -> Result<Vec<Port>, PortRangeError> {
+    fn port(value: &str) -> Result<usize, PortRangeError> {
+        let port = value
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| PortRangeError::InvalidPort(value.trim().to_string()))?;
+        if (1..=65535).contains(&port) {
+            Ok(port)
+        } else {
+            Err(PortRangeError::OutOfRange(port))
+        }
+    }
+
+    let mut ports: Vec<Port> = vec![];
+    let mut protocol = Protocol::TCP;
+    for entry in value.split(',').map(str::trim) {
+        let range = match entry.split_once(':') {
+            Some((prefix, range)) => {
+                protocol = match prefix.trim() {
+                    "T" | "t" => Protocol::TCP,
+                    "U" | "u" => Protocol::UDP,
+                    x => return Err(PortRangeError::InvalidProtocol(x.to_string())),
+                };
+                range.trim()
+            }
+            None => entry,
+        };
+        if range.is_empty() {
+            continue;
+        }
+        let range = match range.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (port(start)?, port(end)?);
+                if start > end {
+                    return Err(PortRangeError::InvalidRange(start, end));
+                }
+                PortRange {
+                    start,
+                    end: Some(end),
+                }
+            }
+            None => PortRange {
+                start: port(range)?,
+                end: None,
+            },
+        };
+        match ports.last_mut() {
+            Some(last) if last.protocol == Some(protocol) => last.range.push(range),
+            _ => ports.push(Port {
+                protocol: Some(protocol),
+                range: vec![range],
+            }),
+        }
+    }
+    if ports.is_empty() {
+        return Err(PortRangeError::Empty);
+    }
+    Ok(ports)
+}
+
 pub fn ports_to_openvas_port_list(ports: Vec<Port>) -> Option<String> {
     fn add_range_to_list(list: &mut String, start: usize, end: Option<usize>) {
         // Add range
@@ -145,7 +224,10 @@ pub fn ports_to_openvas_port_list(ports: Vec<Port>) -> Option<String> {
 #[cfg(test)]
 mod tests {
 
-    use crate::models::{ports_to_openvas_port_list, Port, PortRange, Protocol};
+    use crate::models::{
+        parse_openvas_port_range, ports_to_openvas_port_list, Port, PortRange, PortRangeError,
+        Protocol,
+    };
 
     #[test]
     fn test_port_conversion_to_string() {
@@ -189,4 +271,57 @@ mod tests {
             Some("T:22,23,24,25,80,1000,U:30,31,32,33,34,35,36,37,38,39,40,5060,1000,".to_string())
         );
     }
+
+    #[test]
+    fn parse_combined_port_range() {
+        assert_eq!(
+            parse_openvas_port_range("T:1-1024,8080,U:53"),
+            Ok(vec![
+                Port {
+                    protocol: Some(Protocol::TCP),
+                    range: vec![
+                        PortRange {
+                            start: 1,
+                            end: Some(1024),
+                        },
+                        PortRange {
+                            start: 8080,
+                            end: None,
+                        },
+                    ],
+                },
+                Port {
+                    protocol: Some(Protocol::UDP),
+                    range: vec![PortRange {
+                        start: 53,
+                        end: None,
+                    }],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_invalid_port_range() {
+        assert_eq!(
+            parse_openvas_port_range("X:22"),
+            Err(PortRangeError::InvalidProtocol("X".to_string()))
+        );
+        assert_eq!(
+            parse_openvas_port_range("T:22-abc"),
+            Err(PortRangeError::InvalidPort("abc".to_string()))
+        );
+        assert_eq!(
+            parse_openvas_port_range("T:1024-1"),
+            Err(PortRangeError::InvalidRange(1024, 1))
+        );
+        assert_eq!(
+            parse_openvas_port_range("U:70000"),
+            Err(PortRangeError::OutOfRange(70000))
+        );
+        assert_eq!(
+            parse_openvas_port_range("T:,U:"),
+            Err(PortRangeError::Empty)
+        );
+    }
 }
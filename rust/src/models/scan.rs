@@ -2,6 +2,8 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
+use std::collections::HashSet;
+
 use super::{scanner_preference::ScanPreference, target::Target, vt::VT};
 
 pub type ScanId = String;
@@ -28,3 +30,139 @@ pub struct Scan {
     /// List of VTs to execute for the target
     pub vts: Vec<VT>,
 }
+
+/// Errors that occur while building a scan via [`ScanBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ScanBuildError {
+    #[error("The scan id must not be empty")]
+    EmptyScanId,
+    #[error("The target of the scan must contain at least one host")]
+    NoHosts,
+    #[error("The VT {0} is contained multiple times")]
+    DuplicateVT(String),
+}
+
+/// Builds a validated [`Scan`].
+///
+/// # Examples
+/// ```
+/// use scannerlib::models::{ScanBuildError, ScanBuilder};
+///
+/// let scan = ScanBuilder::new("scan")
+///     .host("127.0.0.1")
+///     .vt("1.3.6.1.4.1.25623.1.0.10267")
+///     .build()
+///     .unwrap();
+/// assert_eq!(scan.target.hosts, ["127.0.0.1"]);
+/// assert_eq!(ScanBuilder::new("scan").build(), Err(ScanBuildError::NoHosts));
+/// ```
+#[derive(Default, Debug, Clone)]
+pub struct ScanBuilder {
+    scan: Scan,
+}
+
+impl ScanBuilder {
+    /// Creates a new builder of a scan with the given id.
+    pub fn new(scan_id: impl Into<ScanId>) -> Self {
+        Self {
+            scan: Scan {
+                scan_id: scan_id.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets the target of the scan, replacing previously added hosts.
+    pub fn target(mut self, target: Target) -> Self {
+        self.scan.target = target;
+        self
+    }
+
+    /// Adds a host to the target of the scan.
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.scan.target.hosts.push(host.into());
+        self
+    }
+
+    /// Adds a VT without parameters to the scan.
+    pub fn vt(self, oid: impl Into<String>) -> Self {
+        self.vt_with_parameters(VT {
+            oid: oid.into(),
+            parameters: vec![],
+        })
+    }
+
+    /// Adds a VT to the scan.
+    pub fn vt_with_parameters(mut self, vt: VT) -> Self {
+        self.scan.vts.push(vt);
+        self
+    }
+
+    /// Adds a scan preference.
+    pub fn preference(mut self, preference: ScanPreference) -> Self {
+        self.scan.scan_preferences.push(preference);
+        self
+    }
+
+    /// Validates and returns the scan.
+    ///
+    /// A scan requires a non empty id, at least one host and each VT must only be contained once.
+    pub fn build(self) -> Result<Scan, ScanBuildError> {
+        if self.scan.scan_id.is_empty() {
+            return Err(ScanBuildError::EmptyScanId);
+        }
+        if self.scan.target.hosts.is_empty() {
+            return Err(ScanBuildError::NoHosts);
+        }
+        let mut oids = HashSet::new();
+        if let Some(vt) = self.scan.vts.iter().find(|vt| !oids.insert(&vt.oid)) {
+            return Err(ScanBuildError::DuplicateVT(vt.oid.clone()));
+        }
+        Ok(self.scan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScanBuildError, ScanBuilder};
+
+    #[test]
+    fn build() {
+        let scan = ScanBuilder::new("id")
+            .host("a")
+            .host("b")
+            .vt("1")
+            .vt("2")
+            .build()
+            .unwrap();
+        assert_eq!(scan.scan_id, "id");
+        assert_eq!(scan.target.hosts, ["a", "b"]);
+        assert_eq!(
+            scan.vts.iter().map(|x| x.oid.as_str()).collect::<Vec<_>>(),
+            ["1", "2"]
+        );
+    }
+
+    #[test]
+    fn empty_scan_id() {
+        let result = ScanBuilder::new("").host("a").build();
+        assert_eq!(result, Err(ScanBuildError::EmptyScanId));
+    }
+
+    #[test]
+    fn no_hosts() {
+        let result = ScanBuilder::new("id").vt("1").build();
+        assert_eq!(result, Err(ScanBuildError::NoHosts));
+    }
+
+    #[test]
+    fn duplicate_vt() {
+        let result = ScanBuilder::new("id")
+            .host("a")
+            .vt("1")
+            .vt("2")
+            .vt("1")
+            .build();
+        assert_eq!(result, Err(ScanBuildError::DuplicateVT("1".into())));
+    }
+}
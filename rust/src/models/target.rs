@@ -2,7 +2,14 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
 use super::{credential::Credential, port::Port};
+use crate::scanner::TargetHost;
 
 pub type Host = String;
 
@@ -57,6 +64,23 @@ pub enum AliveTestMethods {
 pub enum AliveTestMethodsError {
     #[error("Invalid value for AliveTestMethods: {0:#04x}")]
     InvalidValue(u8),
+    #[error("Invalid alive test method: {0}")]
+    InvalidName(String),
+}
+
+impl FromStr for AliveTestMethods {
+    type Err = AliveTestMethodsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp_ack" => Ok(AliveTestMethods::TcpAck),
+            "icmp" => Ok(AliveTestMethods::Icmp),
+            "arp" => Ok(AliveTestMethods::Arp),
+            "consider_alive" => Ok(AliveTestMethods::ConsiderAlive),
+            "tcp_syn" => Ok(AliveTestMethods::TcpSyn),
+            _ => Err(AliveTestMethodsError::InvalidName(s.to_string())),
+        }
+    }
 }
 
 impl TryFrom<u8> for AliveTestMethods {
@@ -73,3 +97,206 @@ impl TryFrom<u8> for AliveTestMethods {
         }
     }
 }
+
+impl Target {
+    /// Returns the expanded hosts without the expanded excluded hosts
+    ///
+    /// See [`expand_hosts`] for the expansion. The order of the hosts is kept. The hosts are
+    /// compared as [`TargetHost`], so that e.g. `[2001:db8::1]` excludes `2001:db8::1`.
+    pub fn expanded_hosts(&self) -> Result<Vec<Host>, HostExpansionError> {
+        let excluded = expand_hosts(&self.excluded_hosts)?
+            .iter()
+            .map(|x| TargetHost::from(x.as_str()))
+            .collect::<HashSet<_>>();
+        let mut hosts = expand_hosts(&self.hosts)?;
+        hosts.retain(|x| !excluded.contains(&TargetHost::from(x.as_str())));
+        Ok(hosts)
+    }
+}
+
+/// Errors that occur while expanding the hosts of a target.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HostExpansionError {
+    #[error("Invalid CIDR block: {0}")]
+    InvalidCidr(Host),
+    #[error("Invalid IP range: {0}")]
+    InvalidRange(Host),
+    #[error("The hosts expand to more than {MAX_EXPANDED_HOSTS} hosts")]
+    TooManyHosts,
+}
+
+/// Maximum amount of hosts a list of hosts may expand to, which is the size of a `/16` block.
+pub const MAX_EXPANDED_HOSTS: usize = 1 << 16;
+
+/// Expands IPv4 CIDR blocks (e.g. `192.168.0.0/30`) and ranges of the last
+/// octet (e.g. `192.168.0.1-5`) into single hosts.
+///
+/// As within openvas the network and broadcast address of a CIDR block are
+/// excluded unless the prefix is `/31` or `/32`. IPv6 CIDR blocks are not
+/// expanded. Every other entry, like a hostname or a single IP address, is
+/// returned untouched.
+///
+/// Fails with [`HostExpansionError::TooManyHosts`] before expanding when the
+/// result would contain more than [`MAX_EXPANDED_HOSTS`] hosts.
+pub fn expand_hosts(hosts: &[Host]) -> Result<Vec<Host>, HostExpansionError> {
+    let mut result = Vec::with_capacity(hosts.len());
+    for host in hosts {
+        if let Some((ip, _)) = host
+            .split_once('/')
+            .filter(|(ip, _)| ip.parse::<Ipv6Addr>().is_ok())
+        {
+            tracing::debug!(host, ip, "IPv6 CIDR blocks are not expanded");
+            result.push(host.clone());
+        } else if let Some((ip, prefix)) = host.split_once('/') {
+            let (first, last) = cidr_bounds(host, ip, prefix)?;
+            reserve(&mut result, (last - first) as usize + 1)?;
+            result.extend((first..=last).map(|x| Ipv4Addr::from(x).to_string()));
+        } else if let Some((ip, end)) = host
+            .split_once('-')
+            .and_then(|(ip, end)| Some((ip.parse::<Ipv4Addr>().ok()?, end)))
+        {
+            result.extend(expand_range(host, ip, end)?);
+        } else {
+            result.push(host.clone());
+        }
+        if result.len() > MAX_EXPANDED_HOSTS {
+            return Err(HostExpansionError::TooManyHosts);
+        }
+    }
+    Ok(result)
+}
+
+/// Reserves the space of `additional` hosts unless that exceeds [`MAX_EXPANDED_HOSTS`]
+fn reserve(result: &mut Vec<Host>, additional: usize) -> Result<(), HostExpansionError> {
+    if result.len() + additional > MAX_EXPANDED_HOSTS {
+        return Err(HostExpansionError::TooManyHosts);
+    }
+    result.reserve(additional);
+    Ok(())
+}
+
+/// Returns the first and last host address of an IPv4 CIDR block
+fn cidr_bounds(host: &str, ip: &str, prefix: &str) -> Result<(u32, u32), HostExpansionError> {
+    let invalid = || HostExpansionError::InvalidCidr(host.to_string());
+    let ip: Ipv4Addr = ip.parse().map_err(|_| invalid())?;
+    let prefix: u32 = prefix.parse().map_err(|_| invalid())?;
+    if prefix > 32 {
+        return Err(invalid());
+    }
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    let network = u32::from(ip) & mask;
+    let broadcast = network | !mask;
+    Ok(if prefix >= 31 {
+        (network, broadcast)
+    } else {
+        (network + 1, broadcast - 1)
+    })
+}
+
+fn expand_range(host: &str, ip: Ipv4Addr, end: &str) -> Result<Vec<Host>, HostExpansionError> {
+    let invalid = || HostExpansionError::InvalidRange(host.to_string());
+    let end: u8 = end.parse().map_err(|_| invalid())?;
+    let [a, b, c, start] = ip.octets();
+    if end < start {
+        return Err(invalid());
+    }
+    Ok((start..=end)
+        .map(|d| Ipv4Addr::new(a, b, c, d).to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts(hosts: &[&str]) -> Vec<Host> {
+        hosts.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn expand_cidr_block() {
+        assert_eq!(
+            expand_hosts(&hosts(&["192.168.0.0/30"])),
+            Ok(hosts(&["192.168.0.1", "192.168.0.2"]))
+        );
+        assert_eq!(
+            expand_hosts(&hosts(&["192.168.0.7/32"])),
+            Ok(hosts(&["192.168.0.7"]))
+        );
+    }
+
+    #[test]
+    fn expand_dash_range() {
+        assert_eq!(
+            expand_hosts(&hosts(&["10.0.0.254-255"])),
+            Ok(hosts(&["10.0.0.254", "10.0.0.255"]))
+        );
+    }
+
+    #[test]
+    fn exclude_hosts() {
+        let target = Target {
+            hosts: hosts(&["192.168.0.0/29", "localhost"]),
+            excluded_hosts: hosts(&["192.168.0.2-3", "192.168.0.5", "localhost"]),
+            ..Default::default()
+        };
+        assert_eq!(
+            target.expanded_hosts(),
+            Ok(hosts(&["192.168.0.1", "192.168.0.4", "192.168.0.6"]))
+        );
+    }
+
+    #[test]
+    fn exclude_ipv6_hosts() {
+        let target = Target {
+            hosts: hosts(&["2001:db8::1", "2001:db8::2", " test.host", "2001:db8::3"]),
+            excluded_hosts: hosts(&["[2001:db8::1]", "2001:db8:0::2 ", "test.host"]),
+            ..Default::default()
+        };
+        assert_eq!(target.expanded_hosts(), Ok(hosts(&["2001:db8::3"])));
+    }
+
+    #[test]
+    fn keep_hostnames() {
+        let names = hosts(&[
+            "localhost",
+            "my-host.example",
+            "127.0.0.1",
+            "::1",
+            "2001:db8::/32",
+        ]);
+        assert_eq!(expand_hosts(&names), Ok(names));
+    }
+
+    #[test]
+    fn too_many_hosts() {
+        assert_eq!(
+            expand_hosts(&hosts(&["10.0.0.0/16"])).map(|x| x.len()),
+            Ok(65534)
+        );
+        for hosts in [
+            hosts(&["10.0.0.0/8"]),
+            hosts(&["0.0.0.0/0"]),
+            hosts(&["10.0.0.0/16", "10.1.0.0/16"]),
+            hosts(&["10.0.0.0/16", "10.1.0.1-5"]),
+        ] {
+            assert_eq!(expand_hosts(&hosts), Err(HostExpansionError::TooManyHosts));
+        }
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        for host in ["192.168.0.0/33", "192.168.0.0/a", "wald.fee/24"] {
+            assert_eq!(
+                expand_hosts(&hosts(&[host])),
+                Err(HostExpansionError::InvalidCidr(host.to_string()))
+            );
+        }
+        for host in ["192.168.0.5-4", "192.168.0.1-256", "192.168.0.1-"] {
+            assert_eq!(
+                expand_hosts(&hosts(&[host])),
+                Err(HostExpansionError::InvalidRange(host.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+//! Defines NASL functions to access the preferences of the running script
+
+use nasl_function_proc_macro::nasl_function;
+
+use crate::nasl::prelude::*;
+
+/// Returns the value of a preference of the running script
+///
+/// The preference is looked up by `id` when given, otherwise by its name. When the scan does
+/// not set the preference its default is returned. Returns NULL for an unknown preference.
+#[nasl_function(named(id))]
+fn script_get_preference(context: &Context, name: Option<&str>, id: Option<i32>) -> NaslValue {
+    context
+        .script_preference(name, id)
+        .cloned()
+        .unwrap_or(NaslValue::Null)
+}
+
+pub struct Preferences;
+
+function_set! {
+    Preferences,
+    (
+        script_get_preference,
+    )
+}
@@ -261,6 +261,16 @@ mod tests {
         t.ok("--a;", 0);
     }
 
+    #[test]
+    fn assignment_in_condition() {
+        let mut t = TestBuilder::default();
+        t.run("if ((a = 12)) b = a;");
+        t.ok("b;", 12);
+        t.run("if ((c = 0)) d = 1; else d = 2;");
+        t.ok("c;", 0);
+        t.ok("d;", 2);
+    }
+
     #[test]
     fn implicit_extend() {
         let mut t = TestBuilder::default();
@@ -98,7 +98,7 @@ impl Interpreter<'_> {
                 let found = self
                     .register()
                     .named(name)
-                    .ok_or_else(|| InterpretError::not_found(name))?
+                    .ok_or_else(|| InterpretError::undefined_function(statement, name))?
                     .clone();
                 match found {
                     ContextType::Function(params, stmt) => {
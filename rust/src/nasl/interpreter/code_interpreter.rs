@@ -50,14 +50,21 @@ impl<'a, 'b> CodeInterpreter<'a, 'b> {
         }
     }
 
-    async fn next_(&mut self) -> Option<InterpretResult> {
-        if let Some(stmt) = self.statement.as_ref() {
-            match self.interpreter.next_interpreter() {
-                Some(inter) => Some(inter.retry_resolve(stmt, 5).await),
-                None => self.next_statement().await,
-            }
-        } else {
-            self.next_statement().await
+    /// Evaluates the current statement with the next forked interpreter
+    ///
+    /// Returns None when every forked interpreter already evaluated the current statement.
+    pub async fn next_fork(&mut self) -> Option<InterpretResult> {
+        let stmt = self.statement.as_ref()?;
+        let inter = self.interpreter.next_interpreter()?;
+        Some(inter.retry_resolve(stmt, 5).await)
+    }
+
+    /// Evaluates the current statement with the next forked interpreter or, when all forked
+    /// interpreter are done, the next statement
+    pub async fn next_result(&mut self) -> Option<InterpretResult> {
+        match self.next_fork().await {
+            Some(result) => Some(result),
+            None => self.next_statement().await,
         }
     }
 
@@ -67,7 +74,7 @@ impl<'a, 'b> CodeInterpreter<'a, 'b> {
         'a: 'b,
     {
         Box::pin(stream::unfold(self, |mut s| async move {
-            s.next_().await.map(|x| (x, s))
+            s.next_result().await.map(|x| (x, s))
         }))
     }
 
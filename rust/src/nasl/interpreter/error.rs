@@ -95,6 +95,9 @@ pub enum InterpretErrorKind {
     /// When the given key was not found in the context
     #[error("Key not found: {0}")]
     NotFound(String),
+    /// When a called function is neither a built-in nor defined by the script
+    #[error("Undefined function: {0}")]
+    UndefinedFunction(String),
     /// A LoadError occurred
     #[error("{0}")]
     LoadError(LoadError),
@@ -180,6 +183,11 @@ impl InterpretError {
         Self::new(InterpretErrorKind::NotFound(name.to_owned()), None)
     }
 
+    /// When a called function is unknown
+    pub fn undefined_function(stmt: &Statement, name: &str) -> Self {
+        Self::from_statement(stmt, InterpretErrorKind::UndefinedFunction(name.to_owned()))
+    }
+
     /// When a include file has syntactical errors
     pub fn include_syntax_error(file: &str, se: SyntaxError) -> Self {
         Self::new(
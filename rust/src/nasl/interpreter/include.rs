@@ -7,7 +7,10 @@ mod tests {
     use std::{collections::HashMap, string::String};
 
     use crate::nasl::interpreter::CodeInterpreter;
-    use crate::nasl::{syntax::LoadError, Loader};
+    use crate::nasl::{
+        syntax::{IncludeLoader, LoadError},
+        Loader,
+    };
 
     use crate::nasl::{nasl_std_functions, prelude::*};
     use crate::storage::DefaultDispatcher;
@@ -69,4 +72,42 @@ mod tests {
             )]))
         );
     }
+
+    #[tokio::test]
+    async fn transitive_includes() {
+        let plugins = HashMap::from([
+            (
+                "test.nasl".to_string(),
+                "include(\"a.inc\"); a() + b();".to_string(),
+            ),
+            (
+                "a.inc".to_string(),
+                "include(\"b.inc\"); function a() { return 1; }".to_string(),
+            ),
+            (
+                "b.inc".to_string(),
+                "function b() { return 2; }".to_string(),
+            ),
+        ]);
+        let loader = IncludeLoader::resolve(FakeInclude { plugins }, "test.nasl").unwrap();
+        for key in ["test.nasl", "a.inc", "b.inc"] {
+            assert!(loader.load(key).is_ok(), "{key} must be resolved");
+        }
+        let code = loader.load("test.nasl").unwrap();
+        let context = ContextFactory {
+            loader,
+            functions: nasl_std_functions(),
+            storage: DefaultDispatcher::default(),
+        };
+        let ctx = context.build(Default::default());
+        let mut interpreter = CodeInterpreter::new(&code, Register::default(), &ctx);
+        assert_eq!(
+            interpreter.next_statement().await.unwrap().unwrap(),
+            NaslValue::Null
+        );
+        assert_eq!(
+            interpreter.next_statement().await.unwrap().unwrap(),
+            3.into()
+        );
+    }
 }
@@ -307,6 +307,7 @@ mod tests {
         cast_to_string_end_plus_4: "1+2+\"\" + 4;" => "34",
         cast_to_string_minus: "11-\"1\";" => "1",
         string_plus: "\"hello \" + \"world!\";" => "hello world!",
+        string_plus_number: "\"ab\" + 1;" => "ab1",
         string_minus : "\"hello \" - 'o ';" => "hell",
         data_plus: "'hello ' + 'world!';" => "hello world!".as_bytes(),
         data_minus: "'hello ' - 'o ';" => "hell".as_bytes(),
@@ -14,7 +14,12 @@ use crate::nasl::syntax::{token::Token, Statement};
 /// A list specifying general categories of Syntax error.
 pub enum ErrorKind {
     /// An unexpected token occurred
-    #[error("Unexpected token: {0}")]
+    #[error(
+        "Unexpected token '{}' at {}:{}",
+        .0.category(),
+        .0.line_column.0,
+        .0.line_column.1
+    )]
     UnexpectedToken(Token),
     /// A token is unclosed
     ///
@@ -225,6 +230,34 @@ impl SyntaxError {
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
     }
+
+    /// Returns the error message followed by the affected line of the given source and a caret
+    /// underline of the offending token.
+    ///
+    /// When the error is not bound to a token or the token is not within the source only the
+    /// error message is returned.
+    pub fn annotated(&self, source: &str) -> String {
+        let message = self.to_string();
+        let Some(token) = self.as_token() else {
+            return message;
+        };
+        let (line, column) = token.line_column;
+        let Some(text) = line.checked_sub(1).and_then(|x| source.lines().nth(x)) else {
+            return message;
+        };
+        // keep tabs so that the underline is aligned to the source line
+        let indent: String = text
+            .chars()
+            .take(column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source
+            .get(token.position.0..token.position.1)
+            .map(|x| x.chars().take_while(|c| *c != '\n').count())
+            .unwrap_or_default()
+            .max(1);
+        format!("{message}\n{text}\n{indent}{}", "^".repeat(width))
+    }
 }
 
 impl From<io::Error> for SyntaxError {
@@ -239,7 +272,11 @@ impl From<io::Error> for SyntaxError {
 
 #[cfg(test)]
 mod tests {
-    use crate::nasl::syntax::{parse, ErrorKind, TokenCategory};
+    use crate::nasl::syntax::{
+        parse,
+        token::{Tokenizer, UnclosedCategory},
+        ErrorKind, Statement, SyntaxError, TokenCategory,
+    };
 
     fn test_for_missing_semicolon(code: &str) {
         let result = parse(code).next().unwrap();
@@ -293,4 +330,50 @@ mod tests {
         test_for_unclosed_token("{ a = 2;", TokenCategory::LeftCurlyBracket);
         test_for_unclosed_token("function a() { a = 2;", TokenCategory::LeftCurlyBracket);
     }
+
+    #[test]
+    fn unexpected_token_message() {
+        let code = "a = 1;\nb = c && d;";
+        let token = Tokenizer::new(code)
+            .find(|t| t.category() == &TokenCategory::AmpersandAmpersand)
+            .unwrap();
+        let error = syntax_error!(ErrorKind::UnexpectedToken(token));
+        assert_eq!(error.to_string(), "Unexpected token '&&' at 2:7");
+        assert_eq!(
+            error.annotated(code),
+            "Unexpected token '&&' at 2:7\nb = c && d;\n      ^^"
+        );
+    }
+
+    #[test]
+    fn annotated_without_token() {
+        let error = syntax_error!(ErrorKind::EoF);
+        assert_eq!(error.annotated("a = 1 +"), "Unexpected end of file.");
+    }
+
+    #[test]
+    fn unclosed_string() {
+        let mut results = parse("a = 1;\nb = \"unclosed;\nc = 2;");
+        assert!(results.next().unwrap().is_ok());
+        test_unclosed_string(results.next(), (2, 5));
+        // parsing resumes on the line after the unclosed string
+        assert!(results.next().unwrap().is_ok());
+        assert!(results.next().is_none());
+    }
+
+    fn test_unclosed_string(
+        result: Option<Result<Statement, SyntaxError>>,
+        line_column: (usize, usize),
+    ) {
+        match result.unwrap().unwrap_err().kind {
+            ErrorKind::UnclosedToken(token) => {
+                assert_eq!(
+                    token.category(),
+                    &TokenCategory::Unclosed(UnclosedCategory::String)
+                );
+                assert_eq!(token.line_column, line_column);
+            }
+            e => panic!("Expected UnclosedToken but got: {e:?}"),
+        }
+    }
 }
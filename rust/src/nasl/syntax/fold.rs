@@ -0,0 +1,175 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+//! Folds operations on literals into a single literal.
+
+use super::{IdentifierType, Statement, StatementKind, TokenCategory};
+
+/// Replaces operations that consist only of number or string literals by their result
+///
+/// Folded are the arithmetic, bitwise and comparison operators as well as the concatenation of
+/// strings. Variables, calls and every other statement are left untouched, but operations
+/// within them are folded.
+///
+/// An operation that would overflow, divide by zero or shift by more than the width of a
+/// number is not folded, so that it behaves at runtime as without folding.
+///
+/// # Examples
+/// ```
+/// use scannerlib::nasl::syntax::{fold_constants, parse};
+///
+/// let statement = parse("2 + 3 * 4;").next().unwrap().unwrap();
+/// assert_eq!(fold_constants(statement).as_number(), Some(14));
+/// ```
+pub fn fold_constants(statement: Statement) -> Statement {
+    let statement = statement.map_children(&mut fold_constants);
+    let StatementKind::Operator(category, operands) = statement.kind() else {
+        return statement;
+    };
+    let literals = operands
+        .iter()
+        .map(|x| match (x.kind(), x.as_token().category()) {
+            (StatementKind::Primitive, TokenCategory::Number(x)) => Some(Literal::Number(*x)),
+            (StatementKind::Primitive, TokenCategory::String(x)) => Some(Literal::String(x)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    let result = literals.and_then(|x| fold(category, &x));
+    match result {
+        Some(result) => statement.into_primitive(result),
+        None => statement,
+    }
+}
+
+enum Literal<'a> {
+    Number(i64),
+    String(&'a str),
+}
+
+fn boolean(x: bool) -> TokenCategory {
+    TokenCategory::Identifier(match x {
+        true => IdentifierType::True,
+        false => IdentifierType::False,
+    })
+}
+
+fn fold(category: &TokenCategory, operands: &[Literal]) -> Option<TokenCategory> {
+    use Literal::*;
+    use TokenCategory as C;
+    let number = |x: Option<i64>| x.map(C::Number);
+    // shifting by the width of a number or more is an overflow
+    let shift = |x: i64, width: i64| (0..width).contains(&x).then_some(x as u32);
+    match (category, operands) {
+        (C::Minus, [Number(a)]) => number(a.checked_neg()),
+        (C::Tilde, [Number(a)]) => Some(C::Number(!a)),
+        (C::Plus, [Number(a), Number(b)]) => number(a.checked_add(*b)),
+        (C::Minus, [Number(a), Number(b)]) => number(a.checked_sub(*b)),
+        (C::Star, [Number(a), Number(b)]) => number(a.checked_mul(*b)),
+        (C::Slash, [Number(a), Number(b)]) => number(a.checked_div(*b)),
+        (C::Percent, [Number(a), Number(b)]) => number(a.checked_rem(*b)),
+        (C::StarStar, [Number(a), Number(b)]) => {
+            let exponent = u32::try_from(*b).ok()?;
+            number((*a as u32).checked_pow(exponent).map(i64::from))
+        }
+        (C::LessLess, [Number(a), Number(b)]) => number(a.checked_shl(shift(*b, 64)?)),
+        (C::GreaterGreater, [Number(a), Number(b)]) => number(a.checked_shr(shift(*b, 64)?)),
+        (C::GreaterGreaterGreater, [Number(a), Number(b)]) => {
+            let result = (*a as u32).checked_shr(shift(*b, 32)?)?;
+            Some(C::Number(result as i32 as i64))
+        }
+        (C::Ampersand, [Number(a), Number(b)]) => Some(C::Number(a & b)),
+        (C::Pipe, [Number(a), Number(b)]) => Some(C::Number(a | b)),
+        (C::Caret, [Number(a), Number(b)]) => Some(C::Number(a ^ b)),
+        (C::Less, [Number(a), Number(b)]) => Some(boolean(a < b)),
+        (C::LessEqual, [Number(a), Number(b)]) => Some(boolean(a <= b)),
+        (C::Greater, [Number(a), Number(b)]) => Some(boolean(a > b)),
+        (C::GreaterEqual, [Number(a), Number(b)]) => Some(boolean(a >= b)),
+        (C::EqualEqual, [Number(a), Number(b)]) => Some(boolean(a == b)),
+        (C::BangEqual, [Number(a), Number(b)]) => Some(boolean(a != b)),
+        (C::EqualEqual, [String(a), String(b)]) => Some(boolean(a == b)),
+        (C::BangEqual, [String(a), String(b)]) => Some(boolean(a != b)),
+        (C::Plus, [String(a), String(b)]) => Some(C::String(format!("{a}{b}"))),
+        (C::Plus, [String(a), Number(b)]) => Some(C::String(format!("{a}{b}"))),
+        (C::Plus, [Number(a), String(b)]) => Some(C::String(format!("{a}{b}"))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nasl::syntax::{parse, IdentifierType, Statement, StatementKind, TokenCategory};
+
+    use super::fold_constants;
+
+    fn folded(code: &str) -> Statement {
+        fold_constants(parse(code).next().unwrap().unwrap())
+    }
+
+    fn category(code: &str) -> Option<TokenCategory> {
+        let statement = folded(code);
+        match statement.kind() {
+            StatementKind::Primitive => Some(statement.as_token().category().clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(folded("2 + 3 * 4;").as_number(), Some(14));
+        assert_eq!(folded("(2 + 3) * 4;").as_number(), Some(20));
+        assert_eq!(folded("1 + -5;").as_number(), Some(-4));
+        assert_eq!(folded("7 % 4;").as_number(), Some(3));
+        assert_eq!(folded("2 ** 10;").as_number(), Some(1024));
+        assert_eq!(folded("1 << 4 | 1;").as_number(), Some(17));
+        assert_eq!(folded("0xFF >>> 4;").as_number(), Some(15));
+    }
+
+    #[test]
+    fn comparison() {
+        let boolean = |x| Some(TokenCategory::Identifier(x));
+        assert_eq!(category("1 < 2;"), boolean(IdentifierType::True));
+        assert_eq!(category("1 + 1 == 3;"), boolean(IdentifierType::False));
+        assert_eq!(category("\"a\" != \"b\";"), boolean(IdentifierType::True));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            category("\"a\" + \"b\" + 1;"),
+            Some(TokenCategory::String("ab1".to_owned()))
+        );
+        // data is not folded
+        assert_eq!(category("'a' + 'b';"), None);
+    }
+
+    #[test]
+    fn untouched() {
+        for code in [
+            "a + 1;",
+            "f() * 2;",
+            "1 / 0;",
+            "9223372036854775807 + 1;",
+            "1 << 64;",
+            "1 =~ 1;",
+        ] {
+            let statement = parse(code).next().unwrap().unwrap();
+            assert_eq!(fold_constants(statement.clone()), statement, "{code}");
+        }
+        // only the negation of the exponent is folded
+        assert_eq!(folded("2 ** -1;").as_number(), None);
+    }
+
+    #[test]
+    fn nested() {
+        let statement = folded("if (a) b = 60 * 60; else exit(1 + 2);");
+        assert_eq!(
+            statement.to_string(),
+            parse("if (a) b = 3600; else exit(3);")
+                .next()
+                .unwrap()
+                .unwrap()
+                .to_string()
+        );
+    }
+}
@@ -103,7 +103,7 @@ impl Grouping for Lexer<'_> {
 
 #[cfg(test)]
 mod test {
-    use super::super::{parse, StatementKind};
+    use super::super::{parse, StatementKind, TokenCategory};
 
     use StatementKind::*;
 
@@ -126,4 +126,40 @@ mod test {
         );
         assert!(matches!(stmt, Block(..)));
     }
+
+    #[test]
+    fn array_literals() {
+        let elements = |code| match result(code) {
+            Assign(_, _, lhs, rhs) => {
+                assert_eq!(lhs.kind(), &Array(None));
+                match rhs.kind() {
+                    Parameter(x) => x
+                        .iter()
+                        .map(|x| x.start().category().clone())
+                        .collect::<Vec<_>>(),
+                    kind => panic!("expected array literal, got: {kind:?}"),
+                }
+            }
+            kind => panic!("expected assignment, got: {kind:?}"),
+        };
+        assert!(elements("x = [];").is_empty());
+        assert_eq!(
+            elements("x = [1, 2, 3];"),
+            vec![
+                TokenCategory::Number(1),
+                TokenCategory::Number(2),
+                TokenCategory::Number(3)
+            ]
+        );
+        assert_eq!(
+            elements("x = [\"a\", 1, 'b',];"),
+            vec![
+                TokenCategory::String("a".to_owned()),
+                TokenCategory::Number(1),
+                TokenCategory::Data(b"b".to_vec())
+            ]
+        );
+        // a preceding expression makes it a subscript instead
+        assert!(matches!(result("x[1];"), Array(Some(_))));
+    }
 }
@@ -493,7 +493,7 @@ mod test {
     use super::super::{
         parse,
         token::{Category, IdentifierType},
-        Statement,
+        AssignOrder, Statement,
     };
 
     use super::super::StatementKind::*;
@@ -520,6 +520,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn if_assignment() {
+        for code in ["if ((x = foo())) bar();", "if (x = foo()) bar();"] {
+            let actual = parse(code).next().unwrap().unwrap();
+            let If(condition, body, None, None) = actual.kind() else {
+                unreachable!("{actual} must be if without else stmt.");
+            };
+            match condition.kind() {
+                Assign(Equal, AssignOrder::AssignReturn, lhs, rhs) => {
+                    assert_eq!(lhs.kind(), &Variable);
+                    assert!(matches!(rhs.kind(), Call(..)), "{rhs}");
+                }
+                _ => unreachable!("{condition} must be an assignment returning the value."),
+            }
+            assert!(matches!(body.kind(), Call(..)), "{body}");
+        }
+    }
+
     #[test]
     fn if_block() {
         let actual = parse("if (description) { ; }").next().unwrap().unwrap();
@@ -13,7 +13,9 @@ use super::{
     AssignOrder, Statement, StatementKind,
 };
 
-use crate::{max_recursion, unexpected_statement, unexpected_token};
+use crate::{
+    max_recursion, unclosed_token, unexpected_end, unexpected_statement, unexpected_token,
+};
 
 /// Is used to parse Token to Statement
 pub struct Lexer<'a> {
@@ -58,24 +60,31 @@ impl Not for End {
 /// The binding power is used to express the order of a statement.
 /// Because the binding power of e,g. Plus is lower than Star the Star operation gets calculate before.
 /// The first number represents the left hand, the second number the right hand binding power
-fn infix_binding_power(op: &Operation) -> Option<(u8, u8)> {
+pub(crate) fn infix_binding_power(op: &Operation) -> Option<(u8, u8)> {
     use self::Operation::*;
     use Category::*;
     let res = match op {
         Operator(Category::StarStar) => (22, 23),
         Operator(Category::Star | Category::Slash | Category::Percent) => (20, 21),
+        // `+` and `-` are also string concatenation and removal, the interpreter decides by the
+        // runtime type of the operands so strings must parse exactly like numbers
         Operator(Plus | Minus) => (18, 19),
         Operator(LessLess | GreaterGreater | GreaterGreaterGreater) => (16, 17),
         Operator(Ampersand) => (14, 15),
         Operator(Caret) => (12, 13),
         Operator(Pipe) => (10, 11),
+        // the substring operators >< and >!< bind like comparisons and are left associative,
+        // so `a >< b == c` is `(a >< b) == c`
         Operator(
             Less | LessEqual | Greater | GreaterEqual | EqualEqual | BangEqual | GreaterLess
             | GreaterBangLess | EqualTilde | BangTilde,
         ) => (8, 9),
         Operator(AmpersandAmpersand) => (6, 7),
         Operator(PipePipe) => (4, 5),
+        // right associative to nest `a ? b : c ? d : e` as `a ? b : (c ? d : e)`
+        Operator(QuestionMark) => (3, 3),
         // two is lowest since on block we can start with 1
+        // `x` repeats the whole left statement, e.g. `send(data: "a" + "b") x 3;`
         Assign(_) | Operator(X) => (2, 3),
 
         _ => return None,
@@ -108,14 +117,13 @@ impl<'a> Lexer<'a> {
     }
 
     /// Returns peeks token of tokenizer
+    ///
+    /// Comments in front of the peeked token are skipped.
     pub(crate) fn peek(&mut self) -> Option<Token> {
-        for token in self.tokenizer.clone() {
-            if token.category() == &Category::Comment {
-                continue;
-            }
-            return Some(token);
+        while self.tokenizer.peek()?.category() == &Category::Comment {
+            self.tokenizer.next();
         }
-        None
+        self.tokenizer.peek().cloned()
     }
 
     pub(crate) fn parse_comma_group(
@@ -149,6 +157,47 @@ impl<'a> Lexer<'a> {
         Ok((end, params))
     }
 
+    /// Parses the conditional operator `cond ? a : b` into an If statement.
+    ///
+    /// The else token of the resulting statement is the `:` token.
+    fn ternary_statement(
+        &mut self,
+        right_bp: u8,
+        token: Token,
+        condition: Statement,
+        abort: &impl Fn(&Category) -> bool,
+    ) -> Result<(End, Statement), SyntaxError> {
+        // a closure would be instantiated anew for each abort and recurse endlessly
+        fn is_colon(c: &Category) -> bool {
+            c == &Category::DoublePoint
+        }
+        let condition = condition.as_returnable_or_err()?;
+        let (end, truthy) = self.statement(0, &is_colon)?;
+        let colon = match end {
+            End::Done(x) if x.category() == &Category::DoublePoint => x,
+            _ => return Err(unclosed_token!(token)),
+        };
+        let (end, falsy) = self.statement(right_bp, abort)?;
+        if matches!(falsy.kind(), StatementKind::EoF) {
+            return Err(unexpected_end!("expected an expression after ':'"));
+        }
+        let end_token = match &end {
+            End::Done(x) if abort(x.category()) => x.clone(),
+            End::Done(_) | End::Continue => falsy.end().clone(),
+        };
+        let stmt = Statement::with_start_end_token(
+            condition.start().clone(),
+            end_token,
+            StatementKind::If(
+                Box::new(condition),
+                Box::new(truthy),
+                Some(colon),
+                Some(Box::new(falsy)),
+            ),
+        );
+        Ok((end, stmt))
+    }
+
     fn infix_statement(
         &mut self,
         op: Operation,
@@ -157,6 +206,9 @@ impl<'a> Lexer<'a> {
         lhs: Statement,
         abort: &impl Fn(&Category) -> bool,
     ) -> Result<(End, Statement), SyntaxError> {
+        if op == Operation::Operator(Category::QuestionMark) {
+            return self.ternary_statement(right_bp, token, lhs, abort);
+        }
         let (end, rhs) = self.statement(right_bp, abort)?;
         if matches!(rhs.kind(), StatementKind::EoF) {
             return Ok((End::Done(token), rhs));
@@ -284,6 +336,9 @@ impl<'a> Lexer<'a> {
         let (state, mut left) = self
             .token()
             .map(|token| {
+                if matches!(token.category(), Category::Unclosed(_)) {
+                    return Err(unclosed_token!(token));
+                }
                 if token.is_faulty() {
                     return Err(unexpected_token!(token));
                 }
@@ -440,6 +495,13 @@ mod infix {
                     todo!("{:?}", token)
                 }
             },
+            If(condition, truthy, _, Some(falsy)) => {
+                if resolve(condition) != 0 {
+                    resolve(truthy)
+                } else {
+                    resolve(falsy)
+                }
+            }
             _ => todo!("operator not found"),
         }
     }
@@ -486,6 +548,32 @@ mod infix {
         calculated_test!("1 ^ 1;", 0);
     }
 
+    #[test]
+    fn ternary() {
+        calculated_test!("1 ? 2 : 3;", 2);
+        calculated_test!("0 ? 2 : 3;", 3);
+        // right associative
+        calculated_test!("1 ? 2 : 0 ? 3 : 4;", 2);
+        calculated_test!("0 ? 2 : 1 ? 3 : 4;", 3);
+        calculated_test!("0 ? 2 : 0 ? 3 : 4;", 4);
+        calculated_test!("1 ? 0 ? 5 : 6 : 7;", 6);
+        // binds weaker than +
+        calculated_test!("1 - 1 ? 2 : 3 + 4;", 7);
+        calculated_test!("1 ? 1 : 2 + 3;", 1);
+        calculated_test!("(1 ? 2 : 3) + 4;", 6);
+
+        let stmt = result("a ? b : c ? d : e;");
+        match stmt.kind() {
+            If(_, _, Some(colon), Some(falsy)) => {
+                assert_eq!(colon.category(), &DoublePoint);
+                assert!(matches!(falsy.kind(), If(..)));
+            }
+            kind => panic!("Expected If, got: {:?}", kind),
+        }
+        assert!(matches!(result("x = a ? b : c;").kind(), Assign(..)));
+        assert!(parse("a ? b;").next().unwrap().is_err());
+    }
+
     #[test]
     fn operator_assignment() {
         use Category::*;
@@ -507,6 +595,121 @@ mod infix {
         expected(result("a >>>= 1;"), GreaterGreaterGreaterEqual);
     }
 
+    #[test]
+    fn increment_order() {
+        fn expected(stmt: &Statement, category: Category, order: AssignOrder) {
+            match stmt.kind() {
+                Assign(cat, o, var, value) => {
+                    assert_eq!(cat, &category);
+                    assert_eq!(o, &order);
+                    assert!(matches!(var.kind(), Variable | Array(..)));
+                    assert_eq!(value.kind(), &NoOp);
+                }
+                kind => panic!("Expected Assign, got: {:?}", kind),
+            }
+        }
+        expected(&result("++i;"), PlusPlus, AssignOrder::AssignReturn);
+        expected(&result("--i;"), MinusMinus, AssignOrder::AssignReturn);
+        expected(&result("i++;"), PlusPlus, AssignOrder::ReturnAssign);
+        expected(&result("i--;"), MinusMinus, AssignOrder::ReturnAssign);
+
+        let stmt = result("a[i++];");
+        match stmt.kind() {
+            Array(Some(lookup)) => expected(lookup, PlusPlus, AssignOrder::ReturnAssign),
+            kind => panic!("Expected Array, got: {:?}", kind),
+        }
+        let stmt = result("x = 1 + i++;");
+        match stmt.kind() {
+            Assign(_, _, _, value) => match value.kind() {
+                Operator(Plus, args) => expected(&args[1], PlusPlus, AssignOrder::ReturnAssign),
+                kind => panic!("Expected Operator, got: {:?}", kind),
+            },
+            kind => panic!("Expected Assign, got: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn substring_operator() {
+        let operands = |stmt: &Statement| match stmt.kind() {
+            Operator(cat, args) => (cat.clone(), args.clone()),
+            kind => panic!("Expected Operator, got: {:?}", kind),
+        };
+        for (code, substring) in [
+            ("a >< b == c;", GreaterLess),
+            ("a >!< b == c;", GreaterBangLess),
+        ] {
+            let (cat, args) = operands(&result(code));
+            assert_eq!(cat, EqualEqual);
+            assert_eq!(operands(&args[0]).0, substring);
+            assert_eq!(args[1].kind(), &Variable);
+        }
+        // binds weaker than +
+        let (cat, args) = operands(&result("a + b >< c;"));
+        assert_eq!(cat, GreaterLess);
+        assert_eq!(operands(&args[0]).0, Plus);
+        // and stronger than &&
+        let (cat, args) = operands(&result("a >!< b && c;"));
+        assert_eq!(cat, AmpersandAmpersand);
+        assert_eq!(operands(&args[0]).0, GreaterBangLess);
+    }
+
+    #[test]
+    fn string_operator() {
+        let operands = |stmt: &Statement| match stmt.kind() {
+            Operator(cat, args) => (cat.clone(), args.clone()),
+            kind => panic!("Expected Operator, got: {:?}", kind),
+        };
+        let primitive = |stmt: &Statement| {
+            assert_eq!(stmt.kind(), &Primitive);
+            stmt.start().category().clone()
+        };
+        for (code, left, right) in [
+            (
+                "\"a\" + \"b\";",
+                String("a".to_owned()),
+                String("b".to_owned()),
+            ),
+            ("\"ab\" + 1;", String("ab".to_owned()), Number(1)),
+            ("1 + 'ab';", Number(1), Data(b"ab".to_vec())),
+        ] {
+            let (cat, args) = operands(&result(code));
+            assert_eq!(cat, Plus);
+            assert_eq!(args.len(), 2);
+            assert_eq!(primitive(&args[0]), left);
+            assert_eq!(primitive(&args[1]), right);
+        }
+        // concatenation is left associative like numeric addition
+        let (cat, args) = operands(&result("\"a\" + \"b\" + 1;"));
+        assert_eq!(cat, Plus);
+        assert_eq!(operands(&args[0]).0, Plus);
+        assert_eq!(primitive(&args[1]), Number(1));
+        // and binds weaker than *
+        let (cat, args) = operands(&result("\"a\" + 2 * 3;"));
+        assert_eq!(cat, Plus);
+        assert_eq!(operands(&args[1]).0, Star);
+        // x repeats the whole concatenation
+        let (cat, args) = operands(&result("\"a\" + \"b\" x 2;"));
+        assert_eq!(cat, X);
+        assert_eq!(operands(&args[0]).0, Plus);
+        assert_eq!(primitive(&args[1]), Number(2));
+    }
+
+    #[test]
+    fn regex_literal() {
+        let pattern = |code| match result(code).kind() {
+            Operator(EqualTilde | BangTilde, args) => args[1].start().category().clone(),
+            kind => panic!("Expected regex match, got: {:?}", kind),
+        };
+        assert_eq!(
+            pattern("banner =~ /^Server:/;"),
+            Regex("^Server:".to_owned())
+        );
+        assert_eq!(
+            pattern(r"url !~ /^\/cgi-bin\//;"),
+            Regex("^/cgi-bin/".to_owned())
+        );
+    }
+
     #[test]
     fn compare_operator() {
         use Category::*;
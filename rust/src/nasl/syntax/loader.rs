@@ -5,13 +5,17 @@
 //! This crate is used to load NASL code based on a name.
 
 use std::{
+    collections::HashMap,
     fs::{self, File},
-    io,
+    io::{self, BufRead, Read},
     path::{Path, PathBuf},
 };
 
+use flate2::read::GzDecoder;
 use thiserror::Error;
 
+use super::{parse, StatementKind, TokenCategory};
+
 /// Defines abstract Loader error cases
 #[derive(Clone, Debug, PartialEq, Eq, Error)]
 pub enum LoadError {
@@ -179,3 +183,216 @@ where
         Ok(String::default())
     }
 }
+
+/// Is a plugin loader reading the scripts directly out of a feed archive.
+///
+/// The archive must be a tar archive, it may be gzip compressed. All `.nasl` and `.inc` entries
+/// are decompressed once on creation and kept in memory, keys are resolved relative to the root
+/// of the archive.
+#[derive(Debug, Clone)]
+pub struct ArchivePluginLoader {
+    root: String,
+    entries: HashMap<String, String>,
+}
+
+impl ArchivePluginLoader {
+    /// Creates a new loader based on the archive at the given path
+    pub fn new<P>(path: P) -> Result<Self, LoadError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| LoadError::from((path, e)))?;
+        Self::from_reader(path.to_str().unwrap_or_default(), io::BufReader::new(file))
+    }
+
+    /// Creates a new loader based on an archive read from the reader.
+    ///
+    /// The root is only used to identify the archive, e.g. in errors.
+    pub fn from_reader<R>(root: &str, mut reader: R) -> Result<Self, LoadError>
+    where
+        R: BufRead,
+    {
+        let gzip = reader
+            .fill_buf()
+            .map_err(|e| LoadError::from((root, e)))?
+            .starts_with(&[0x1f, 0x8b]);
+        let reader: Box<dyn Read> = if gzip {
+            Box::new(GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+        let mut entries = HashMap::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(|e| LoadError::from((root, e)))? {
+            let mut entry = entry.map_err(|e| LoadError::from((root, e)))?;
+            let path = entry
+                .path()
+                .map_err(|e| LoadError::from((root, e)))?
+                .to_string_lossy()
+                .into_owned();
+            if !(path.ends_with(".nasl") || path.ends_with(".inc")) {
+                continue;
+            }
+            let mut content = vec![];
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| LoadError::from((path.as_str(), e)))?;
+            // unfortunately nasl is still in iso-8859-1
+            let content = content.iter().map(|&b| b as char).collect();
+            entries.insert(Self::normalize(&path).to_owned(), content);
+        }
+        Ok(Self {
+            root: root.to_owned(),
+            entries,
+        })
+    }
+
+    fn normalize(key: &str) -> &str {
+        key.trim_start_matches("./").trim_start_matches('/')
+    }
+}
+
+impl Loader for ArchivePluginLoader {
+    fn load(&self, key: &str) -> Result<String, LoadError> {
+        self.entries
+            .get(Self::normalize(key))
+            .cloned()
+            .ok_or_else(|| LoadError::NotFound(format!("{key} does not exist in {}.", self.root)))
+    }
+
+    fn root_path(&self) -> Result<String, LoadError> {
+        Ok(self.root.clone())
+    }
+}
+
+/// Is a loader resolving all transitive includes of a script before it is interpreted.
+///
+/// The script as well as every file included directly or indirectly via
+/// `include("...")` are loaded once on creation and kept in memory. Each file is
+/// only loaded once, so include cycles are resolved as well. Keys not known
+/// upfront, e.g. because the include is computed at runtime, are loaded by the
+/// wrapped loader.
+pub struct IncludeLoader<L> {
+    inner: L,
+    sources: HashMap<String, String>,
+}
+
+impl<L> IncludeLoader<L>
+where
+    L: Loader,
+{
+    /// Loads the script of the given key and all of its includes.
+    pub fn resolve(inner: L, key: &str) -> Result<Self, LoadError> {
+        let mut sources = HashMap::new();
+        let mut pending = vec![key.to_owned()];
+        while let Some(key) = pending.pop() {
+            if sources.contains_key(&key) {
+                continue;
+            }
+            let code = inner.load(&key)?;
+            pending.extend(Self::includes(&code).into_iter().rev());
+            sources.insert(key, code);
+        }
+        Ok(Self { inner, sources })
+    }
+
+    /// Returns the names of all files that are included with a constant name.
+    fn includes(code: &str) -> Vec<String> {
+        parse(code)
+            .filter_map(Result::ok)
+            .flat_map(|stmt| {
+                stmt.find(&|s| matches!(s.kind(), StatementKind::Include(_)))
+                    .into_iter()
+                    .filter_map(|s| match s.kind() {
+                        StatementKind::Include(name) => match name.as_token().category() {
+                            TokenCategory::String(name) => Some(name.clone()),
+                            TokenCategory::Data(name) => {
+                                Some(String::from_utf8_lossy(name).into_owned())
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl<L> Loader for IncludeLoader<L>
+where
+    L: Loader,
+{
+    fn load(&self, key: &str) -> Result<String, LoadError> {
+        match self.sources.get(key) {
+            Some(code) => Ok(code.clone()),
+            None => self.inner.load(key),
+        }
+    }
+
+    fn root_path(&self) -> Result<String, LoadError> {
+        self.inner.root_path()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{ArchivePluginLoader, IncludeLoader, LoadError, Loader};
+
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        let mut encoder = builder.into_inner().unwrap();
+        encoder.flush().unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn load_from_archive() {
+        let data = archive(&[
+            ("./plugin_feed_info.inc", "PLUGIN_SET = \"202501010000\";"),
+            ("2025/test.nasl", "display('hi');"),
+            ("README", "not a script"),
+        ]);
+        let loader = ArchivePluginLoader::from_reader("feed.tar.gz", &data[..]).unwrap();
+        assert_eq!(
+            loader.load("plugin_feed_info.inc").unwrap(),
+            "PLUGIN_SET = \"202501010000\";"
+        );
+        assert_eq!(loader.load("2025/test.nasl").unwrap(), "display('hi');");
+        assert!(matches!(loader.load("README"), Err(LoadError::NotFound(_))));
+        assert_eq!(loader.root_path().unwrap(), "feed.tar.gz");
+    }
+
+    #[test]
+    fn resolve_include_cycles() {
+        let loaded = std::sync::Mutex::new(vec![]);
+        let inner = |key: &str| {
+            loaded.lock().unwrap().push(key.to_owned());
+            match key {
+                "test.nasl" => "include('a.inc');",
+                "a.inc" => "if (TRUE) { include(\"b.inc\"); }",
+                "b.inc" => "include(\"a.inc\");",
+                _ => "",
+            }
+            .to_owned()
+        };
+        let loader = IncludeLoader::resolve(inner, "test.nasl").unwrap();
+        assert_eq!(*loaded.lock().unwrap(), ["test.nasl", "a.inc", "b.inc"]);
+        assert_eq!(loader.load("b.inc").unwrap(), "include(\"a.inc\");");
+        assert_eq!(loaded.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,129 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+//! Extracts the metadata of a script from its AST without executing it.
+
+use super::{parse, NaslValue, Statement, StatementKind, SyntaxError, TokenCategory};
+
+/// Metadata of a script that is declared by calls of the description functions
+///
+/// Only literal arguments are considered. Arguments that are computed, e.g. by a variable or
+/// a concatenation, are skipped, as the extraction does not execute the script. When a
+/// description function is called multiple times the values are appended.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScriptMetadata {
+    /// OID declared by `script_oid`
+    pub oid: Option<String>,
+    /// Filenames declared by `script_dependencies`
+    pub dependencies: Vec<String>,
+    /// Keys declared by `script_exclude_keys`
+    pub excluded_keys: Vec<String>,
+    /// Keys declared by `script_mandatory_keys`
+    pub mandatory_keys: Vec<String>,
+    /// Keys declared by `script_require_keys`
+    pub required_keys: Vec<String>,
+    /// Ports declared by `script_require_ports`
+    pub required_ports: Vec<String>,
+    /// Ports declared by `script_require_udp_ports`
+    pub required_udp_ports: Vec<String>,
+}
+
+impl ScriptMetadata {
+    fn add(&mut self, call: &Statement) {
+        let StatementKind::Call(parameter) = call.kind() else {
+            return;
+        };
+        let TokenCategory::Identifier(name) = call.as_token().category() else {
+            return;
+        };
+        let field = match name.to_string().as_str() {
+            "script_oid" => {
+                self.oid = literals(parameter).into_iter().next().or(self.oid.take());
+                return;
+            }
+            "script_dependencies" => &mut self.dependencies,
+            "script_exclude_keys" => &mut self.excluded_keys,
+            "script_mandatory_keys" => &mut self.mandatory_keys,
+            "script_require_keys" => &mut self.required_keys,
+            "script_require_ports" => &mut self.required_ports,
+            "script_require_udp_ports" => &mut self.required_udp_ports,
+            _ => return,
+        };
+        field.extend(literals(parameter));
+    }
+}
+
+// Returns the positional literal arguments of a call
+fn literals(parameter: &Statement) -> Vec<String> {
+    parameter
+        .children()
+        .iter()
+        .filter(|x| matches!(x.kind(), StatementKind::Primitive))
+        .filter_map(|x| NaslValue::try_from(x.as_token()).ok())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Extracts the metadata of a script without executing it
+///
+/// Returns the first syntax error of the script. See [`ScriptMetadata`] for the limitations.
+///
+/// # Examples
+/// ```
+/// use scannerlib::nasl::syntax::extract_metadata;
+///
+/// let code = r#"
+/// if (description) {
+///   script_oid("1.2.3");
+///   script_dependencies("a.nasl", "b.nasl");
+///   exit(0);
+/// }
+/// "#;
+/// let metadata = extract_metadata(code).unwrap();
+/// assert_eq!(metadata.oid.as_deref(), Some("1.2.3"));
+/// assert_eq!(metadata.dependencies, ["a.nasl", "b.nasl"]);
+/// ```
+pub fn extract_metadata(code: &str) -> Result<ScriptMetadata, SyntaxError> {
+    let mut metadata = ScriptMetadata::default();
+    for statement in parse(code) {
+        statement?.walk(&mut |x| metadata.add(x));
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_metadata;
+
+    #[test]
+    fn extract() {
+        let code = r#"
+        if (description) {
+          script_oid("1.3.6.1.4.1.25623.1.0.1");
+          script_dependencies("a.nasl");
+          script_dependencies("b.nasl", 'c.nasl');
+          script_mandatory_keys("Host/runs_unixoide", re: "Host/runs_unixoide=1");
+          script_require_keys("ssh/login");
+          script_exclude_keys("Settings/disable_cgi_scanning");
+          script_require_ports("Services/www", 80);
+          script_require_udp_ports(161);
+          exit(0);
+        }
+        script_dependencies(computed + ".nasl");
+        "#;
+        let metadata = extract_metadata(code).unwrap();
+        assert_eq!(metadata.oid.as_deref(), Some("1.3.6.1.4.1.25623.1.0.1"));
+        assert_eq!(metadata.dependencies, ["a.nasl", "b.nasl", "c.nasl"]);
+        assert_eq!(metadata.mandatory_keys, ["Host/runs_unixoide"]);
+        assert_eq!(metadata.required_keys, ["ssh/login"]);
+        assert_eq!(metadata.excluded_keys, ["Settings/disable_cgi_scanning"]);
+        assert_eq!(metadata.required_ports, ["Services/www", "80"]);
+        assert_eq!(metadata.required_udp_ports, ["161"]);
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert!(extract_metadata("script_oid(\"1\"").is_err());
+    }
+}
@@ -5,21 +5,26 @@
 #![doc = include_str!("README.md")]
 mod cursor;
 mod error;
+mod fold;
 mod grouping_extension;
 mod keyword_extension;
 mod lexer;
 mod loader;
+mod metadata;
 mod naslvalue;
 mod operation;
 mod prefix_extension;
 mod statement;
 mod token;
+mod unparse;
 mod variable_extension;
 
 pub use crate::storage::item::ACT;
 pub use error::{ErrorKind, SyntaxError};
+pub use fold::fold_constants;
 pub use lexer::Lexer;
 pub use loader::*;
+pub use metadata::{extract_metadata, ScriptMetadata};
 pub use naslvalue::*;
 pub use statement::*;
 pub use token::Base as NumberBase;
@@ -27,6 +32,8 @@ pub use token::Category as TokenCategory;
 pub use token::IdentifierType;
 pub use token::Token;
 pub use token::Tokenizer;
+pub use token::{unescape, InvalidEscape};
+pub use unparse::unparse;
 
 /// Parses given code and returns found Statements and Errors
 ///
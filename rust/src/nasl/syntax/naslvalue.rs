@@ -70,6 +70,35 @@ impl NaslValue {
     }
 }
 
+/// Converts a NaslValue into JSON, e.g. to report the data of a result in a structured way.
+///
+/// Data is converted into a string by mapping each byte to the char of the same code point, as
+/// it is done when displaying it. Return values are converted to the returned value and attack
+/// categories to their name. Control flow values like `exit` or `break` do not contain data and
+/// are converted to null.
+pub fn nasl_value_to_json(value: &NaslValue) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        NaslValue::String(x) => Value::String(x.clone()),
+        NaslValue::Data(x) => Value::String(x.iter().map(|x| *x as char).collect()),
+        NaslValue::Number(x) => Value::Number((*x).into()),
+        NaslValue::Boolean(x) => Value::Bool(*x),
+        NaslValue::Array(x) | NaslValue::Fork(x) => {
+            Value::Array(x.iter().map(nasl_value_to_json).collect())
+        }
+        NaslValue::Dict(x) => Value::Object(
+            x.iter()
+                .map(|(k, v)| (k.clone(), nasl_value_to_json(v)))
+                .collect(),
+        ),
+        NaslValue::Return(x) => nasl_value_to_json(x),
+        NaslValue::AttackCategory(x) => Value::String(IdentifierType::ACT(*x).to_string()),
+        NaslValue::Null | NaslValue::Continue | NaslValue::Break | NaslValue::Exit(_) => {
+            Value::Null
+        }
+    }
+}
+
 impl PartialOrd for NaslValue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -269,9 +298,9 @@ impl TryFrom<&Token> for NaslValue {
 
     fn try_from(token: &Token) -> Result<Self, Self::Error> {
         match token.category() {
-            TokenCategory::String(category) | TokenCategory::IPv4Address(category) => {
-                Ok(NaslValue::String(category.clone()))
-            }
+            TokenCategory::String(category)
+            | TokenCategory::IPv4Address(category)
+            | TokenCategory::Regex(category) => Ok(NaslValue::String(category.clone())),
             TokenCategory::Data(data) => Ok(NaslValue::Data(data.clone())),
             TokenCategory::Identifier(IdentifierType::Undefined(id)) => {
                 Ok(NaslValue::String(id.clone()))
@@ -316,3 +345,43 @@ impl From<Primitive> for NaslValue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::{nasl_value_to_json, NaslValue};
+    use crate::nasl::syntax::ACT;
+
+    #[test]
+    fn nested_array_to_json() {
+        let value = NaslValue::Array(vec![
+            NaslValue::Number(1),
+            NaslValue::Array(vec![NaslValue::String("a".into()), NaslValue::Null]),
+            NaslValue::Dict(HashMap::from([("b".to_owned(), NaslValue::Boolean(true))])),
+            NaslValue::AttackCategory(ACT::GatherInfo),
+        ]);
+        assert_eq!(
+            nasl_value_to_json(&value),
+            json!([1, ["a", null], {"b": true}, "ACT_GATHER_INFO"])
+        );
+    }
+
+    #[test]
+    fn data_to_json() {
+        let value = NaslValue::Data(vec![b'a', 0, 0xff]);
+        assert_eq!(nasl_value_to_json(&value), json!("a\u{0}\u{ff}"));
+    }
+
+    #[test]
+    fn control_flow_to_json() {
+        assert_eq!(nasl_value_to_json(&NaslValue::Exit(1)), json!(null));
+        assert_eq!(nasl_value_to_json(&NaslValue::Break), json!(null));
+        assert_eq!(
+            nasl_value_to_json(&NaslValue::Return(Box::new(NaslValue::Number(2)))),
+            json!(2)
+        );
+    }
+}
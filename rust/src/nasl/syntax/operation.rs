@@ -58,6 +58,7 @@ impl Operation {
             | Category::GreaterEqual
             | Category::LessEqual
             | Category::X
+            | Category::QuestionMark
             | Category::StarStar => Some(Operation::Operator(token.category().clone())),
             Category::Equal
             | Category::MinusEqual
@@ -74,6 +75,7 @@ impl Operation {
             | Category::MinusMinus => Some(Operation::Assign(token.category().clone())),
             Category::String(_)
             | Category::Data(_)
+            | Category::Regex(_)
             | Category::Number(_)
             | Category::IPv4Address(_) => Some(Operation::Primitive),
             Category::LeftParen
@@ -26,10 +26,15 @@ pub(crate) trait Prefix {
     ) -> Result<(End, Statement), SyntaxError>;
 }
 
+/// Binding power of the prefix operators `+`, `-`, `~` and `!`.
+pub(crate) const PREFIX_BINDING_POWER: u8 = 21;
+
 /// Is used to verify operations.
 fn prefix_binding_power(token: &Token) -> Result<u8, SyntaxError> {
     match token.category() {
-        Category::Plus | Category::Minus | Category::Tilde | Category::Bang => Ok(21),
+        Category::Plus | Category::Minus | Category::Tilde | Category::Bang => {
+            Ok(PREFIX_BINDING_POWER)
+        }
         _ => Err(unexpected_token!(token.clone())),
     }
 }
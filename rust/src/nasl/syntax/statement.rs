@@ -5,7 +5,7 @@
 use core::fmt;
 use std::ops::Range;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "serde_support"))]
 use serde::{Deserialize, Serialize};
 
 use crate::unexpected_statement;
@@ -14,7 +14,7 @@ use super::{SyntaxError, Token, TokenCategory};
 
 /// Specifies the order of assignment
 #[derive(Clone, Debug, PartialEq, Eq)]
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub enum AssignOrder {
     /// Assign first than return
     AssignReturn,
@@ -24,7 +24,7 @@ pub enum AssignOrder {
 
 /// Is a executable step.
 #[derive(Clone, Debug, PartialEq, Eq)]
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub enum StatementKind {
     /// Either a Number, String, Boolean or Null
     Primitive,
@@ -57,6 +57,8 @@ pub enum StatementKind {
     /// An Operator (e.g. +, -, *)
     Operator(TokenCategory, Vec<Statement>),
     /// If statement, containing a condition, expression to be executed when the condition is true and an optional else expression
+    ///
+    /// The conditional operator `cond ? a : b` is parsed to an If statement as well, its else token is `:`.
     If(
         Box<Statement>,
         Box<Statement>,
@@ -94,7 +96,7 @@ pub enum StatementKind {
 /// start returns a token of the beginning of that statement while end contains
 /// the end of the statement. So as an example of the statement:
 /// 'my_function(1);' start will point to 'my_function' and end to ';'.
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub struct Statement {
     start: Token,
     end: Option<Token>,
@@ -117,6 +119,16 @@ impl Statement {
         &self.start
     }
 
+    /// Returns the value of a number literal
+    ///
+    /// Returns None when the statement is not a primitive number.
+    pub fn as_number(&self) -> Option<i64> {
+        match (self.kind(), self.start.category()) {
+            (StatementKind::Primitive, TokenCategory::Number(x)) => Some(*x),
+            _ => None,
+        }
+    }
+
     /// Retrieves the stored token in a Statement.
     ///
     /// If a Statement contains multiple Statements (e.g. Declare) than just the first one is returned.
@@ -344,6 +356,109 @@ impl Statement {
         &self.start
     }
 
+    /// Calls the visitor on this statement and all statements within it in pre-order
+    ///
+    /// # Examples
+    /// ```
+    /// use scannerlib::nasl::syntax::{parse, StatementKind};
+    ///
+    /// let mut calls = vec![];
+    /// for statement in parse("a = f(g(1)); if (a) h();") {
+    ///     statement.unwrap().walk(&mut |s| {
+    ///         if let StatementKind::Call(_) = s.kind() {
+    ///             calls.push(s.as_token().category().to_string());
+    ///         }
+    ///     });
+    /// }
+    /// assert_eq!(calls, ["f", "g", "h"]);
+    /// ```
+    pub fn walk(&self, visitor: &mut dyn FnMut(&Statement)) {
+        visitor(self);
+        for child in self.child_statements() {
+            child.walk(visitor);
+        }
+    }
+
+    /// Calls the visitor on this statement and all statements within it in pre-order
+    ///
+    /// The children of a statement are visited after the visitor was called on it, so changes
+    /// made by the visitor are visited as well.
+    pub fn walk_mut(&mut self, visitor: &mut dyn FnMut(&mut Statement)) {
+        visitor(self);
+        for child in self.child_statements_mut() {
+            child.walk_mut(visitor);
+        }
+    }
+
+    // Returns the direct children in the order they appear in the code
+    fn child_statements(&self) -> Vec<&Statement> {
+        match &self.kind {
+            StatementKind::Primitive
+            | StatementKind::AttackCategory
+            | StatementKind::Variable
+            | StatementKind::NoOp
+            | StatementKind::Break
+            | StatementKind::Continue
+            | StatementKind::Array(None)
+            | StatementKind::EoF => vec![],
+            StatementKind::NamedParameter(x)
+            | StatementKind::Exit(x)
+            | StatementKind::Return(x)
+            | StatementKind::Include(x)
+            | StatementKind::Call(x)
+            | StatementKind::Array(Some(x)) => vec![&**x],
+            StatementKind::Block(x)
+            | StatementKind::Operator(_, x)
+            | StatementKind::Parameter(x)
+            | StatementKind::Declare(x) => x.iter().collect(),
+            StatementKind::While(x, y)
+            | StatementKind::Repeat(x, y)
+            | StatementKind::Assign(_, _, x, y)
+            | StatementKind::ForEach(_, x, y)
+            | StatementKind::FunctionDeclaration(_, x, y) => vec![&**x, &**y],
+            StatementKind::If(r, x, _, y) => {
+                let mut results: Vec<&Statement> = vec![&**r, &**x];
+                results.extend(y.as_deref());
+                results
+            }
+            StatementKind::For(r, x, y, z) => vec![&**r, &**x, &**y, &**z],
+        }
+    }
+
+    fn child_statements_mut(&mut self) -> Vec<&mut Statement> {
+        match &mut self.kind {
+            StatementKind::Primitive
+            | StatementKind::AttackCategory
+            | StatementKind::Variable
+            | StatementKind::NoOp
+            | StatementKind::Break
+            | StatementKind::Continue
+            | StatementKind::Array(None)
+            | StatementKind::EoF => vec![],
+            StatementKind::NamedParameter(x)
+            | StatementKind::Exit(x)
+            | StatementKind::Return(x)
+            | StatementKind::Include(x)
+            | StatementKind::Call(x)
+            | StatementKind::Array(Some(x)) => vec![&mut **x],
+            StatementKind::Block(x)
+            | StatementKind::Operator(_, x)
+            | StatementKind::Parameter(x)
+            | StatementKind::Declare(x) => x.iter_mut().collect(),
+            StatementKind::While(x, y)
+            | StatementKind::Repeat(x, y)
+            | StatementKind::Assign(_, _, x, y)
+            | StatementKind::ForEach(_, x, y)
+            | StatementKind::FunctionDeclaration(_, x, y) => vec![&mut **x, &mut **y],
+            StatementKind::If(r, x, _, y) => {
+                let mut results: Vec<&mut Statement> = vec![&mut **r, &mut **x];
+                results.extend(y.as_deref_mut());
+                results
+            }
+            StatementKind::For(r, x, y, z) => vec![&mut **r, &mut **x, &mut **y, &mut **z],
+        }
+    }
+
     /// Returns self if it is a returnable or an SyntaxError otherwise
     pub fn as_returnable_or_err(self) -> Result<Self, SyntaxError> {
         if self.kind().is_returnable() {
@@ -386,6 +501,66 @@ impl Statement {
     pub(crate) fn set_end(&mut self, cat: Token) {
         self.end = Some(cat)
     }
+
+    /// Replaces the statement by a primitive of the given category while keeping its position
+    pub(crate) fn into_primitive(self, category: TokenCategory) -> Self {
+        Self {
+            kind: StatementKind::Primitive,
+            start: Token {
+                category,
+                ..self.start
+            },
+            end: self.end,
+        }
+    }
+
+    /// Applies f on each direct child statement
+    pub(crate) fn map_children<F>(self, f: &mut F) -> Self
+    where
+        F: FnMut(Statement) -> Statement,
+    {
+        let mut boxed = |x: Box<Statement>| Box::new(f(*x));
+        let kind = match self.kind {
+            kind @ (StatementKind::Primitive
+            | StatementKind::AttackCategory
+            | StatementKind::Variable
+            | StatementKind::NoOp
+            | StatementKind::Break
+            | StatementKind::Continue
+            | StatementKind::Array(None)
+            | StatementKind::EoF) => kind,
+            StatementKind::Array(Some(x)) => StatementKind::Array(Some(boxed(x))),
+            StatementKind::Call(x) => StatementKind::Call(boxed(x)),
+            StatementKind::Exit(x) => StatementKind::Exit(boxed(x)),
+            StatementKind::Return(x) => StatementKind::Return(boxed(x)),
+            StatementKind::Include(x) => StatementKind::Include(boxed(x)),
+            StatementKind::NamedParameter(x) => StatementKind::NamedParameter(boxed(x)),
+            StatementKind::Declare(x) => {
+                StatementKind::Declare(x.into_iter().map(&mut *f).collect())
+            }
+            StatementKind::Parameter(x) => {
+                StatementKind::Parameter(x.into_iter().map(&mut *f).collect())
+            }
+            StatementKind::Block(x) => StatementKind::Block(x.into_iter().map(&mut *f).collect()),
+            StatementKind::Operator(c, x) => {
+                StatementKind::Operator(c, x.into_iter().map(&mut *f).collect())
+            }
+            StatementKind::Assign(c, o, x, y) => StatementKind::Assign(c, o, boxed(x), boxed(y)),
+            StatementKind::If(c, x, t, y) => {
+                StatementKind::If(boxed(c), boxed(x), t, y.map(&mut boxed))
+            }
+            StatementKind::For(a, b, c, d) => {
+                StatementKind::For(boxed(a), boxed(b), boxed(c), boxed(d))
+            }
+            StatementKind::While(x, y) => StatementKind::While(boxed(x), boxed(y)),
+            StatementKind::Repeat(x, y) => StatementKind::Repeat(boxed(x), boxed(y)),
+            StatementKind::ForEach(t, x, y) => StatementKind::ForEach(t, boxed(x), boxed(y)),
+            StatementKind::FunctionDeclaration(t, x, y) => {
+                StatementKind::FunctionDeclaration(t, boxed(x), boxed(y))
+            }
+        };
+        Self { kind, ..self }
+    }
 }
 
 impl std::fmt::Display for Statement {
@@ -474,6 +649,15 @@ impl StatementKind {
                 )
                 | StatementKind::Array(..)
                 | StatementKind::Operator(..)
+                | StatementKind::If(
+                    _,
+                    _,
+                    Some(Token {
+                        category: TokenCategory::DoublePoint,
+                        ..
+                    }),
+                    _
+                )
         )
     }
 }
@@ -482,7 +666,7 @@ impl StatementKind {
 mod tests {
     use insta::assert_snapshot;
 
-    use super::super::parse;
+    use super::super::{parse, TokenCategory};
 
     #[test]
     fn position() {
@@ -545,6 +729,63 @@ mod tests {
         assert_eq!(tests, expected.len());
     }
 
+    #[test]
+    fn as_number() {
+        let number = |code: &str| parse(code).next().unwrap().unwrap().as_number();
+        assert_eq!(number("10;"), Some(10));
+        assert_eq!(number("0x10;"), Some(16));
+        assert_eq!(number("010;"), Some(8));
+        assert_eq!(number("0b10;"), Some(2));
+        assert_eq!(number("'10';"), None);
+        assert_eq!(number("a;"), None);
+    }
+
+    #[test]
+    fn walk() {
+        let code = r#"
+        function f(a) { return g(a) + 1; }
+        if (f(1)) display(f(2)); else exit(h());
+        for (i = 0; i < max(); i++) { foreach x(list()) log(x); }
+        "#;
+        let mut calls = vec![];
+        for stmt in parse(code) {
+            stmt.unwrap().walk(&mut |s| {
+                if matches!(s.kind(), super::StatementKind::Call(_)) {
+                    calls.push(s.as_token().category().to_string());
+                }
+            });
+        }
+        assert_eq!(calls, ["g", "f", "display", "f", "h", "max", "list", "log"]);
+    }
+
+    #[test]
+    fn walk_mut() {
+        let mut stmt = parse("a = b + c(d);").next().unwrap().unwrap();
+        stmt.walk_mut(&mut |s| {
+            if matches!(s.kind(), super::StatementKind::Variable) {
+                *s = s.clone().into_primitive(TokenCategory::Number(1));
+            }
+        });
+        let mut variables = 0;
+        stmt.walk(&mut |s| {
+            if matches!(s.kind(), super::StatementKind::Variable) {
+                variables += 1;
+            }
+        });
+        assert_eq!(variables, 0);
+    }
+
+    #[test]
+    fn serialize_to_json() {
+        let stmt = parse("if (x) y();").next().unwrap().unwrap();
+        let json = serde_json::to_value(&stmt).unwrap();
+        let kind = &json["kind"]["If"];
+        assert!(kind.is_array(), "{json}");
+        assert!(kind[1]["kind"]["Call"].is_object(), "{json}");
+        assert_eq!(json["start"]["line_column"], serde_json::json!([1, 1]));
+        assert_eq!(json["start"]["position"], serde_json::json!([0, 2]));
+    }
+
     #[track_caller]
     fn test_statement(name: &str, code: &str) {
         insta::with_settings!({ prepend_module_to_snapshot => false }, {
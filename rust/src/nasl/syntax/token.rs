@@ -6,15 +6,16 @@
 use std::fmt::Display;
 use std::ops::Range;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "serde_support"))]
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use super::cursor::Cursor;
 use crate::storage::item::ACT;
 
 /// Identifies if number is base10, base 8, hex or binary
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub enum Base {
     /// Base 2: contains 01 is defined by 0b e.g.: `0b010101`
     Binary,
@@ -64,11 +65,15 @@ impl Base {
 
 /// Is used to identify which Category type is unclosed
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub enum UnclosedCategory {
     /// Is a unclosed String.
     String,
     Data,
+    /// Is a unclosed regular expression.
+    Regex,
+    /// Is a unclosed block comment.
+    Comment,
 }
 
 macro_rules! make_keyword_matcher {
@@ -126,7 +131,7 @@ impl Display for IdentifierType {
 
 /// Unless Dynamic those are reserved words that cannot be reused otherwise.
 #[derive(Clone, Debug, PartialEq, Eq)]
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub enum IdentifierType {
     /// function declaration
     Function,
@@ -207,7 +212,7 @@ make_keyword_matcher! {
 
 /// Is used to identify a Token
 #[derive(Clone, Debug, PartialEq, Eq)]
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub enum Category {
     /// `(`
     LeftParen,
@@ -233,6 +238,8 @@ pub enum Category {
     Semicolon,
     /// `:`
     DoublePoint,
+    /// `?`
+    QuestionMark,
     /// `~`
     Tilde,
     /// `^`
@@ -315,15 +322,24 @@ pub enum Category {
     /// Data can be over multiple lines and are escaped (`a = "a\";` is valid).
     /// Unlike string the data types are stored in bytes.
     Data(Vec<u8>),
+    /// A regular expression literal (`/.../`)
+    ///
+    /// It is only recognized on the right side of `=~` and `!~`; escaped slashes (`\/`) are
+    /// unescaped, all other escape sequences are kept for the regex engine.
+    Regex(String),
     /// A Number can be either binary (0b), octal (0), base10 (1-9) or hex (0x)
     Number(i64),
-    /// We currently just support 127.0.0.1 notation
+    /// We currently just support 127.0.0.1 notation, optionally followed by a CIDR prefix length
+    /// (e.g. 127.0.0.0/8)
     IPv4Address(String),
-    /// Wrongfully identified as IpV4
+    /// Wrongfully identified as IpV4, e.g. because of an octet above 255
     IllegalIPv4Address,
     /// An illegal Number e.g. 0b2
     IllegalNumber(Base),
     /// A comment starts with # and should be ignored
+    ///
+    /// Additionally to the traditional line comments block comments (`/* ... */`) are supported,
+    /// they can span multiple lines and may be nested.
     Comment,
     /// Identifier are literals that are not strings and don't start with a number
     Identifier(IdentifierType),
@@ -335,57 +351,69 @@ pub enum Category {
     UnknownSymbol,
 }
 
+impl Category {
+    /// Returns the NASL source text of a punctuation or operator category
+    ///
+    /// Returns None for categories without a fixed text, e.g. literals or identifiers.
+    pub fn as_str(&self) -> Option<&'static str> {
+        Some(match self {
+            Category::LeftParen => "(",
+            Category::RightParen => ")",
+            Category::LeftBrace => "[",
+            Category::RightBrace => "]",
+            Category::LeftCurlyBracket => "{",
+            Category::RightCurlyBracket => "}",
+            Category::Comma => ",",
+            Category::Dot => ".",
+            Category::Percent => "%",
+            Category::PercentEqual => "%=",
+            Category::Semicolon => ";",
+            Category::DoublePoint => ":",
+            Category::QuestionMark => "?",
+            Category::Tilde => "~",
+            Category::Caret => "^",
+            Category::Ampersand => "&",
+            Category::AmpersandAmpersand => "&&",
+            Category::Pipe => "|",
+            Category::PipePipe => "||",
+            Category::Bang => "!",
+            Category::BangEqual => "!=",
+            Category::BangTilde => "!~",
+            Category::Equal => "=",
+            Category::EqualEqual => "==",
+            Category::EqualTilde => "=~",
+            Category::Greater => ">",
+            Category::GreaterGreater => ">>",
+            Category::GreaterEqual => ">=",
+            Category::GreaterLess => "><",
+            Category::Less => "<",
+            Category::LessLess => "<<",
+            Category::LessEqual => "<=",
+            Category::Minus => "-",
+            Category::MinusMinus => "--",
+            Category::MinusEqual => "-=",
+            Category::Plus => "+",
+            Category::PlusEqual => "+=",
+            Category::PlusPlus => "++",
+            Category::Slash => "/",
+            Category::SlashEqual => "/=",
+            Category::Star => "*",
+            Category::StarStar => "**",
+            Category::StarEqual => "*=",
+            Category::GreaterGreaterGreater => ">>>",
+            Category::GreaterGreaterEqual => ">>=",
+            Category::LessLessEqual => "<<=",
+            Category::GreaterBangLess => ">!<",
+            Category::GreaterGreaterGreaterEqual => ">>>=",
+            Category::X => "X",
+            _ => return None,
+        })
+    }
+}
+
 impl Display for Category {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Category::LeftParen => write!(f, "("),
-            Category::RightParen => write!(f, ")"),
-            Category::LeftBrace => write!(f, "["),
-            Category::RightBrace => write!(f, "]"),
-            Category::LeftCurlyBracket => write!(f, "{{"),
-            Category::RightCurlyBracket => write!(f, "}}"),
-            Category::Comma => write!(f, ","),
-            Category::Dot => write!(f, "."),
-            Category::Percent => write!(f, "%"),
-            Category::PercentEqual => write!(f, "%="),
-            Category::Semicolon => write!(f, ";"),
-            Category::DoublePoint => write!(f, ":"),
-            Category::Tilde => write!(f, "~"),
-            Category::Caret => write!(f, "^"),
-            Category::Ampersand => write!(f, "&"),
-            Category::AmpersandAmpersand => write!(f, "&&"),
-            Category::Pipe => write!(f, "|"),
-            Category::PipePipe => write!(f, "||"),
-            Category::Bang => write!(f, "!"),
-            Category::BangEqual => write!(f, "!="),
-            Category::BangTilde => write!(f, "!~"),
-            Category::Equal => write!(f, "="),
-            Category::EqualEqual => write!(f, "=="),
-            Category::EqualTilde => write!(f, "=~"),
-            Category::Greater => write!(f, ">"),
-            Category::GreaterGreater => write!(f, ">>"),
-            Category::GreaterEqual => write!(f, ">="),
-            Category::GreaterLess => write!(f, "><"),
-            Category::Less => write!(f, "<"),
-            Category::LessLess => write!(f, "<<"),
-            Category::LessEqual => write!(f, "<="),
-            Category::Minus => write!(f, "-"),
-            Category::MinusMinus => write!(f, "--"),
-            Category::MinusEqual => write!(f, "-="),
-            Category::Plus => write!(f, "+"),
-            Category::PlusEqual => write!(f, "+="),
-            Category::PlusPlus => write!(f, "++"),
-            Category::Slash => write!(f, "/"),
-            Category::SlashEqual => write!(f, "/="),
-            Category::Star => write!(f, "*"),
-            Category::StarStar => write!(f, "**"),
-            Category::StarEqual => write!(f, "*="),
-            Category::GreaterGreaterGreater => write!(f, ">>>"),
-            Category::GreaterGreaterEqual => write!(f, ">>="),
-            Category::LessLessEqual => write!(f, "<<="),
-            Category::GreaterBangLess => write!(f, ">!<"),
-            Category::GreaterGreaterGreaterEqual => write!(f, ">>>="),
-            Category::X => write!(f, "X"),
             Category::String(x) => write!(f, "\"{x}\""),
             Category::Number(x) => write!(f, "{x}"),
             Category::IPv4Address(x) => write!(f, "{x}"),
@@ -397,13 +425,15 @@ impl Display for Category {
             Category::UnknownBase => write!(f, "UnknownBase"),
             Category::UnknownSymbol => write!(f, "UnknownSymbol"),
             Category::Data(x) => write!(f, "{x:?}"),
+            Category::Regex(x) => write!(f, "/{}/", x.replace('/', r"\/")),
+            operator => f.write_str(operator.as_str().unwrap_or_default()),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 /// Contains the TokenType as well as the position.
-#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(any(test, feature = "serde_support"), derive(Serialize, Deserialize))]
 pub struct Token {
     /// The category or kind of a token
     pub category: Category,
@@ -470,12 +500,78 @@ impl Token {
     }
 }
 
+/// Is returned by [unescape] on an unknown escape sequence.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("invalid escape sequence {0}")]
+pub struct InvalidEscape(pub String);
+
+/// Decodes the escape sequences of a data literal (`'...'`).
+///
+/// Supported are `\n`, `\t`, `\r`, `\\`, `\'`, `\"` and `\xHH`. Strings (`"..."`) are not
+/// escapable in NASL and therefore must not be decoded.
+///
+/// Unlike the tokenizer, which keeps unknown escape sequences as they are, this returns an error
+/// on them.
+pub fn unescape(raw: &str) -> Result<Vec<u8>, InvalidEscape> {
+    decode_escapes(raw, true)
+}
+
+fn decode_escapes(raw: &str, strict: bool) -> Result<Vec<u8>, InvalidEscape> {
+    let mut result = Vec::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        let escaped = chars.next();
+        let decoded = match escaped {
+            Some('n') => Some(b'\n'),
+            Some('t') => Some(b'\t'),
+            Some('r') => Some(b'\r'),
+            Some('\\') => Some(b'\\'),
+            Some('\'') => Some(b'\''),
+            Some('"') => Some(b'"'),
+            Some('x') => {
+                let hex = chars.as_str().get(..2).unwrap_or_default();
+                if hex.len() == 2 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    chars.nth(1);
+                    u8::from_str_radix(hex, 16).ok()
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        match (decoded, escaped) {
+            (Some(x), _) => result.push(x),
+            (None, escaped) if strict => {
+                return Err(InvalidEscape(format!(
+                    "\\{}",
+                    escaped.map(String::from).unwrap_or_default()
+                )))
+            }
+            (None, escaped) => {
+                result.push(b'\\');
+                if let Some(escaped) = escaped {
+                    result.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
 /// Tokenizer uses a cursor to create tokens
 #[derive(Clone)]
 pub struct Tokenizer<'a> {
     // Is used to lookup keywords
     code: &'a str,
     cursor: Cursor<'a>,
+    // a slash after =~ or !~ starts a regex literal instead of a division
+    expects_regex: bool,
+    // token already read by peek, None when nothing is peeked yet
+    peeked: Option<Option<Token>>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -484,9 +580,22 @@ impl<'a> Tokenizer<'a> {
         Tokenizer {
             code,
             cursor: Cursor::new(code),
+            expects_regex: false,
+            peeked: None,
         }
     }
 
+    /// Returns the next token without advancing the tokenizer
+    ///
+    /// The following call of `next` returns the same token.
+    pub fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            let token = self.read_token();
+            self.peeked = Some(token);
+        }
+        self.peeked.as_ref().and_then(|x| x.as_ref())
+    }
+
     /// Returns a reference of a substring within code at given range
     pub fn lookup(&self, range: Range<usize>) -> &'a str {
         &self.code[range]
@@ -569,8 +678,10 @@ impl<'a> Tokenizer<'a> {
     fn tokenize_string(&mut self) -> Category {
         //'"' => self.tokenize_string(StringCategory::Unquotable, |c| c != '"'),
         let start = self.cursor.len_consumed();
+        let opening = self.cursor.clone();
         self.cursor.skip_while(|c| c != '"');
         if self.cursor.is_eof() {
+            self.resume_at_line_end(opening);
             Category::Unclosed(UnclosedCategory::String)
         } else {
             let result = self.code[Range {
@@ -583,10 +694,20 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    // Moves the cursor back to the given cursor and on to the end of its line.
+    //
+    // An unclosed string swallows the rest of the code, to be able to report the errors after it
+    // tokenizing resumes at the end of the line the string started on.
+    fn resume_at_line_end(&mut self, opening: Cursor<'a>) {
+        self.cursor = opening;
+        self.cursor.skip_while(|c| c != '\n');
+    }
+
     // Skips initial and ending string identifier ' || " and verifies that a string is closed
     fn tokenize_data(&mut self) -> Category {
         // we don't want the lookup to contain "
         let start = self.cursor.len_consumed();
+        let opening = self.cursor.clone();
         let mut back_slash = false;
         self.cursor.skip_while(|c| {
             if !back_slash && c == '\'' {
@@ -597,23 +718,65 @@ impl<'a> Tokenizer<'a> {
             }
         });
         if self.cursor.is_eof() {
+            self.resume_at_line_end(opening);
             Category::Unclosed(UnclosedCategory::Data)
         } else {
-            let mut raw_str = self.code[Range {
+            let raw_str = self.lookup(Range {
+                start,
+                end: self.cursor.len_consumed(),
+            });
+            self.cursor.advance();
+            Category::Data(decode_escapes(raw_str, false).unwrap_or_default())
+        }
+    }
+    // Skips a possibly nested block comment; the initial / is already consumed
+    fn tokenize_block_comment(&mut self) -> Category {
+        self.cursor.advance();
+        let mut depth = 1;
+        while let Some(c) = self.cursor.advance() {
+            match (c, self.cursor.peek(0)) {
+                ('/', '*') => {
+                    self.cursor.advance();
+                    depth += 1;
+                }
+                ('*', '/') => {
+                    self.cursor.advance();
+                    depth -= 1;
+                    if depth == 0 {
+                        return Category::Comment;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Category::Unclosed(UnclosedCategory::Comment)
+    }
+
+    // Skips the initial and ending / of a regex literal and verifies that it is closed
+    fn tokenize_regex(&mut self) -> Category {
+        let start = self.cursor.len_consumed();
+        let mut back_slash = false;
+        self.cursor.skip_while(|c| {
+            if !back_slash && c == '/' {
+                false
+            } else {
+                back_slash = !back_slash && c == '\\';
+                true
+            }
+        });
+        if self.cursor.is_eof() {
+            Category::Unclosed(UnclosedCategory::Regex)
+        } else {
+            let pattern = self.code[Range {
                 start,
                 end: self.cursor.len_consumed(),
             }]
-            .to_owned();
-            raw_str = raw_str.replace(r#"\""#, "\"");
-            raw_str = raw_str.replace(r#"\n"#, "\n");
-            raw_str = raw_str.replace(r"\\", "\\");
-            raw_str = raw_str.replace(r"\'", "'");
-            raw_str = raw_str.replace(r"\r", "\r");
-            raw_str = raw_str.replace(r"\t", "\t");
+            .replace(r"\/", "/");
             self.cursor.advance();
-            Category::Data(raw_str.as_bytes().to_vec())
+            Category::Regex(pattern)
         }
     }
+
     fn may_parse_ipv4(&mut self, base: Base, start: usize) -> Option<Category> {
         use Base::*;
         // IPv4Address start as Base10
@@ -638,6 +801,26 @@ impl<'a> Tokenizer<'a> {
                 } else {
                     return Some(Category::IllegalIPv4Address);
                 }
+                let address = self.lookup(Range {
+                    start,
+                    end: self.cursor.len_consumed(),
+                });
+                if !address.split('.').all(|octet| octet.parse::<u8>().is_ok()) {
+                    return Some(Category::IllegalIPv4Address);
+                }
+                // an optional CIDR prefix length like 192.168.0.0/24
+                if self.cursor.peek(0) == '/' && self.cursor.peek(1).is_ascii_digit() {
+                    self.cursor.advance();
+                    let prefix_start = self.cursor.len_consumed();
+                    self.cursor.skip_while(|c| c.is_ascii_digit());
+                    let prefix = self.lookup(Range {
+                        start: prefix_start,
+                        end: self.cursor.len_consumed(),
+                    });
+                    if !prefix.parse::<u8>().is_ok_and(|x| x <= 32) {
+                        return Some(Category::IllegalIPv4Address);
+                    }
+                }
                 return Some(Category::IPv4Address(
                     self.code[Range {
                         start,
@@ -755,6 +938,15 @@ impl Iterator for Tokenizer<'_> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(token) => token,
+            None => self.read_token(),
+        }
+    }
+}
+
+impl Tokenizer<'_> {
+    fn read_token(&mut self) -> Option<Token> {
         use Category::*;
         self.cursor.skip_while(|c| c.is_whitespace());
         let start = self.cursor.len_consumed();
@@ -776,9 +968,12 @@ impl Iterator for Tokenizer<'_> {
             '+' => two_symbol_token!(self.cursor, start, Plus, '+', PlusPlus, '=', PlusEqual),
             '%' => two_symbol_token!(self.cursor, start, Percent, '=', PercentEqual),
             ';' => Semicolon,
-            '/' => two_symbol_token!(self.cursor, start, Slash, '=', SlashEqual), /* self.tokenize_slash(start), */
+            '/' if self.cursor.peek(0) == '*' => self.tokenize_block_comment(),
+            '/' if self.expects_regex => self.tokenize_regex(),
+            '/' => two_symbol_token!(self.cursor, start, Slash, '=', SlashEqual),
             '*' => two_symbol_token!(self.cursor, start, Star, '*', StarStar, '=', StarEqual),
             ':' => DoublePoint,
+            '?' => QuestionMark,
             '~' => Tilde,
             '&' => two_symbol_token!(self.cursor, start, Ampersand, '&', AmpersandAmpersand),
             '|' => two_symbol_token!(self.cursor, start, Pipe, '|', PipePipe),
@@ -794,6 +989,9 @@ impl Iterator for Tokenizer<'_> {
             current if current.is_alphabetic() || current == '_' => self.tokenize_identifier(start),
             _ => UnknownSymbol,
         };
+        if category != Comment {
+            self.expects_regex = matches!(category, EqualTilde | BangTilde);
+        }
         let byte_position = (start, self.cursor.len_consumed());
         Some(Token {
             category,
@@ -971,13 +1169,234 @@ mod tests {
         );
     }
 
+    #[test]
+    fn data_escape_sequences() {
+        use super::{unescape, InvalidEscape};
+
+        assert_eq!(unescape(r"a\nb\tc"), Ok(b"a\nb\tc".to_vec()));
+        assert_eq!(unescape(r"\x41\x4a!"), Ok(b"AJ!".to_vec()));
+        assert_eq!(unescape(r"\\n"), Ok(br"\n".to_vec()));
+        assert_eq!(unescape(r"\q"), Err(InvalidEscape(r"\q".to_owned())));
+        assert_eq!(unescape(r"\x4"), Err(InvalidEscape(r"\x".to_owned())));
+        // the tokenizer keeps unknown escape sequences
+        verify_tokens!(r"'\x41\q'", [r"[65, 92, 113]"]);
+        // strings are never unescaped
+        verify_tokens!(r#""\x41""#, [r#""\x41""#]);
+    }
+
+    #[test]
+    fn block_comments() {
+        verify_tokens!("a /* comment */ = 1;", ["a", "Comment", "=", "1", ";"]);
+        verify_tokens!(
+            "a = 1;\n/*\n * multi\n * line\n */\nb;",
+            ["a", "=", "1", ";", "Comment", "b", ";"]
+        );
+        verify_tokens!(
+            "/* outer /* inner */ still outer */ a;",
+            ["Comment", "a", ";"]
+        );
+        verify_tokens!("a; /* unterminated", ["a", ";", "UnclosedComment"]);
+        verify_tokens!("a; /* /* */", ["a", ";", "UnclosedComment"]);
+    }
+
+    #[test]
+    fn regex_literal() {
+        verify_tokens!("a =~ /^Server:/;", ["a", "=~", "/^Server:/", ";"]);
+        verify_tokens!(
+            r"a !~ /^\/cgi-bin\// # comment",
+            ["a", "!~", r"/^\/cgi-bin\//", "Comment"]
+        );
+        let pattern = Tokenizer::new(r"a =~ /^\/cgi-bin\//").nth(2).unwrap();
+        assert_eq!(
+            pattern.category(),
+            &Category::Regex("^/cgi-bin/".to_owned())
+        );
+        // without a preceding =~ or !~ it is a division
+        verify_tokens!("a / b / c", ["a", "/", "b", "/", "c"]);
+        verify_tokens!("a =~ /unclosed", ["a", "=~", "UnclosedRegex"]);
+    }
+
     #[test]
     fn simplified_ipv4_address() {
         verify_tokens!("10.187.76.12", ["10.187.76.12",]);
     }
 
+    #[test]
+    fn ipv4_address_validation() {
+        verify_tokens!("255.255.255.0;", ["255.255.255.0", ";"]);
+        verify_tokens!("999.1.1.1;", ["IllegalIPv4Address", ";"]);
+        verify_tokens!("1.1.256.1;", ["IllegalIPv4Address", ";"]);
+        verify_tokens!("192.168.0.0/24;", ["192.168.0.0/24", ";"]);
+        verify_tokens!("192.168.0.0/33;", ["IllegalIPv4Address", ";"]);
+    }
+
     #[test]
     fn repeat_x_times() {
         verify_tokens!("x() x 10;", ["x", "(", ")", "X", "10", ";"]);
     }
+
+    #[test]
+    fn operator_round_trip() {
+        let operators = [
+            (Category::LeftParen, "("),
+            (Category::RightParen, ")"),
+            (Category::LeftBrace, "["),
+            (Category::RightBrace, "]"),
+            (Category::LeftCurlyBracket, "{"),
+            (Category::RightCurlyBracket, "}"),
+            (Category::Comma, ","),
+            (Category::Dot, "."),
+            (Category::Percent, "%"),
+            (Category::PercentEqual, "%="),
+            (Category::Semicolon, ";"),
+            (Category::DoublePoint, ":"),
+            (Category::QuestionMark, "?"),
+            (Category::Tilde, "~"),
+            (Category::Caret, "^"),
+            (Category::Ampersand, "&"),
+            (Category::AmpersandAmpersand, "&&"),
+            (Category::Pipe, "|"),
+            (Category::PipePipe, "||"),
+            (Category::Bang, "!"),
+            (Category::BangEqual, "!="),
+            (Category::BangTilde, "!~"),
+            (Category::Equal, "="),
+            (Category::EqualEqual, "=="),
+            (Category::EqualTilde, "=~"),
+            (Category::Greater, ">"),
+            (Category::GreaterGreater, ">>"),
+            (Category::GreaterEqual, ">="),
+            (Category::GreaterLess, "><"),
+            (Category::Less, "<"),
+            (Category::LessLess, "<<"),
+            (Category::LessEqual, "<="),
+            (Category::Minus, "-"),
+            (Category::MinusMinus, "--"),
+            (Category::MinusEqual, "-="),
+            (Category::Plus, "+"),
+            (Category::PlusEqual, "+="),
+            (Category::PlusPlus, "++"),
+            (Category::Slash, "/"),
+            (Category::SlashEqual, "/="),
+            (Category::Star, "*"),
+            (Category::StarStar, "**"),
+            (Category::StarEqual, "*="),
+            (Category::GreaterGreaterGreater, ">>>"),
+            (Category::GreaterGreaterEqual, ">>="),
+            (Category::LessLessEqual, "<<="),
+            (Category::GreaterBangLess, ">!<"),
+            (Category::GreaterGreaterGreaterEqual, ">>>="),
+            (Category::X, "X"),
+        ];
+        for (category, glyph) in operators {
+            assert_eq!(category.as_str(), Some(glyph));
+            assert_eq!(category.to_string(), glyph);
+            // X is only a category after a call, otherwise it is an identifier
+            if category != Category::X {
+                let token = Tokenizer::new(glyph).next().unwrap();
+                assert_eq!(token.category(), &category, "{glyph}");
+            }
+        }
+        assert_eq!(Category::Number(1).as_str(), None);
+        assert_eq!(Category::Comment.as_str(), None);
+    }
+
+    #[test]
+    fn peek() {
+        let mut tokenizer = Tokenizer::new("a = 1;");
+        let peeked = tokenizer.peek().cloned();
+        assert_eq!(
+            peeked.as_ref().map(|t| t.category()),
+            Some(&Category::Identifier(IdentifierType::Undefined(
+                "a".to_owned()
+            )))
+        );
+        // peeking again does not advance
+        assert_eq!(tokenizer.peek().cloned(), peeked);
+        assert_eq!(tokenizer.next(), peeked);
+        assert_eq!(
+            tokenizer.peek().map(|t| t.category()),
+            Some(&Category::Equal)
+        );
+        let rest = tokenizer
+            .map(|t| t.category().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(rest, ["=", "1", ";"]);
+    }
+
+    #[test]
+    fn peek_at_end() {
+        let mut tokenizer = Tokenizer::new(";");
+        assert_eq!(
+            tokenizer.next().map(|t| t.category().clone()),
+            Some(Category::Semicolon)
+        );
+        assert_eq!(tokenizer.peek(), None);
+        assert_eq!(tokenizer.next(), None);
+    }
+
+    #[test]
+    fn peek_regex() {
+        // the peeked token decides whether a slash starts a regex
+        let mut tokenizer = Tokenizer::new("a =~ /b/");
+        tokenizer.next();
+        assert_eq!(
+            tokenizer.peek().map(|t| t.category()),
+            Some(&Category::EqualTilde)
+        );
+        tokenizer.next();
+        assert_eq!(
+            tokenizer.peek().map(|t| t.category()),
+            Some(&Category::Regex("b".to_owned()))
+        );
+    }
+
+    #[test]
+    fn unclosed_string_resumes_on_next_line() {
+        verify_tokens!(
+            "a = \"unclosed;\nb = 'unclosed;\nc = 1;",
+            [
+                "a",
+                "=",
+                "UnclosedString",
+                "b",
+                "=",
+                "UnclosedData",
+                "c",
+                "=",
+                "1",
+                ";"
+            ]
+        );
+        let token = Tokenizer::new("a = 1;\nb = \"unclosed").nth(6).unwrap();
+        assert_eq!(
+            token.category(),
+            &Category::Unclosed(UnclosedCategory::String)
+        );
+        assert_eq!(token.line_column, (2, 5));
+        assert_eq!(token.position, (11, 20));
+    }
+
+    #[test]
+    fn number_bases() {
+        let as_number = |token: &Token| match token.category() {
+            Category::Number(x) => Some(*x),
+            _ => None,
+        };
+        let number = |code: &str| Tokenizer::new(code).next().and_then(|t| as_number(&t));
+        assert_eq!(number("42"), Some(42));
+        assert_eq!(number("0"), Some(0));
+        assert_eq!(number("0x1F"), Some(31));
+        assert_eq!(number("0xff"), Some(255));
+        assert_eq!(number("017"), Some(15));
+        assert_eq!(number("0b101"), Some(5));
+        // 8 is not an octal digit, the number ends before it
+        let tokens = Tokenizer::new("018").collect::<Vec<_>>();
+        assert_eq!(as_number(&tokens[0]), Some(1));
+        assert_eq!(as_number(&tokens[1]), Some(8));
+        assert_eq!(number("0b2"), None);
+        assert_eq!(number("0x"), None);
+        assert_eq!(number("0xFFFFFFFFFFFFFFFFF"), None);
+        assert_eq!(number("\"1\""), None);
+    }
 }
@@ -0,0 +1,311 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+//! Reconstructs NASL source code from parsed statements.
+use super::{
+    lexer::infix_binding_power,
+    operation::Operation,
+    prefix_extension::PREFIX_BINDING_POWER,
+    token::{Category, Token},
+    AssignOrder, Statement, StatementKind,
+};
+
+const INDENT: &str = "    ";
+
+/// Binding power of assignments and named parameters.
+const ASSIGN_BINDING_POWER: (u8, u8) = (2, 3);
+
+/// Returns NASL source code of the given statement.
+///
+/// The output is not necessarily identical to the originally parsed code, as
+/// e.g. comments, whitespace and redundant parentheses are lost, but parsing
+/// it again results in an equal statement apart from token positions.
+///
+/// # Examples
+/// Basic usage:
+///
+/// ```
+/// use scannerlib::nasl::syntax::{parse, unparse};
+/// let stmt = parse("a = (1 + 2) * 3;").next().unwrap().unwrap();
+/// assert_eq!(unparse(&stmt), "a = (1 + 2) * 3;");
+/// ```
+pub fn unparse(stmt: &Statement) -> String {
+    statement(stmt, 0)
+}
+
+fn statement(stmt: &Statement, depth: usize) -> String {
+    match stmt.kind() {
+        StatementKind::Block(stmts) => {
+            let mut result = "{\n".to_string();
+            for s in stmts {
+                result.push_str(&INDENT.repeat(depth + 1));
+                result.push_str(&statement(s, depth + 1));
+                result.push('\n');
+            }
+            result.push_str(&INDENT.repeat(depth));
+            result.push('}');
+            result
+        }
+        StatementKind::If(..) if is_ternary(stmt) => format!("{};", expression(stmt)),
+        StatementKind::If(cond, body, _, r#else) => {
+            let mut result = format!("if ({}) {}", expression(cond), statement(body, depth));
+            if let Some(r#else) = r#else {
+                result.push_str(" else ");
+                result.push_str(&statement(r#else, depth));
+            }
+            result
+        }
+        StatementKind::For(assignment, cond, update, body) => format!(
+            "for ({}; {}; {}) {}",
+            expression(assignment),
+            expression(cond),
+            expression(update),
+            statement(body, depth)
+        ),
+        StatementKind::While(cond, body) => {
+            format!("while ({}) {}", expression(cond), statement(body, depth))
+        }
+        StatementKind::Repeat(body, cond) => {
+            format!(
+                "repeat {} until {};",
+                statement(body, depth),
+                expression(cond)
+            )
+        }
+        StatementKind::ForEach(variable, array, body) => format!(
+            "foreach {} ({}) {}",
+            variable.category(),
+            expression(array),
+            statement(body, depth)
+        ),
+        StatementKind::FunctionDeclaration(id, params, body) => format!(
+            "function {}({}) {}",
+            id.category(),
+            list(params.children()),
+            statement(body, depth)
+        ),
+        StatementKind::Declare(variables) => {
+            format!("{} {};", stmt.start().category(), list(variables))
+        }
+        StatementKind::Return(value) => match value.kind() {
+            StatementKind::NoOp => "return;".to_string(),
+            _ => format!("return {};", expression(value)),
+        },
+        StatementKind::Exit(value) => format!("exit({});", expression(value)),
+        StatementKind::Include(value) => format!("include({});", expression(value)),
+        StatementKind::Break => "break;".to_string(),
+        StatementKind::Continue => "continue;".to_string(),
+        StatementKind::NoOp => ";".to_string(),
+        StatementKind::EoF => String::new(),
+        _ => format!("{};", expression(stmt)),
+    }
+}
+
+fn expression(stmt: &Statement) -> String {
+    let name = stmt.start().category();
+    match stmt.kind() {
+        StatementKind::Primitive => primitive(stmt.start()),
+        StatementKind::Variable | StatementKind::AttackCategory | StatementKind::Array(None) => {
+            name.to_string()
+        }
+        StatementKind::Array(Some(lookup)) => format!("{name}[{}]", expression(lookup)),
+        StatementKind::Call(params) => format!("{name}({})", list(params.children())),
+        StatementKind::Parameter(values) => format!("[{}]", list(values)),
+        StatementKind::NamedParameter(value) => {
+            format!("{name}: {}", operand(value, ASSIGN_BINDING_POWER.1))
+        }
+        StatementKind::Assign(category, order, lhs, rhs) => match (order, rhs.kind()) {
+            (AssignOrder::AssignReturn, StatementKind::NoOp) => {
+                format!("{category}{}", expression(lhs))
+            }
+            (AssignOrder::ReturnAssign, StatementKind::NoOp) => {
+                format!("{}{category}", expression(lhs))
+            }
+            _ => format!(
+                "{} {category} {}",
+                expression(lhs),
+                operand(rhs, ASSIGN_BINDING_POWER.1)
+            ),
+        },
+        StatementKind::Operator(category, args) => match &args[..] {
+            [value] => {
+                let value = operand(value, PREFIX_BINDING_POWER);
+                // prevents e.g. `- -a` to be tokenized as `--a`
+                if value.starts_with(['+', '-', '~', '!']) {
+                    format!("{category} {value}")
+                } else {
+                    format!("{category}{value}")
+                }
+            }
+            [lhs, rhs] => {
+                let (left, right) = binding_power(category);
+                format!("{} {category} {}", operand(lhs, left), operand(rhs, right))
+            }
+            _ => format!("{category}({})", list(args)),
+        },
+        StatementKind::If(cond, truthy, _, Some(falsy)) if is_ternary(stmt) => {
+            let (left, right) = binding_power(&Category::QuestionMark);
+            format!(
+                "{} ? {} : {}",
+                operand(cond, left + 1),
+                expression(truthy),
+                operand(falsy, right)
+            )
+        }
+        StatementKind::NoOp | StatementKind::EoF => String::new(),
+        _ => statement(stmt, 0),
+    }
+}
+
+/// Returns the expression and wraps it into parentheses when it binds weaker
+/// than the surrounding operation requires.
+fn operand(stmt: &Statement, min_binding_power: u8) -> String {
+    let result = expression(stmt);
+    if precedence(stmt) < min_binding_power {
+        format!("({result})")
+    } else {
+        result
+    }
+}
+
+/// Returns the left binding power of the outermost operation of a statement.
+fn precedence(stmt: &Statement) -> u8 {
+    match stmt.kind() {
+        StatementKind::Operator(category, args) if args.len() == 2 => binding_power(category).0,
+        StatementKind::Operator(..) => PREFIX_BINDING_POWER,
+        StatementKind::Assign(_, _, _, rhs) if !matches!(rhs.kind(), StatementKind::NoOp) => {
+            ASSIGN_BINDING_POWER.0
+        }
+        StatementKind::NamedParameter(_) => ASSIGN_BINDING_POWER.0,
+        StatementKind::If(..) if is_ternary(stmt) => binding_power(&Category::QuestionMark).0,
+        _ => u8::MAX,
+    }
+}
+
+/// Returns true when the statement is a conditional operator `cond ? a : b`.
+fn is_ternary(stmt: &Statement) -> bool {
+    matches!(
+        stmt.kind(),
+        StatementKind::If(_, _, Some(token), _) if token.category() == &Category::DoublePoint
+    )
+}
+
+fn binding_power(category: &Category) -> (u8, u8) {
+    infix_binding_power(&Operation::Operator(category.clone())).unwrap_or(ASSIGN_BINDING_POWER)
+}
+
+fn primitive(token: &Token) -> String {
+    match token.category() {
+        Category::Data(data) => {
+            let data = String::from_utf8_lossy(data)
+                .replace('\\', r"\\")
+                .replace('\'', r"\'");
+            format!("'{data}'")
+        }
+        category => category.to_string(),
+    }
+}
+
+fn list(stmts: &[Statement]) -> String {
+    stmts.iter().map(expression).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse, Statement, StatementKind::*};
+    use super::unparse;
+
+    /// Describes a statement without token positions and tokens that are
+    /// irrelevant for the execution, like parentheses.
+    fn shape(stmt: &Statement) -> String {
+        let name = stmt.start().category();
+        let all = |stmts: &[Statement]| stmts.iter().map(shape).collect::<Vec<_>>().join(", ");
+        match stmt.kind() {
+            Primitive | Variable | AttackCategory => name.to_string(),
+            Array(lookup) => format!("Array({name}, {:?})", lookup.as_deref().map(shape)),
+            Call(params) => format!("Call({name}, {})", shape(params)),
+            NamedParameter(value) => format!("NamedParameter({name}, {})", shape(value)),
+            Declare(variables) => format!("Declare({name}, {})", all(variables)),
+            Exit(x) => format!("Exit({})", shape(x)),
+            Return(x) => format!("Return({})", shape(x)),
+            Include(x) => format!("Include({})", shape(x)),
+            Parameter(x) => format!("Parameter({})", all(x)),
+            Block(x) => format!("Block({})", all(x)),
+            Assign(c, o, l, r) => format!("Assign({c}, {o:?}, {}, {})", shape(l), shape(r)),
+            Operator(c, args) => format!("Operator({c}, {})", all(args)),
+            If(c, b, _, e) => format!(
+                "If({}, {}, {:?})",
+                shape(c),
+                shape(b),
+                e.as_deref().map(shape)
+            ),
+            For(a, c, u, b) => format!(
+                "For({}, {}, {}, {})",
+                shape(a),
+                shape(c),
+                shape(u),
+                shape(b)
+            ),
+            While(c, b) => format!("While({}, {})", shape(c), shape(b)),
+            Repeat(b, c) => format!("Repeat({}, {})", shape(b), shape(c)),
+            ForEach(v, a, b) => format!("ForEach({}, {}, {})", v.category(), shape(a), shape(b)),
+            FunctionDeclaration(id, p, b) => {
+                format!("Function({}, {}, {})", id.category(), shape(p), shape(b))
+            }
+            Break | Continue | NoOp | EoF => format!("{:?}", stmt.kind()),
+        }
+    }
+
+    fn shapes(code: &str) -> Vec<String> {
+        parse(code)
+            .map(|stmt| shape(&stmt.unwrap_or_else(|e| panic!("{code}: {e}"))))
+            .collect()
+    }
+
+    #[test]
+    fn reparse_to_equal_statements() {
+        let snippets = [
+            "a = 1 + 2 * 3;",
+            "b = (1 + 2) * 3 - (4 - 5);",
+            "c = -(1 + 2) ** 2 + (-1) ** 2 - -c;",
+            "d = a && (b || !c) == (e < f);",
+            "e = (b = 1) + 2;",
+            "g = [1, 2, [3, 'x']];",
+            "h = _FCT_ANON_ARGS[0] + a[b[1]];",
+            r#"display("value: ", 'data\'s \\ end', a[1], named: 2 + 3);"#,
+            "script_category(ACT_GATHER_INFO);",
+            "if (a == 1) b = 2; else { c(); d++; }",
+            "if (a) { if (b) c = 1; else c = 2; }",
+            "function f(x, y) { local_var z; z = x << y; return z; }",
+            "function g() { return; }",
+            "for (i = 0; i < 10; i++) { if (i % 2) continue; else break; }",
+            "for (;i < 1;) exit(0);",
+            "foreach item (make_list(1, 2)) display(item);",
+            "while (--n > 0) x += n * 2;",
+            "repeat { i++; } until (i > 3 || i == NULL);",
+            r#"include("foo.inc");"#,
+            "global_var a, b;",
+            "x = 0x10 | 1 & ~2 ^ 3;",
+            "y = a ? b : c ? d : e;",
+            "z = (a ? b : c) ? d + 1 : (e = 2);",
+            "display(a || b ? [1] : f(x: 1));",
+        ];
+        for code in snippets {
+            let unparsed = parse(code)
+                .map(|stmt| unparse(&stmt.unwrap()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            assert_eq!(shapes(code), shapes(&unparsed), "{code} -> {unparsed}");
+        }
+    }
+
+    #[test]
+    fn parentheses_only_when_needed() {
+        let unparse_first = |code| unparse(&parse(code).next().unwrap().unwrap());
+        assert_eq!(unparse_first("a = ((1 + 2)) * 3;"), "a = (1 + 2) * 3;");
+        assert_eq!(unparse_first("a = 1 + (2 * 3);"), "a = 1 + 2 * 3;");
+        assert_eq!(unparse_first("a = 1 - (2 - 3);"), "a = 1 - (2 - 3);");
+        assert_eq!(unparse_first("a = - -1;"), "a = - -1;");
+    }
+}
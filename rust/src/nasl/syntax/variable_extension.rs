@@ -4,7 +4,9 @@
 
 #[cfg(test)]
 mod test {
-    use super::super::{parse, AssignOrder, Statement, StatementKind, TokenCategory};
+    use super::super::{
+        parse, AssignOrder, IdentifierType, Statement, StatementKind, TokenCategory,
+    };
 
     use StatementKind::*;
 
@@ -37,6 +39,37 @@ mod test {
         }
     }
 
+    #[test]
+    fn subscripts() {
+        let lookup = |stmt: &Statement| match stmt.kind() {
+            Array(Some(lookup)) => lookup.start().category().clone(),
+            kind => panic!("expected subscript, got: {kind:?}"),
+        };
+        assert_eq!(lookup(&result("a[1];")), TokenCategory::Number(1));
+        assert_eq!(
+            lookup(&result("a[\"k\"];")),
+            TokenCategory::String("k".to_owned())
+        );
+
+        let re = result("x = a[i];");
+        match re.kind() {
+            Assign(TokenCategory::Equal, AssignOrder::AssignReturn, var, value) => {
+                assert_eq!(var.kind(), &Variable);
+                assert!(matches!(value.kind(), Array(Some(_))));
+            }
+            _ => panic!("{re} must be an assign statement"),
+        }
+
+        let re = result("a[\"k\"] = x;");
+        match re.kind() {
+            Assign(TokenCategory::Equal, AssignOrder::AssignReturn, arr, value) => {
+                assert_eq!(lookup(arr), TokenCategory::String("k".to_owned()));
+                assert_eq!(value.kind(), &Variable);
+            }
+            _ => panic!("{re} must be an assign statement"),
+        }
+    }
+
     #[test]
     fn anon_function_call() {
         assert!(matches!(result("a(1, 2, 3);").kind(), &Call(..)))
@@ -49,4 +82,25 @@ mod test {
             &Call(..)
         ));
     }
+
+    #[test]
+    fn mixed_function_call() {
+        let re = result("foo(a, name: b);");
+        let params = match re.kind() {
+            Call(params) => params.children(),
+            _ => panic!("{re} must be a call statement"),
+        };
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].kind(), &Variable);
+        match params[1].kind() {
+            NamedParameter(value) => {
+                assert_eq!(
+                    params[1].start().category(),
+                    &TokenCategory::Identifier(IdentifierType::Undefined("name".to_owned()))
+                );
+                assert_eq!(value.kind(), &Variable);
+            }
+            kind => panic!("expected named parameter, got: {kind:?}"),
+        }
+    }
 }
@@ -9,6 +9,7 @@ use itertools::Itertools;
 use crate::nasl::builtin::KBError;
 use crate::nasl::syntax::{Loader, NaslValue, Statement};
 use crate::nasl::{FromNaslValue, WithErrorInfo};
+use crate::storage::item::NvtPreference;
 use crate::storage::{ContextKey, Dispatcher, Field, Retrieve, Retriever};
 
 use super::error::ReturnBehavior;
@@ -406,6 +407,8 @@ pub struct Context<'a> {
     loader: &'a dyn Loader,
     /// Default function executor.
     executor: &'a Executor,
+    /// Preferences of the running script with their values within the scan
+    script_preferences: Vec<(NvtPreference, NaslValue)>,
 }
 
 impl<'a> Context<'a> {
@@ -425,9 +428,32 @@ impl<'a> Context<'a> {
             retriever,
             loader,
             executor,
+            script_preferences: vec![],
         }
     }
 
+    /// Sets the preferences of the running script with their values within the scan
+    pub fn with_script_preferences(
+        mut self,
+        script_preferences: Vec<(NvtPreference, NaslValue)>,
+    ) -> Self {
+        self.script_preferences = script_preferences;
+        self
+    }
+
+    /// Returns the value of a preference of the running script
+    ///
+    /// As in openvas the preference is looked up by its id when given, otherwise by its name.
+    pub fn script_preference(&self, name: Option<&str>, id: Option<i32>) -> Option<&NaslValue> {
+        self.script_preferences
+            .iter()
+            .find(|(p, _)| match id {
+                Some(id) => p.id() == Some(id),
+                None => name.is_some_and(|x| x == p.name()),
+            })
+            .map(|(_, value)| value)
+    }
+
     /// Executes a function by name
     ///
     /// Returns None when the function was not found.
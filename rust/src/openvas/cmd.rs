@@ -4,28 +4,215 @@
 
 use configparser::ini::Ini;
 use std::{
+    env,
+    ffi::OsStr,
+    fmt::Display,
     io::Result,
-    process::{Child, Command},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, Output},
+    sync::OnceLock,
 };
 
+use super::error::OpenvasError;
+
+/// Locations openvas is usually installed to, checked when it is not found in `$PATH`
+const COMMON_LOCATIONS: [&str; 4] = ["/usr/local/sbin", "/usr/local/bin", "/usr/sbin", "/usr/bin"];
+
+static OPENVAS: OnceLock<PathBuf> = OnceLock::new();
+
+/// Maximum amount of bytes of stderr kept in an error
+const STDERR_TAIL: usize = 1024;
+
+/// Returns the end of stderr, as the reason of a failure is usually printed last.
+fn stderr_tail(stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let stderr = stderr.trim_end();
+    match stderr.char_indices().rev().nth(STDERR_TAIL - 1) {
+        Some((start, _)) if start > 0 => format!("...{}", &stderr[start..]),
+        _ => stderr.to_string(),
+    }
+}
+
+/// Runs a command to completion while capturing stdout and stderr.
+///
+/// On a non-zero exit the error contains the tail of stderr.
+fn output(command: &mut Command) -> std::result::Result<Output, OpenvasError> {
+    let output = command.output().map_err(OpenvasError::CmdError)?;
+    if !output.status.success() {
+        return Err(OpenvasError::ExecFailed {
+            status: output.status,
+            stderr: stderr_tail(&output.stderr),
+        });
+    }
+    Ok(output)
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|x| x.is_file() && x.permissions().mode() & 0o111 != 0)
+}
+
+fn find_openvas_in(
+    configured: Option<&Path>,
+    path_var: Option<&OsStr>,
+    common_locations: &[&str],
+) -> std::result::Result<PathBuf, OpenvasError> {
+    let candidates: Vec<PathBuf> = match configured {
+        // a configured path must be used, even when there is another openvas
+        Some(configured) => vec![configured.to_path_buf()],
+        None => path_var
+            .map(|x| env::split_paths(x).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .chain(common_locations.iter().map(PathBuf::from))
+            .map(|x| x.join("openvas"))
+            .collect(),
+    };
+    match candidates.iter().find(|x| is_executable(x)) {
+        Some(found) => {
+            tracing::info!(path = %found.display(), "Found openvas executable");
+            Ok(found.clone())
+        }
+        None => Err(OpenvasError::ExecNotFound(candidates)),
+    }
+}
+
+/// Looks for the openvas executable.
+///
+/// When a path is configured only that one is checked. Otherwise openvas is searched
+/// in `$PATH` and afterwards in the common install locations.
+pub fn find_openvas(configured: Option<&Path>) -> std::result::Result<PathBuf, OpenvasError> {
+    find_openvas_in(
+        configured,
+        env::var_os("PATH").as_deref(),
+        &COMMON_LOCATIONS,
+    )
+}
+
+/// Discovers the openvas executable used by all commands of this module.
+///
+/// Must be called before any other command, otherwise the executable is discovered
+/// without a configured path on first use. Fails when the executable was already
+/// discovered.
+pub fn init_openvas(configured: Option<&Path>) -> std::result::Result<&'static Path, OpenvasError> {
+    if let Some(cached) = OPENVAS.get() {
+        return Err(OpenvasError::AlreadyInitialized(cached.clone()));
+    }
+    let found = find_openvas(configured)?;
+    OPENVAS
+        .set(found)
+        .map_err(|_| OpenvasError::AlreadyInitialized(openvas().to_path_buf()))?;
+    Ok(openvas())
+}
+
+/// Returns the path to the openvas executable.
+fn openvas() -> &'static Path {
+    OPENVAS.get_or_init(|| {
+        find_openvas(None).unwrap_or_else(|e| {
+            tracing::warn!(%e, "Falling back to openvas");
+            PathBuf::from("openvas")
+        })
+    })
+}
+
+/// Version of an openvas executable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OpenvasVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Display for OpenvasVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Oldest openvas version which can be controlled
+pub const MIN_OPENVAS_VERSION: OpenvasVersion = OpenvasVersion {
+    major: 23,
+    minor: 0,
+    patch: 0,
+};
+
+static VERSION: OnceLock<OpenvasVersion> = OnceLock::new();
+
+/// Parses the version from the output of `openvas --version`, e.g. `OpenVAS 23.9.0`.
+///
+/// Suffixes like `~git` are ignored and a missing patch level counts as 0.
+fn parse_version(output: &str) -> std::result::Result<OpenvasVersion, OpenvasError> {
+    let invalid = || OpenvasError::InvalidVersion(output.lines().next().unwrap_or("").to_string());
+    let version = output
+        .lines()
+        .find_map(|x| x.trim().strip_prefix("OpenVAS "))
+        .ok_or_else(invalid)?
+        .trim();
+    let end = version
+        .find(|x: char| !x.is_ascii_digit() && x != '.')
+        .unwrap_or(version.len());
+    let mut parts = version[..end].split('.').map(|x| x.parse::<u64>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), patch) => Ok(OpenvasVersion {
+            major,
+            minor,
+            patch: match patch {
+                Some(Ok(patch)) => patch,
+                None => 0,
+                Some(Err(_)) => return Err(invalid()),
+            },
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+fn verify_version(version: OpenvasVersion) -> std::result::Result<OpenvasVersion, OpenvasError> {
+    if version < MIN_OPENVAS_VERSION {
+        return Err(OpenvasError::UnsupportedVersion {
+            found: version,
+            minimum: MIN_OPENVAS_VERSION,
+        });
+    }
+    Ok(version)
+}
+
+/// Verifies that the version of the openvas executable is supported.
+///
+/// openvas is only invoked until a supported version was found, afterwards the
+/// cached version is returned.
+pub fn check_version() -> std::result::Result<OpenvasVersion, OpenvasError> {
+    if let Some(version) = VERSION.get() {
+        return Ok(*version);
+    }
+    let output = output(Command::new(openvas()).arg("--version"))?;
+    let version = verify_version(parse_version(&String::from_utf8_lossy(&output.stdout))?)?;
+    tracing::debug!(%version, "Using supported openvas version");
+    Ok(*VERSION.get_or_init(|| version))
+}
+
 /// This module provides functions to call the openvas executable for different
 /// purposes, e.g. start or stopping a scan.
 ///
 /// Check if it is possible to start openvas.
 pub fn check() -> bool {
-    Command::new("openvas").spawn().is_ok()
+    Command::new(openvas()).spawn().is_ok()
 }
 
 /// Check if it is possible to start openvas with the sudo command. In most
 /// environments it is necessary to start openvas as sudo, as it is not possible
 /// to use all functionalities.
 pub fn check_sudo() -> bool {
-    Command::new("sudo").args(["-n", "openvas"]).spawn().is_ok()
+    Command::new("sudo")
+        .arg("-n")
+        .arg(openvas())
+        .spawn()
+        .is_ok()
 }
 
 /// Read the openvas configuration.
-pub fn read_openvas_config() -> Result<Ini> {
-    let oconfig = Command::new("openvas").arg("-s").output()?;
+pub fn read_openvas_config() -> std::result::Result<Ini, OpenvasError> {
+    let oconfig = output(Command::new(openvas()).arg("-s"))?;
 
     let mut config = Ini::new();
     let oconfig = oconfig.stdout.iter().map(|x| *x as char).collect();
@@ -52,36 +239,178 @@ pub fn start(id: &str, sudo: bool, nice: Option<i8>) -> Result<Child> {
     match nice {
         Some(niceness) => match sudo {
             true => Command::new("nice")
-                .args([
-                    "-n",
-                    &niceness.to_string(),
-                    "sudo",
-                    "-n",
-                    "openvas",
-                    "--scan-start",
-                    id,
-                ])
+                .args(["-n", &niceness.to_string(), "sudo", "-n"])
+                .arg(openvas())
+                .args(["--scan-start", id])
                 .spawn(),
             false => Command::new("nice")
-                .args(["-n", &niceness.to_string(), "openvas", "--scan-start", id])
+                .args(["-n", &niceness.to_string()])
+                .arg(openvas())
+                .args(["--scan-start", id])
                 .spawn(),
         },
         None => match sudo {
             true => Command::new("sudo")
-                .args(["-n", "openvas", "--scan-start", id])
+                .arg("-n")
+                .arg(openvas())
+                .args(["--scan-start", id])
                 .spawn(),
-            false => Command::new("openvas").args(["--scan-start", id]).spawn(),
+            false => Command::new(openvas()).args(["--scan-start", id]).spawn(),
         },
     }
 }
 
 /// Stops a running scan. Openvas internally sends an SIGUSR1 to the running
 /// openvas scan.
-pub fn stop(id: &str, sudo: bool) -> Result<Child> {
+///
+/// Fails when `openvas --scan-stop` does not succeed, e.g. when the scan
+/// already finished.
+pub fn stop(id: &str, sudo: bool) -> std::result::Result<(), OpenvasError> {
     match sudo {
-        true => Command::new("sudo")
-            .args(["-n", "openvas", "--scan-stop", id])
-            .spawn(),
-        false => Command::new("openvas").args(["--scan-stop", id]).spawn(),
+        true => output(
+            Command::new("sudo")
+                .arg("-n")
+                .arg(openvas())
+                .args(["--scan-stop", id]),
+        ),
+        false => output(Command::new(openvas()).args(["--scan-stop", id])),
+    }?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+    use super::{
+        find_openvas_in, init_openvas, openvas, output, parse_version, verify_version,
+        OpenvasVersion,
+    };
+    use crate::openvas::error::OpenvasError;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn fake_openvas(dir: &std::path::Path) -> PathBuf {
+        fake_openvas_script(dir, "exit 0")
+    }
+
+    fn fake_openvas_script(dir: &std::path::Path, script: &str) -> PathBuf {
+        let path = dir.join("openvas");
+        fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn find_openvas_in_path() {
+        let empty = temp_dir();
+        let bin = temp_dir();
+        let expected = fake_openvas(&bin);
+        let path_var = std::env::join_paths([&empty, &bin]).unwrap();
+        assert_eq!(
+            find_openvas_in(None, Some(path_var.as_os_str()), &[]).unwrap(),
+            expected
+        );
+        // the configured path is preferred
+        let configured = fake_openvas(&empty);
+        assert_eq!(
+            find_openvas_in(Some(configured.as_path()), Some(path_var.as_os_str()), &[]).unwrap(),
+            configured
+        );
+        fs::remove_dir_all(empty).unwrap();
+        fs::remove_dir_all(bin).unwrap();
+    }
+
+    #[test]
+    fn openvas_not_found() {
+        let dir = temp_dir();
+        // not executable
+        fs::write(dir.join("openvas"), "").unwrap();
+        let path_var = dir.clone().into_os_string();
+        let result = find_openvas_in(None, Some(path_var.as_os_str()), &[]);
+        assert!(
+            matches!(result, Err(OpenvasError::ExecNotFound(ref x)) if x == &vec![dir.join("openvas")])
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn init_after_discovery() {
+        let discovered = openvas();
+        let result = init_openvas(None);
+        assert!(matches!(result, Err(OpenvasError::AlreadyInitialized(ref x)) if x == discovered));
+    }
+
+    #[test]
+    fn parse_openvas_version() {
+        let output =
+            "OpenVAS 23.9.0\ngvm-libs 22.10.0\n\nMost new code since 2005: (C) 2024 Greenbone AG\n";
+        let version = parse_version(output).unwrap();
+        assert_eq!(
+            version,
+            OpenvasVersion {
+                major: 23,
+                minor: 9,
+                patch: 0
+            }
+        );
+        assert!(verify_version(version).is_ok());
+        assert_eq!(
+            parse_version("OpenVAS 23.10~git").unwrap(),
+            OpenvasVersion {
+                major: 23,
+                minor: 10,
+                patch: 0
+            }
+        );
+        assert!(matches!(
+            parse_version("gvm-libs 22.10.0"),
+            Err(OpenvasError::InvalidVersion(_))
+        ));
+    }
+
+    #[test]
+    fn reject_unsupported_openvas_version() {
+        let version = parse_version("OpenVAS 22.4.1").unwrap();
+        assert!(matches!(
+            verify_version(version),
+            Err(OpenvasError::UnsupportedVersion { found, .. }) if found == version
+        ));
+    }
+
+    #[test]
+    fn stderr_on_failure() {
+        let dir = temp_dir();
+        let openvas = fake_openvas_script(
+            &dir,
+            "echo 'starting'\necho 'Unable to connect to redis' >&2\nexit 1",
+        );
+        let result = output(&mut std::process::Command::new(&openvas));
+        match result {
+            Err(e @ OpenvasError::ExecFailed { .. }) => {
+                let msg = e.to_string();
+                assert!(msg.contains("Unable to connect to redis"), "{msg}");
+                assert!(!msg.contains("starting"), "{msg}");
+            }
+            x => panic!("expected ExecFailed, got {x:?}"),
+        }
+
+        let openvas = fake_openvas_script(&dir, "echo 'warning' >&2\nexit 0");
+        let output = output(&mut std::process::Command::new(&openvas)).unwrap();
+        assert_eq!(output.stderr, b"warning\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn truncate_stderr() {
+        let stderr = format!("{}reason", "x".repeat(2000));
+        let tail = super::stderr_tail(stderr.as_bytes());
+        assert!(tail.starts_with("..."));
+        assert!(tail.ends_with("reason"));
+        assert_eq!(tail.len(), super::STDERR_TAIL + 3);
     }
 }
@@ -2,7 +2,14 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
-use std::time::Duration;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use thiserror::Error;
 
 #[derive(Debug, Clone)]
 #[cfg_attr(
@@ -15,6 +22,9 @@ pub struct Config {
     pub max_running_scans: Option<usize>,
     pub min_free_mem: Option<u64>,
     pub check_interval: Duration,
+    /// Path to the openvas executable. When not set it is searched in `$PATH`
+    /// and the common install locations.
+    pub openvas_path: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -24,6 +34,187 @@ impl Default for Config {
             max_running_scans: Default::default(),
             min_free_mem: Default::default(),
             check_interval: Duration::from_secs(1),
+            openvas_path: None,
+        }
+    }
+}
+
+/// Keys of a config file, all other keys are ignored.
+const KNOWN_KEYS: [&str; 5] = [
+    "max_queued_scans",
+    "max_running_scans",
+    "min_free_mem",
+    "check_interval",
+    "openvas_path",
+];
+
+/// Keys which must be set in a config file
+const REQUIRED_KEYS: [&str; 1] = ["check_interval"];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unable to read config file {0}: {1}")]
+    Read(PathBuf, io::Error),
+    #[error("Invalid config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Missing required config field {0}")]
+    MissingField(&'static str),
+    #[error("Invalid value {value:?} of environment variable {name}")]
+    InvalidEnv { name: &'static str, value: String },
+}
+
+/// Representation of a config file, e.g.
+///
+/// ```toml
+/// # interval in seconds in which running scans are checked, required
+/// check_interval = 1
+/// max_queued_scans = 10
+/// max_running_scans = 4
+/// # in bytes
+/// min_free_mem = 2147483648
+/// openvas_path = "/usr/local/sbin/openvas"
+/// ```
+#[derive(Debug, serde::Deserialize)]
+struct ConfigFile {
+    max_queued_scans: Option<usize>,
+    max_running_scans: Option<usize>,
+    min_free_mem: Option<u64>,
+    check_interval: u64,
+    openvas_path: Option<PathBuf>,
+}
+
+impl From<ConfigFile> for Config {
+    fn from(value: ConfigFile) -> Self {
+        Self {
+            max_queued_scans: value.max_queued_scans,
+            max_running_scans: value.max_running_scans,
+            min_free_mem: value.min_free_mem,
+            check_interval: Duration::from_secs(value.check_interval),
+            openvas_path: value.openvas_path,
+        }
+    }
+}
+
+fn env_value<T: FromStr>(
+    env: &impl Fn(&str) -> Option<String>,
+    name: &'static str,
+) -> Result<Option<T>, ConfigError> {
+    match env(name) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::InvalidEnv { name, value }),
+        None => Ok(None),
+    }
+}
+
+impl Config {
+    /// Parses a TOML config. Unknown keys are ignored with a warning.
+    pub fn from_toml(config: &str) -> Result<Self, ConfigError> {
+        let mut table: toml::Table = config.parse()?;
+        table.retain(|key, _| {
+            let known = KNOWN_KEYS.contains(&key);
+            if !known {
+                tracing::warn!(key, "Ignoring unknown config key");
+            }
+            known
+        });
+        if let Some(missing) = REQUIRED_KEYS.iter().find(|x| !table.contains_key(**x)) {
+            return Err(ConfigError::MissingField(missing));
         }
+        let file: ConfigFile = toml::Value::Table(table).try_into()?;
+        Ok(file.into())
+    }
+
+    /// Overrides settings with the environment variables `OPENVAS_MAX_QUEUED_SCANS`,
+    /// `OPENVAS_MAX_RUNNING_SCANS`, `OPENVAS_MIN_FREE_MEM`, `OPENVAS_CHECK_INTERVAL`
+    /// (in seconds) and `OPENVAS_PATH` returned by `env`.
+    pub fn with_env_overrides(
+        mut self,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        if let Some(x) = env_value(&env, "OPENVAS_MAX_QUEUED_SCANS")? {
+            self.max_queued_scans = Some(x);
+        }
+        if let Some(x) = env_value(&env, "OPENVAS_MAX_RUNNING_SCANS")? {
+            self.max_running_scans = Some(x);
+        }
+        if let Some(x) = env_value(&env, "OPENVAS_MIN_FREE_MEM")? {
+            self.min_free_mem = Some(x);
+        }
+        if let Some(x) = env_value(&env, "OPENVAS_CHECK_INTERVAL")? {
+            self.check_interval = Duration::from_secs(x);
+        }
+        if let Some(x) = env_value(&env, "OPENVAS_PATH")? {
+            self.openvas_path = Some(x);
+        }
+        Ok(self)
+    }
+
+    /// Loads the config from a TOML file and applies the overrides of the environment.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+        Self::from_toml(&config)?.with_env_overrides(|x| std::env::var(x).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::{Config, ConfigError};
+
+    #[tracing_test::traced_test]
+    #[test]
+    fn minimal_config() {
+        let config = Config::from_toml("check_interval = 5\nunknown = true").unwrap();
+        assert_eq!(config.check_interval, Duration::from_secs(5));
+        assert_eq!(config.max_queued_scans, None);
+        assert_eq!(config.max_running_scans, None);
+        assert_eq!(config.min_free_mem, None);
+        assert_eq!(config.openvas_path, None);
+        assert!(logs_contain("Ignoring unknown config key"));
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let config = Config::from_toml(
+            "check_interval = 5\nmax_running_scans = 2\nopenvas_path = \"/usr/sbin/openvas\"",
+        )
+        .unwrap()
+        .with_env_overrides(|x| match x {
+            "OPENVAS_MAX_RUNNING_SCANS" => Some("4".to_string()),
+            "OPENVAS_MAX_QUEUED_SCANS" => Some("8".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(config.max_running_scans, Some(4));
+        assert_eq!(config.max_queued_scans, Some(8));
+        assert_eq!(config.check_interval, Duration::from_secs(5));
+        assert_eq!(
+            config.openvas_path,
+            Some(PathBuf::from("/usr/sbin/openvas"))
+        );
+
+        let result = Config::default().with_env_overrides(|x| match x {
+            "OPENVAS_CHECK_INTERVAL" => Some("soon".to_string()),
+            _ => None,
+        });
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnv {
+                name: "OPENVAS_CHECK_INTERVAL",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_required_field() {
+        assert!(matches!(
+            Config::from_toml("max_running_scans = 2"),
+            Err(ConfigError::MissingField("check_interval"))
+        ));
     }
 }
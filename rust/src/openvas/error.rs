@@ -2,10 +2,38 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
-use std::io;
+use std::{io, path::PathBuf, process::ExitStatus};
 
 use thiserror::Error;
 
+use super::{cmd::OpenvasVersion, config::ConfigError};
+use crate::storage::redis::DbError;
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|x| x.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Category of an [`OpenvasError`] to allow handling errors without matching
+/// on their messages. The categories are stable, new errors are assigned to
+/// one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// A scan is missing
+    Storage,
+    /// Communication with redis failed
+    Redis,
+    /// The openvas executable is missing, unsupported or failed
+    Subprocess,
+    /// The configuration is invalid
+    Config,
+    /// A scan cannot be scheduled
+    Scheduling,
+}
+
 #[derive(Debug, Error)]
 pub enum OpenvasError {
     #[error("A scan with ID {0} already exists.")]
@@ -20,4 +48,120 @@ pub enum OpenvasError {
     MaxQueuedScans,
     #[error("Unable to run openvas.")]
     UnableToRunExec,
+    #[error("Unable to find the openvas executable, looked at {}.", display_paths(.0))]
+    ExecNotFound(Vec<PathBuf>),
+    #[error("The openvas executable was already discovered as {}.", .0.display())]
+    AlreadyInitialized(PathBuf),
+    #[error("openvas failed with {status}: {stderr}")]
+    ExecFailed { status: ExitStatus, stderr: String },
+    #[error("Unable to parse the openvas version from {0:?}.")]
+    InvalidVersion(String),
+    #[error("openvas {found} is not supported, at least {minimum} is required.")]
+    UnsupportedVersion {
+        found: OpenvasVersion,
+        minimum: OpenvasVersion,
+    },
+    #[error("{0}")]
+    Redis(#[from] DbError),
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+}
+
+impl OpenvasError {
+    /// Returns the category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            OpenvasError::ScanNotFound(_) => ErrorKind::Storage,
+            OpenvasError::Redis(_) => ErrorKind::Redis,
+            OpenvasError::MissingExec
+            | OpenvasError::CmdError(_)
+            | OpenvasError::UnableToRunExec
+            | OpenvasError::ExecNotFound(_)
+            | OpenvasError::ExecFailed { .. }
+            | OpenvasError::InvalidVersion(_)
+            | OpenvasError::UnsupportedVersion { .. } => ErrorKind::Subprocess,
+            OpenvasError::Config(_) | OpenvasError::AlreadyInitialized(_) => ErrorKind::Config,
+            OpenvasError::DuplicateScanID(_) | OpenvasError::MaxQueuedScans => {
+                ErrorKind::Scheduling
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, os::unix::process::ExitStatusExt, path::PathBuf, process::ExitStatus};
+
+    use super::{ErrorKind, OpenvasError};
+    use crate::openvas::{cmd::OpenvasVersion, config::ConfigError};
+    use crate::storage::redis::DbError;
+
+    #[test]
+    fn error_kinds() {
+        let version = OpenvasVersion {
+            major: 22,
+            minor: 4,
+            patch: 0,
+        };
+        let errors = [
+            (
+                OpenvasError::DuplicateScanID("1".to_string()),
+                ErrorKind::Scheduling,
+            ),
+            (OpenvasError::MissingExec, ErrorKind::Subprocess),
+            (
+                OpenvasError::ScanNotFound("1".to_string()),
+                ErrorKind::Storage,
+            ),
+            (
+                OpenvasError::CmdError(io::Error::other("failed")),
+                ErrorKind::Subprocess,
+            ),
+            (OpenvasError::MaxQueuedScans, ErrorKind::Scheduling),
+            (OpenvasError::UnableToRunExec, ErrorKind::Subprocess),
+            (OpenvasError::ExecNotFound(vec![]), ErrorKind::Subprocess),
+            (
+                OpenvasError::ExecFailed {
+                    status: ExitStatus::from_raw(256),
+                    stderr: String::new(),
+                },
+                ErrorKind::Subprocess,
+            ),
+            (
+                OpenvasError::InvalidVersion(String::new()),
+                ErrorKind::Subprocess,
+            ),
+            (
+                OpenvasError::UnsupportedVersion {
+                    found: version,
+                    minimum: version,
+                },
+                ErrorKind::Subprocess,
+            ),
+            (OpenvasError::from(DbError::NoAvailDbErr), ErrorKind::Redis),
+            (
+                OpenvasError::from(ConfigError::MissingField("check_interval")),
+                ErrorKind::Config,
+            ),
+            (
+                OpenvasError::AlreadyInitialized(PathBuf::from("openvas")),
+                ErrorKind::Config,
+            ),
+        ];
+        for (error, kind) in errors {
+            assert_eq!(error.kind(), kind, "{error}");
+        }
+    }
+
+    #[test]
+    fn display_is_kept() {
+        assert_eq!(
+            OpenvasError::ScanNotFound("1".to_string()).to_string(),
+            "A scan with ID 1 not found."
+        );
+        assert_eq!(
+            OpenvasError::from(DbError::NoAvailDbErr).to_string(),
+            DbError::NoAvailDbErr.to_string()
+        );
+    }
 }
@@ -9,6 +9,15 @@ mod error;
 mod openvas;
 mod openvas_redis;
 mod pref_handler;
+mod preferences;
 mod result_collector;
 
+pub use config::{Config, ConfigError};
+pub use error::{ErrorKind, OpenvasError};
 pub use openvas::Scanner;
+pub use openvas_redis::{KbAccess, Reconnecting, VtHelper};
+pub use preferences::{defaults, PREFERENCES};
+pub use result_collector::{
+    to_csv, to_json, to_xml, EnrichedResult, ResultFilter, ResultHelper, Results, Severity,
+    RESULTS_CSV_HEADER, RESULTS_SCHEMA_VERSION,
+};
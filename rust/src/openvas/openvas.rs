@@ -5,7 +5,7 @@
 use super::{
     cmd,
     error::OpenvasError,
-    openvas_redis::{KbAccess, RedisHelper},
+    openvas_redis::{KbAccess, Reconnecting, RedisHelper},
     pref_handler::PreferenceHandler,
     result_collector::ResultHelper,
 };
@@ -17,7 +17,7 @@ use crate::models::{
 };
 use crate::{
     models::{self, resources::check::Checker, Scan},
-    storage::redis::{NameSpaceSelector, RedisCtx},
+    storage::redis::{NameSpaceSelector, RedisConfig, RedisCtx, RedisTlsConfig},
 };
 use async_trait::async_trait;
 use std::{
@@ -33,8 +33,9 @@ use std::{
 pub struct Scanner {
     running: Mutex<HashMap<String, (Child, u32)>>,
     sudo: bool,
-    redis_socket: String,
+    redis: RedisConfig,
     resource_checker: Option<Checker>,
+    expand_env: bool,
 }
 
 impl From<OpenvasError> for ScanError {
@@ -96,8 +97,9 @@ impl Scanner {
         Self {
             running: Default::default(),
             sudo,
-            redis_socket: url,
+            redis: RedisConfig::from(url.as_str()),
             resource_checker: Some(Checker::new_relative_memory(memory, None)),
+            expand_env: false,
         }
     }
 
@@ -105,11 +107,25 @@ impl Scanner {
         Self {
             running: Default::default(),
             sudo,
-            redis_socket: url,
+            redis: RedisConfig::from(url.as_str()),
             resource_checker: Some(Checker::new(memory, cpu)),
+            expand_env: false,
         }
     }
 
+    /// Sets the TLS configuration used when connecting to a `rediss://` url.
+    pub fn with_redis_tls(mut self, tls: RedisTlsConfig) -> Self {
+        self.redis.tls = tls;
+        self
+    }
+
+    /// Sets whether references to environment variables like `$SCAN_USER` within string
+    /// scan preferences are replaced by their values. Disabled by default.
+    pub fn with_env_expansion(mut self, expand_env: bool) -> Self {
+        self.expand_env = expand_env;
+        self
+    }
+
     /// Removes a scan from init and add it to the list of running scans
     fn add_running(&self, id: String, dbid: u32) -> Result<bool, OpenvasError> {
         let openvas = cmd::start(&id, self.sudo, None).map_err(OpenvasError::CmdError)?;
@@ -125,26 +141,26 @@ impl Scanner {
     fn create_redis_connector(
         &self,
         dbid: Option<u32>,
-    ) -> Result<RedisHelper<RedisCtx>, ScanError> {
+    ) -> Result<RedisHelper<Reconnecting<RedisCtx>>, ScanError> {
         let namespace = match dbid {
             Some(id) => [NameSpaceSelector::Fix(id)],
             None => [NameSpaceSelector::Free],
         };
 
-        tracing::trace!(url = &self.redis_socket, "connecting to redis");
-        let kbctx = Arc::new(Mutex::new(
-            match RedisCtx::open(&self.redis_socket, &namespace) {
-                Ok(x) => x,
-                Err(e) => return Err(ScanError::Connection(format!("{e}"))),
-            },
-        ));
-        let nvtcache = Arc::new(Mutex::new(
-            match RedisCtx::open(&self.redis_socket, &[NameSpaceSelector::Key("nvticache")]) {
-                Ok(x) => x,
-                Err(e) => return Err(ScanError::Connection(format!("{e}"))),
-            },
-        ));
-        Ok(RedisHelper::<RedisCtx>::new(nvtcache, kbctx))
+        tracing::trace!(url = &self.redis.url, "connecting to redis");
+        let open = |selector: &[NameSpaceSelector]| {
+            let ctx = RedisCtx::open_with_config(&self.redis, selector)
+                .map_err(|e| ScanError::Connection(format!("{e}")))?;
+            // reconnect to the same namespace when the connection gets lost
+            let config = self.redis.clone();
+            let db = ctx.db;
+            Ok::<_, ScanError>(Reconnecting::new(ctx, move || {
+                RedisCtx::open_with_config(&config, &[NameSpaceSelector::Fix(db)])
+            }))
+        };
+        let kbctx = Arc::new(Mutex::new(open(&namespace)?));
+        let nvtcache = Arc::new(Mutex::new(open(&[NameSpaceSelector::Key("nvticache")])?));
+        Ok(RedisHelper::<Reconnecting<RedisCtx>>::new(nvtcache, kbctx))
     }
 }
 
@@ -153,19 +169,22 @@ impl Default for Scanner {
         Self {
             running: Default::default(),
             sudo: cmd::check_sudo(),
-            redis_socket: cmd::get_redis_socket(),
+            redis: RedisConfig::from(cmd::get_redis_socket().as_str()),
             resource_checker: None,
+            expand_env: false,
         }
     }
 }
 #[async_trait]
 impl ScanStarter for Scanner {
     async fn start_scan(&self, scan: Scan) -> Result<(), ScanError> {
+        cmd::check_version()?;
         // Prepare the connections to redis for communication with openvas.
         let mut redis_help = self.create_redis_connector(None)?;
 
         // Prepare preferences and store them in redis
-        let mut pref_handler = PreferenceHandler::new(scan.clone(), &mut redis_help);
+        let mut pref_handler = PreferenceHandler::new(scan.clone(), &mut redis_help)
+            .with_env_expansion(self.expand_env);
         match pref_handler.prepare_preferences_for_openvas().await {
             Ok(_) => (),
             Err(e) => {
@@ -204,19 +223,18 @@ impl ScanStopper for Scanner {
             None => return Err(OpenvasError::ScanNotFound(scan_id.to_string()).into()),
         };
 
-        cmd::stop(scan_id, self.sudo)
-            .map_err(OpenvasError::CmdError)?
-            .wait()
-            .map_err(OpenvasError::CmdError)?;
+        // the scan is already removed, so its process and kb must be cleaned up regardless
+        if let Err(e) = cmd::stop(scan_id, self.sudo) {
+            tracing::warn!(scan_id, error = %e, "unable to stop the openvas scan");
+        }
 
-        scan.wait().map_err(OpenvasError::CmdError)?;
+        let waited = scan.wait().map_err(OpenvasError::CmdError);
 
         // Release the task kb
         let mut redis_help = self.create_redis_connector(Some(dbid))?;
-        redis_help
-            .release()
-            .map_err(|e| ScanError::Unexpected(e.to_string()))?;
+        redis_help.release().map_err(OpenvasError::Redis)?;
 
+        waited?;
         Ok(())
     }
 }
@@ -245,7 +263,7 @@ impl ScanDeleter for Scanner {
         ov_results
             .collect_scan_status(scan_id.to_string())
             .await
-            .map_err(|e| ScanError::Unexpected(e.to_string()))?;
+            .map_err(OpenvasError::Redis)?;
 
         let mut scan_status = Phase::Running;
         if let Ok(res) = Arc::as_ref(&ov_results.results).lock() {
@@ -265,9 +283,7 @@ impl ScanDeleter for Scanner {
             }
             _ => match self.remove_running(scan_id) {
                 Some(_) => {
-                    redis_help
-                        .release()
-                        .map_err(|e| ScanError::Unexpected(e.to_string()))?;
+                    redis_help.release().map_err(OpenvasError::Redis)?;
                     tracing::debug!("Scan {scan_id} delete successfully");
                     Ok(())
                 }
@@ -302,15 +318,15 @@ impl ScanResultFetcher for Scanner {
         ov_results
             .collect_results()
             .await
-            .map_err(|e| ScanError::Unexpected(e.to_string()))?;
+            .map_err(OpenvasError::Redis)?;
         ov_results
             .collect_host_status()
             .await
-            .map_err(|e| ScanError::Unexpected(e.to_string()))?;
+            .map_err(OpenvasError::Redis)?;
         ov_results
             .collect_scan_status(scan_id.to_string())
             .await
-            .map_err(|e| ScanError::Unexpected(e.to_string()))?;
+            .map_err(OpenvasError::Redis)?;
 
         match Arc::as_ref(&ov_results.results).lock() {
             Ok(all_results) => {
@@ -386,9 +402,7 @@ impl ScanResultFetcher for Scanner {
                         }
                     }
 
-                    redis_help
-                        .release()
-                        .map_err(|e| ScanError::Unexpected(e.to_string()))?;
+                    redis_help.release().map_err(OpenvasError::Redis)?;
                     self.running.lock().unwrap().remove(scan_id);
                 }
 
@@ -2,15 +2,208 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
-use crate::nasl::syntax::ACT;
-use crate::storage::item::{Nvt, NvtPreference, PreferenceType};
-use crate::storage::redis::{DbError, RedisCtx, RedisGetNvt, RedisStorageResult, RedisWrapper};
-use std::collections::BTreeMap;
+use crate::storage::item::Nvt;
+use crate::storage::redis::{
+    prefix_pattern, DbError, RedisCtx, RedisGetNvt, RedisStorageResult, RedisWrapper,
+};
+use crate::storage::{Kb, INTERNAL_KB_PREFIX};
 use std::{
-    collections::HashMap,
+    ops::{Deref, DerefMut},
     sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
 };
 
+/// Prefix of the KB items containing the secrets of credentials
+pub const SECRET_KB_PREFIX: &str = "Secret/";
+
+/// Default amount of reconnection attempts before an error is returned
+const MAX_RECONNECT_ATTEMPTS: usize = 3;
+/// Default delay before the first reconnection attempt, which doubles with each further attempt
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+type Connect<R> = Box<dyn FnMut() -> RedisStorageResult<R> + Send>;
+
+/// Wraps a redis connection and re-establishes it on connection-level errors.
+///
+/// When an operation fails because the connection got lost, the connection is
+/// dropped and replaced by a new one. Reads, as well as deleting and expiring
+/// keys, are retried on the new connection. After `max_attempts` reconnections
+/// the error is returned to the caller.
+///
+/// Pushing and popping are not retried, as it is unknown whether redis already
+/// executed them before the connection got lost. The connection is replaced
+/// and the error is returned, so that the caller decides how to proceed.
+pub struct Reconnecting<R> {
+    inner: R,
+    connect: Connect<R>,
+    max_attempts: usize,
+    backoff: Duration,
+}
+
+impl<R> Reconnecting<R> {
+    pub fn new<F>(inner: R, connect: F) -> Self
+    where
+        F: FnMut() -> RedisStorageResult<R> + Send + 'static,
+    {
+        Self {
+            inner,
+            connect: Box::new(connect),
+            max_attempts: MAX_RECONNECT_ATTEMPTS,
+            backoff: RECONNECT_BACKOFF,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first reconnection attempt, which doubles with each further attempt
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    fn is_connection_error(err: &DbError) -> bool {
+        matches!(err, DbError::IoError(_) | DbError::ConnectionLost(_))
+    }
+
+    fn reconnect(&mut self, attempts: usize) {
+        let delay = self
+            .backoff
+            .saturating_mul(1 << (attempts - 1).min(16) as u32);
+        std::thread::sleep(delay);
+        match (self.connect)() {
+            Ok(inner) => self.inner = inner,
+            Err(e) => tracing::debug!(error=%e, attempts, "unable to reconnect"),
+        }
+    }
+
+    /// Runs an idempotent operation and retries it after reconnecting
+    fn run<T, F>(&mut self, mut f: F) -> RedisStorageResult<T>
+    where
+        F: FnMut(&mut R) -> RedisStorageResult<T>,
+    {
+        let mut attempts = 0;
+        loop {
+            match f(&mut self.inner) {
+                Err(e) if Self::is_connection_error(&e) && attempts < self.max_attempts => {
+                    attempts += 1;
+                    tracing::warn!(error=%e, attempts, "lost connection to redis, reconnecting");
+                    self.reconnect(attempts);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Runs an operation that must not be repeated and reconnects when it fails
+    fn run_once<T, F>(&mut self, f: F) -> RedisStorageResult<T>
+    where
+        F: FnOnce(&mut R) -> RedisStorageResult<T>,
+    {
+        let result = f(&mut self.inner);
+        if let Err(e) = &result {
+            if Self::is_connection_error(e) && self.max_attempts > 0 {
+                tracing::warn!(error=%e, "lost connection to redis, reconnecting without retry");
+                self.reconnect(1);
+            }
+        }
+        result
+    }
+}
+
+impl<R> Deref for Reconnecting<R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<R> DerefMut for Reconnecting<R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<R: RedisWrapper> RedisWrapper for Reconnecting<R> {
+    fn rpush<T: redis::ToRedisArgs>(&mut self, key: &str, val: T) -> RedisStorageResult<()> {
+        let val = val.to_redis_args();
+        self.run_once(|r| r.rpush(key, &val))
+    }
+
+    fn lpush<T: redis::ToRedisArgs>(&mut self, key: &str, val: T) -> RedisStorageResult<()> {
+        let val = val.to_redis_args();
+        self.run_once(|r| r.lpush(key, &val))
+    }
+
+    fn del(&mut self, key: &str) -> RedisStorageResult<()> {
+        self.run(|r| r.del(key))
+    }
+
+    fn lindex(&mut self, key: &str, index: isize) -> RedisStorageResult<String> {
+        self.run(|r| r.lindex(key, index))
+    }
+
+    fn lrange(&mut self, key: &str, start: isize, end: isize) -> RedisStorageResult<Vec<String>> {
+        self.run(|r| r.lrange(key, start, end))
+    }
+
+    fn keys(&mut self, pattern: &str) -> RedisStorageResult<Vec<String>> {
+        self.run(|r| r.keys(pattern))
+    }
+
+    fn scan_keys(&mut self, pattern: &str) -> RedisStorageResult<Vec<String>> {
+        self.run(|r| r.scan_keys(pattern))
+    }
+
+    fn pop(&mut self, key: &str) -> RedisStorageResult<Vec<String>> {
+        self.run_once(|r| r.pop(key))
+    }
+
+    fn expire(&mut self, key: &str, ttl: Duration) -> RedisStorageResult<()> {
+        self.run(|r| r.expire(key, ttl))
+    }
+
+    fn lpush_many(&mut self, items: &[(String, Vec<Vec<u8>>)]) -> RedisStorageResult<()> {
+        self.run_once(|r| r.lpush_many(items))
+    }
+}
+
+impl<R: RedisWrapper> RedisGetNvt for Reconnecting<R> {}
+
+/// Namespace of a task KB.
+///
+/// Each scan gets its own redis database, so that scans against the same host
+/// don't see each other's KB items.
+pub trait KbNamespace {
+    /// Returns the redis database of the task KB
+    fn db(&self) -> u32;
+    /// Deletes all items of the task KB and releases the database for other scans
+    fn delete_namespace(&mut self) -> RedisStorageResult<()>;
+}
+
+impl KbNamespace for RedisCtx {
+    fn db(&self) -> u32 {
+        self.db
+    }
+
+    fn delete_namespace(&mut self) -> RedisStorageResult<()> {
+        RedisCtx::delete_namespace(self)
+    }
+}
+
+impl<R: KbNamespace> KbNamespace for Reconnecting<R> {
+    fn db(&self) -> u32 {
+        self.inner.db()
+    }
+
+    fn delete_namespace(&mut self) -> RedisStorageResult<()> {
+        self.run(|r| r.delete_namespace())
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct RedisHelper<R>
 where
@@ -27,10 +220,10 @@ where
     /// Initialize a RedisHelper struct with the connection to access the NVT cache
     /// and a empty task knowledge base to store the scan configuration to be sent to openvas.
     pub fn new(
-        nvti_cache: Arc<Mutex<RedisCtx>>,
-        kb_cache: Arc<Mutex<RedisCtx>>,
-    ) -> RedisHelper<RedisCtx> {
-        RedisHelper::<RedisCtx> {
+        nvti_cache: Arc<Mutex<Reconnecting<RedisCtx>>>,
+        kb_cache: Arc<Mutex<Reconnecting<RedisCtx>>>,
+    ) -> RedisHelper<Reconnecting<RedisCtx>> {
+        RedisHelper::<Reconnecting<RedisCtx>> {
             cache: nvti_cache,
             task_kb: kb_cache,
         }
@@ -55,6 +248,41 @@ pub trait KbAccess {
         key: &str,
         value: T,
     ) -> RedisStorageResult<()>;
+    /// Pushes a KB item that expires after the given ttl.
+    ///
+    /// This is meant for volatile KB items, e.g. port detection results, that
+    /// must not outlive the scan.
+    fn dispatch_with_ttl<T: redis::ToRedisArgs>(
+        &mut self,
+        key: &str,
+        value: T,
+        ttl: Duration,
+    ) -> RedisStorageResult<()>;
+    /// Pushes multiple KB items at once while keeping their order.
+    fn dispatch_many<T: redis::ToRedisArgs>(
+        &mut self,
+        items: &[(String, T)],
+    ) -> RedisStorageResult<()> {
+        for (key, value) in items {
+            self.push_kb_item(key, value)?;
+        }
+        Ok(())
+    }
+    /// Returns all values of a KB item, an expired item has no values.
+    fn kb_item(&mut self, key: &str) -> RedisStorageResult<Vec<String>>;
+    /// Returns every value of every KB item whose key starts with `prefix`, sorted by key.
+    fn kb_items_by_prefix(&mut self, prefix: &str) -> RedisStorageResult<Vec<Kb>>;
+    /// Returns every value of every KB item of the task KB, sorted by key.
+    ///
+    /// Secrets of credentials are left out, as well as the internal items which
+    /// contain the scan preferences including the credentials.
+    fn dump_kb(&mut self) -> RedisStorageResult<Vec<Kb>> {
+        let mut items = self.kb_items_by_prefix("")?;
+        items.retain(|x| {
+            !x.key.starts_with(SECRET_KB_PREFIX) && !x.key.starts_with(INTERNAL_KB_PREFIX)
+        });
+        Ok(items)
+    }
     fn scan_status(&mut self, _scan_id: String) -> RedisStorageResult<String> {
         Ok(String::new())
     }
@@ -70,16 +298,21 @@ pub trait KbAccess {
     }
 }
 
-impl KbAccess for RedisHelper<RedisCtx> {
+impl<R> KbAccess for RedisHelper<R>
+where
+    R: RedisWrapper + KbNamespace,
+{
     /// Provide access to the cache
     fn kb_id(&self) -> RedisStorageResult<u32> {
         // TODO: Should this really be self.lock_task_kb? This seems
         // like it should be self.lock_cache, but I'm keeping it as it
         // was for now.
-        Ok(self.lock_task_kb()?.db)
+        Ok(self.lock_task_kb()?.db())
     }
 
     /// Release the redis namespace and make it available again for other tasks
+    ///
+    /// All KB items, including the secrets of credentials, are deleted.
     fn release(&mut self) -> RedisStorageResult<()> {
         self.lock_task_kb()?.delete_namespace()
     }
@@ -93,6 +326,45 @@ impl KbAccess for RedisHelper<RedisCtx> {
         Ok(())
     }
 
+    fn dispatch_with_ttl<T: redis::ToRedisArgs>(
+        &mut self,
+        key: &str,
+        value: T,
+        ttl: Duration,
+    ) -> RedisStorageResult<()> {
+        let mut kb = self.lock_task_kb()?;
+        kb.lpush(key, value)?;
+        kb.expire(key, ttl)
+    }
+
+    fn dispatch_many<T: redis::ToRedisArgs>(
+        &mut self,
+        items: &[(String, T)],
+    ) -> RedisStorageResult<()> {
+        let items: Vec<_> = items
+            .iter()
+            .map(|(key, value)| (key.clone(), value.to_redis_args()))
+            .collect();
+        self.lock_task_kb()?.lpush_many(&items)
+    }
+
+    fn kb_item(&mut self, key: &str) -> RedisStorageResult<Vec<String>> {
+        self.lock_task_kb()?.lrange(key, 0, -1)
+    }
+
+    fn kb_items_by_prefix(&mut self, prefix: &str) -> RedisStorageResult<Vec<Kb>> {
+        let mut kb = self.lock_task_kb()?;
+        let mut keys = kb.scan_keys(&prefix_pattern(prefix))?;
+        keys.sort();
+        let mut result = Vec::new();
+        for key in keys {
+            for value in kb.lrange(&key, 0, -1)? {
+                result.push((key.as_str(), value).into());
+            }
+        }
+        Ok(result)
+    }
+
     fn scan_status(&mut self, scan_id: String) -> RedisStorageResult<String> {
         self.lock_task_kb()?
             .lindex(&format!("internal/{}", scan_id), 0)
@@ -111,76 +383,425 @@ pub trait VtHelper {
     fn get_vt(&self, oid: &str) -> RedisStorageResult<Option<Nvt>>;
 }
 
-impl VtHelper for RedisHelper<RedisCtx> {
+impl VtHelper for RedisHelper<Reconnecting<RedisCtx>> {
     fn get_vt(&self, oid: &str) -> RedisStorageResult<Option<Nvt>> {
         self.lock_cache()?.redis_get_vt(oid)
     }
 }
 
-pub struct FakeRedis {
-    pub data: HashMap<String, Vec<Vec<u8>>>,
-}
+#[cfg(test)]
+pub use fake::FakeRedis;
 
-impl FakeRedis {
-    #[cfg(test)]
-    pub fn item_exists(&self, key: &str, value: &str) -> bool {
-        let mut v: Vec<String> = Vec::new();
-        if let Some(item) = self.data.get(key) {
-            for i in item {
-                v.push(String::from_utf8(i.to_vec()).unwrap());
+#[cfg(test)]
+mod fake {
+    use std::collections::{BTreeMap, HashMap};
+    use std::time::{Duration, Instant};
+
+    use crate::nasl::syntax::ACT;
+    use crate::storage::item::{Nvt, NvtPreference, PreferenceType};
+    use crate::storage::redis::RedisStorageResult;
+    use crate::storage::Kb;
+
+    use super::{KbAccess, VtHelper};
+
+    /// In-memory task KB for tests
+    #[derive(Default)]
+    pub struct FakeRedis {
+        pub data: HashMap<String, Vec<Vec<u8>>>,
+        pub expires: HashMap<String, Instant>,
+    }
+
+    impl FakeRedis {
+        pub fn item_exists(&self, key: &str, value: &str) -> bool {
+            let mut v: Vec<String> = Vec::new();
+            if let Some(item) = self.data.get(key) {
+                for i in item {
+                    v.push(String::from_utf8(i.to_vec()).unwrap());
+                }
             }
+            v.contains(&value.to_string())
+        }
+    }
+
+    impl VtHelper for FakeRedis {
+        fn get_vt(&self, oid: &str) -> RedisStorageResult<Option<Nvt>> {
+            match oid {
+                "123" => Ok(Some(Nvt {
+                    oid: "123".to_string(),
+                    name: "test".to_string(),
+                    filename: "test.nasl".to_string(),
+                    tag: BTreeMap::new(),
+                    dependencies: Vec::new(),
+                    required_keys: Vec::new(),
+                    mandatory_keys: Vec::new(),
+                    excluded_keys: Vec::new(),
+                    required_ports: Vec::new(),
+                    required_udp_ports: Vec::new(),
+                    references: Vec::new(),
+                    preferences: vec![
+                        NvtPreference {
+                            id: Some(1),
+                            class: PreferenceType::CheckBox,
+                            name: "test1".to_string(),
+                            default: "no".to_string(),
+                        },
+                        NvtPreference {
+                            id: Some(2),
+                            class: PreferenceType::Entry,
+                            name: "test2".to_string(),
+                            default: "".to_string(),
+                        },
+                    ],
+                    category: ACT::Init,
+                    family: "test".to_string(),
+                })),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    impl KbAccess for FakeRedis {
+        fn push_kb_item<T: redis::ToRedisArgs>(
+            &mut self,
+            key: &str,
+            value: T,
+        ) -> RedisStorageResult<()> {
+            self.data.insert(key.to_string(), value.to_redis_args());
+            Ok(())
+        }
+
+        fn dispatch_with_ttl<T: redis::ToRedisArgs>(
+            &mut self,
+            key: &str,
+            value: T,
+            ttl: Duration,
+        ) -> RedisStorageResult<()> {
+            self.push_kb_item(key, value)?;
+            self.expires.insert(key.to_string(), Instant::now() + ttl);
+            Ok(())
+        }
+
+        fn kb_item(&mut self, key: &str) -> RedisStorageResult<Vec<String>> {
+            if self.expires.get(key).is_some_and(|x| *x <= Instant::now()) {
+                self.expires.remove(key);
+                self.data.remove(key);
+            }
+            Ok(self
+                .data
+                .get(key)
+                .map(|x| {
+                    x.iter()
+                        .map(|v| String::from_utf8_lossy(v).to_string())
+                        .collect()
+                })
+                .unwrap_or_default())
+        }
+
+        fn kb_items_by_prefix(&mut self, prefix: &str) -> RedisStorageResult<Vec<Kb>> {
+            let mut keys: Vec<_> = self
+                .data
+                .keys()
+                .filter(|x| x.starts_with(prefix))
+                .cloned()
+                .collect();
+            keys.sort();
+            let mut result = Vec::new();
+            for key in keys {
+                for value in self.kb_item(&key)? {
+                    result.push((key.as_str(), value).into());
+                }
+            }
+            Ok(result)
+        }
+
+        fn kb_id(&self) -> RedisStorageResult<u32> {
+            Ok(3)
+        }
+
+        fn release(&mut self) -> RedisStorageResult<()> {
+            self.data.clear();
+            self.expires.clear();
+            Ok(())
         }
-        v.contains(&value.to_string())
     }
 }
 
-impl VtHelper for FakeRedis {
-    fn get_vt(&self, oid: &str) -> RedisStorageResult<Option<Nvt>> {
-        match oid {
-            "123" => Ok(Some(Nvt {
-                oid: "123".to_string(),
-                name: "test".to_string(),
-                filename: "test.nasl".to_string(),
-                tag: BTreeMap::new(),
-                dependencies: Vec::new(),
-                required_keys: Vec::new(),
-                mandatory_keys: Vec::new(),
-                excluded_keys: Vec::new(),
-                required_ports: Vec::new(),
-                required_udp_ports: Vec::new(),
-                references: Vec::new(),
-                preferences: vec![
-                    NvtPreference {
-                        id: Some(1),
-                        class: PreferenceType::CheckBox,
-                        name: "test1".to_string(),
-                        default: "no".to_string(),
-                    },
-                    NvtPreference {
-                        id: Some(2),
-                        class: PreferenceType::Entry,
-                        name: "test2".to_string(),
-                        default: "".to_string(),
-                    },
-                ],
-                category: ACT::Init,
-                family: "test".to_string(),
-            })),
-            _ => Ok(None),
-        }
-    }
-}
-
-impl KbAccess for FakeRedis {
-    fn push_kb_item<T: redis::ToRedisArgs>(
-        &mut self,
-        key: &str,
-        value: T,
-    ) -> RedisStorageResult<()> {
-        self.data.insert(key.to_string(), value.to_redis_args());
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use crate::storage::redis::{DbError, RedisStorageResult, RedisWrapper};
+
+    use super::{FakeRedis, KbAccess, KbNamespace, Reconnecting, RedisHelper};
+
+    /// Connection that fails all operations after it got dropped
+    struct FakeConnection {
+        alive: bool,
     }
-    fn kb_id(&self) -> RedisStorageResult<u32> {
-        Ok(3)
+
+    impl FakeConnection {
+        fn check(&self) -> RedisStorageResult<()> {
+            if self.alive {
+                Ok(())
+            } else {
+                Err(DbError::IoError("broken pipe".to_string()))
+            }
+        }
+    }
+
+    impl RedisWrapper for FakeConnection {
+        fn rpush<T: redis::ToRedisArgs>(&mut self, _: &str, _: T) -> RedisStorageResult<()> {
+            self.check()
+        }
+
+        fn lpush<T: redis::ToRedisArgs>(&mut self, _: &str, _: T) -> RedisStorageResult<()> {
+            self.check()
+        }
+
+        fn del(&mut self, _: &str) -> RedisStorageResult<()> {
+            self.check()
+        }
+
+        fn lindex(&mut self, _: &str, _: isize) -> RedisStorageResult<String> {
+            self.check().map(|_| "value".to_string())
+        }
+
+        fn lrange(&mut self, _: &str, _: isize, _: isize) -> RedisStorageResult<Vec<String>> {
+            self.check().map(|_| vec![])
+        }
+
+        fn keys(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
+            self.check().map(|_| vec![])
+        }
+
+        fn scan_keys(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
+            self.check().map(|_| vec![])
+        }
+
+        fn pop(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
+            self.check().map(|_| vec![])
+        }
+
+        fn expire(&mut self, _: &str, _: Duration) -> RedisStorageResult<()> {
+            self.check()
+        }
+    }
+
+    type Databases = Arc<Mutex<HashMap<u32, HashMap<String, Vec<String>>>>>;
+
+    /// Connection to a database of a redis server shared with other connections
+    struct FakeDatabase {
+        databases: Databases,
+        db: u32,
+    }
+
+    impl FakeDatabase {
+        fn with_kb<T>(&self, f: impl FnOnce(&mut HashMap<String, Vec<String>>) -> T) -> T {
+            f(self.databases.lock().unwrap().entry(self.db).or_default())
+        }
+    }
+
+    impl RedisWrapper for FakeDatabase {
+        fn rpush<T: redis::ToRedisArgs>(&mut self, key: &str, val: T) -> RedisStorageResult<()> {
+            let values = val
+                .to_redis_args()
+                .into_iter()
+                .map(|x| String::from_utf8_lossy(&x).to_string());
+            self.with_kb(|kb| kb.entry(key.to_string()).or_default().extend(values));
+            Ok(())
+        }
+
+        fn lpush<T: redis::ToRedisArgs>(&mut self, key: &str, val: T) -> RedisStorageResult<()> {
+            self.rpush(key, val)
+        }
+
+        fn del(&mut self, key: &str) -> RedisStorageResult<()> {
+            self.with_kb(|kb| kb.remove(key));
+            Ok(())
+        }
+
+        fn lindex(&mut self, key: &str, index: isize) -> RedisStorageResult<String> {
+            Ok(self
+                .lrange(key, 0, -1)?
+                .get(index as usize)
+                .cloned()
+                .unwrap_or_default())
+        }
+
+        fn lrange(&mut self, key: &str, _: isize, _: isize) -> RedisStorageResult<Vec<String>> {
+            Ok(self.with_kb(|kb| kb.get(key).cloned().unwrap_or_default()))
+        }
+
+        fn keys(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
+            Ok(self.with_kb(|kb| kb.keys().cloned().collect()))
+        }
+
+        fn scan_keys(&mut self, pattern: &str) -> RedisStorageResult<Vec<String>> {
+            // only prefix patterns are used
+            let prefix = pattern.trim_end_matches('*').replace('\\', "");
+            Ok(self.with_kb(|kb| {
+                kb.keys()
+                    .filter(|x| x.starts_with(&prefix))
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        fn pop(&mut self, key: &str) -> RedisStorageResult<Vec<String>> {
+            Ok(self.with_kb(|kb| kb.remove(key).unwrap_or_default()))
+        }
+
+        fn expire(&mut self, _: &str, _: Duration) -> RedisStorageResult<()> {
+            Ok(())
+        }
+    }
+
+    impl KbNamespace for FakeDatabase {
+        fn db(&self) -> u32 {
+            self.db
+        }
+
+        fn delete_namespace(&mut self) -> RedisStorageResult<()> {
+            self.databases.lock().unwrap().remove(&self.db);
+            Ok(())
+        }
+    }
+
+    fn scan_kb(databases: &Databases, db: u32) -> RedisHelper<FakeDatabase> {
+        let connect = || {
+            Arc::new(Mutex::new(FakeDatabase {
+                databases: databases.clone(),
+                db,
+            }))
+        };
+        RedisHelper {
+            cache: connect(),
+            task_kb: connect(),
+        }
+    }
+
+    #[test]
+    fn scans_do_not_share_kb_items() {
+        let databases = Databases::default();
+        let mut scan_a = scan_kb(&databases, 1);
+        let mut scan_b = scan_kb(&databases, 2);
+        // both scans target the same host
+        scan_a.push_kb_item("Ports/tcp/20", 1).unwrap();
+        scan_b.push_kb_item("Ports/tcp/22", 1).unwrap();
+
+        assert_eq!(scan_a.kb_item("Ports/tcp/20").unwrap(), vec!["1"]);
+        assert!(scan_a.kb_item("Ports/tcp/22").unwrap().is_empty());
+        assert!(scan_b.kb_item("Ports/tcp/20").unwrap().is_empty());
+        assert_eq!(scan_b.kb_item("Ports/tcp/22").unwrap(), vec!["1"]);
+
+        scan_a.release().unwrap();
+        assert!(scan_a.kb_item("Ports/tcp/20").unwrap().is_empty());
+        assert_eq!(scan_b.dump_kb().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn kb_items_by_prefix() {
+        let databases = Databases::default();
+        let mut kb = scan_kb(&databases, 1);
+        kb.push_kb_item("Ports/tcp/80", 1).unwrap();
+        kb.push_kb_item("Ports/tcp/22", 1).unwrap();
+        kb.push_kb_item("Ports/udp/161", 1).unwrap();
+        kb.push_kb_item("Host/os", "linux").unwrap();
+        let keys: Vec<_> = kb
+            .kb_items_by_prefix("Ports/tcp/")
+            .unwrap()
+            .into_iter()
+            .map(|kb| kb.key)
+            .collect();
+        assert_eq!(keys, ["Ports/tcp/22", "Ports/tcp/80"]);
+        assert_eq!(kb.kb_items_by_prefix("").unwrap().len(), 4);
+    }
+
+    fn dropped_connection(
+        alive_after_reconnect: bool,
+    ) -> (Reconnecting<FakeConnection>, Arc<AtomicUsize>) {
+        let connects = Arc::new(AtomicUsize::new(0));
+        let counter = connects.clone();
+        let connection = Reconnecting::new(FakeConnection { alive: false }, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(FakeConnection {
+                alive: alive_after_reconnect,
+            })
+        })
+        .with_backoff(Duration::ZERO);
+        (connection, connects)
+    }
+
+    #[test]
+    fn reconnects_on_dropped_connection() {
+        let (mut connection, connects) = dropped_connection(true);
+        assert_eq!(connection.lindex("key", 0).unwrap(), "value");
+        connection.rpush("key", "value").unwrap();
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn does_not_retry_writes() {
+        let (mut connection, connects) = dropped_connection(true);
+        assert!(matches!(
+            connection.rpush("key", "value"),
+            Err(DbError::IoError(_))
+        ));
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+        // the connection got replaced for the following operations
+        connection.lpush("key", "value").unwrap();
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let (connection, connects) = dropped_connection(false);
+        let mut connection = connection.with_max_attempts(2);
+        assert!(matches!(connection.del("key"), Err(DbError::IoError(_))));
+        assert_eq!(connects.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn kb_item_expires_after_ttl() {
+        let mut rc = FakeRedis::default();
+        rc.dispatch_with_ttl("Ports/tcp/22", 1, Duration::from_millis(20))
+            .unwrap();
+        rc.push_kb_item("Ports/tcp/80", 1).unwrap();
+        assert_eq!(rc.kb_item("Ports/tcp/22").unwrap(), vec!["1"]);
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(rc.kb_item("Ports/tcp/22").unwrap().is_empty());
+        assert_eq!(rc.kb_item("Ports/tcp/80").unwrap(), vec!["1"]);
+    }
+
+    #[test]
+    fn dump_kb() {
+        let mut rc = FakeRedis::default();
+        rc.push_kb_item("Ports/tcp/80", 1).unwrap();
+        rc.push_kb_item("Host/os", "linux").unwrap();
+        rc.push_kb_item("Ports/tcp/22", 1).unwrap();
+        rc.push_kb_item("Secret/SSH/password", "s3cr3t").unwrap();
+        rc.dispatch_with_ttl("Ports/udp/161", 1, Duration::ZERO)
+            .unwrap();
+        let kbs: Vec<_> = rc
+            .dump_kb()
+            .unwrap()
+            .into_iter()
+            .map(|kb| (kb.key, kb.value.to_string()))
+            .collect();
+        assert_eq!(
+            kbs,
+            [
+                ("Host/os".to_owned(), "linux".to_owned()),
+                ("Ports/tcp/22".to_owned(), "1".to_owned()),
+                ("Ports/tcp/80".to_owned(), "1".to_owned()),
+            ]
+        );
     }
 }
@@ -3,14 +3,19 @@
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
 use std::collections::HashMap;
+use std::fmt::Display;
+
+use thiserror::Error;
 
 use crate::models::{
-    ports_to_openvas_port_list, AliveTestMethods, CredentialType, Scan, Service, VT,
+    parse_openvas_port_range, ports_to_openvas_port_list, AliveTestMethods, AliveTestMethodsError,
+    Credential, CredentialType, PreferenceValue, Scan, ScanPreference, Service, VT,
 };
-use crate::storage::redis::RedisStorageResult;
+use crate::storage::redis::{DbError, RedisStorageResult};
 
 use super::cmd;
 use super::openvas_redis::{KbAccess, VtHelper};
+use super::preferences::{defaults, PREFERENCES};
 
 const OID_SSH_AUTH: &str = "1.3.6.1.4.1.25623.1.0.103591";
 const OID_SMB_AUTH: &str = "1.3.6.1.4.1.25623.1.0.90023";
@@ -22,6 +27,12 @@ const BOREAS_ALIVE_TEST: &str = "ALIVE_TEST";
 const BOREAS_ALIVE_TEST_PORTS: &str = "ALIVE_TEST_PORTS";
 const ALIVE_TEST_SCAN_CONFIG_DEFAULT: u8 = 0x00;
 
+/// Scan preference containing a port range like `T:1-1024,U:53`
+const PREF_PORT_RANGE: &str = "port_range";
+/// Scan preference containing alive test methods, either as bit mask or as
+/// comma separated names like `icmp,tcp_ack`
+const PREF_ALIVE_TEST: &str = "alive_test";
+
 fn bool_to_str(value: &str) -> String {
     if value == "0" {
         return "no".to_string();
@@ -29,11 +40,202 @@ fn bool_to_str(value: &str) -> String {
     "yes".to_string()
 }
 
+/// Scan preferences that do not match the known preferences
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InvalidPreferences {
+    /// IDs of preferences with a value of the wrong type together with the expected type
+    pub mismatched: Vec<(String, &'static str)>,
+    /// IDs of preferences with a value that cannot be parsed together with the reason
+    pub malformed: Vec<(String, String)>,
+    /// IDs of preferences that are not known, e.g. the ones of a newer openvas
+    ///
+    /// They are passed through unchecked and don't prevent the scan from starting.
+    pub unknown: Vec<String>,
+}
+
+impl InvalidPreferences {
+    /// Returns true when the scan must not be started, which is the case for mismatched and
+    /// malformed preferences
+    pub fn is_fatal(&self) -> bool {
+        !self.mismatched.is_empty() || !self.malformed.is_empty()
+    }
+}
+
+impl Display for InvalidPreferences {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.mismatched.is_empty() {
+            let mismatched = self
+                .mismatched
+                .iter()
+                .map(|(id, expected)| format!("{id} (expected {expected})"))
+                .collect::<Vec<_>>();
+            write!(f, "type mismatches: {}", mismatched.join(", "))?;
+        }
+        if !self.malformed.is_empty() {
+            if !self.mismatched.is_empty() {
+                write!(f, "; ")?;
+            }
+            let malformed = self
+                .malformed
+                .iter()
+                .map(|(id, reason)| format!("{id} ({reason})"))
+                .collect::<Vec<_>>();
+            write!(f, "malformed: {}", malformed.join(", "))?;
+        }
+        if !self.unknown.is_empty() {
+            if self.is_fatal() {
+                write!(f, "; ")?;
+            }
+            write!(f, "unknown: {}", self.unknown.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PreferenceError {
+    #[error("Invalid scan preferences: {0}")]
+    Invalid(InvalidPreferences),
+    #[error("Unable to store preferences: {0}")]
+    Storage(#[from] DbError),
+    #[error("Environment variable {variable} referenced by preference {id} is not set")]
+    UnsetVariable { id: String, variable: String },
+}
+
+/// Replaces references to environment variables like `$SCAN_USER` by the value returned by
+/// `env`.
+///
+/// A name consists of ASCII letters, digits and underscores and must not start with a digit.
+/// `$$` is replaced by a literal `$`, a `$` that is not followed by a name is kept. Returns
+/// the name of the first variable that is not set.
+fn expand_env_vars(value: &str, env: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(i) = rest.find('$') {
+        result.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+        let len = after
+            .char_indices()
+            .find(|(i, c)| {
+                !(c.is_ascii_alphabetic() || *c == '_' || (*i > 0 && c.is_ascii_digit()))
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(after.len());
+        if len == 0 {
+            result.push('$');
+        } else {
+            let name = &after[..len];
+            result.push_str(&env(name).ok_or_else(|| name.to_string())?);
+        }
+        rest = &after[len..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Returns the name of the expected type when the value does not match the
+/// type of the default value.
+fn type_mismatch(default: &PreferenceValue, value: &str) -> Option<&'static str> {
+    match default {
+        PreferenceValue::Bool(_) => {
+            (!matches!(value, "yes" | "no" | "true" | "false" | "1" | "0")).then_some("bool")
+        }
+        PreferenceValue::Int(_) => value.parse::<i64>().is_err().then_some("integer"),
+        PreferenceValue::String(_) => None,
+    }
+}
+
+/// Parses alive test methods given either as bit mask or as comma separated names.
+fn parse_alive_test(value: &str) -> Result<Vec<AliveTestMethods>, AliveTestMethodsError> {
+    let value = value.trim();
+    let Ok(bits) = value.parse::<u8>() else {
+        return value.split(',').map(|x| x.trim().parse()).collect();
+    };
+    if bits > 0x1f {
+        return Err(AliveTestMethodsError::InvalidValue(bits));
+    }
+    (0..5)
+        .map(|i| 1 << i)
+        .filter(|x| bits & x != 0)
+        .map(AliveTestMethods::try_from)
+        .collect::<Result<_, _>>()
+}
+
+/// Returns the KB items of a credential as they are expected by the NASL standard library.
+///
+/// The values are secrets and must never be logged.
+fn secret_kb_items(credential: &Credential) -> Vec<(&'static str, String)> {
+    match (&credential.service, &credential.credential_type) {
+        (
+            Service::SSH,
+            CredentialType::UP {
+                username, password, ..
+            },
+        ) => vec![
+            ("Secret/SSH/login", username.clone()),
+            ("Secret/SSH/password", password.clone()),
+        ],
+        (
+            Service::SSH,
+            CredentialType::USK {
+                username,
+                password,
+                private_key,
+                ..
+            },
+        ) => vec![
+            ("Secret/SSH/login", username.clone()),
+            ("Secret/SSH/passphrase", password.clone()),
+            ("Secret/SSH/privatekey", private_key.clone()),
+        ],
+        (
+            Service::SMB,
+            CredentialType::UP {
+                username, password, ..
+            },
+        ) => vec![
+            ("Secret/SMB/login", username.clone()),
+            ("Secret/SMB/password", password.clone()),
+        ],
+        (
+            Service::ESXi,
+            CredentialType::UP {
+                username, password, ..
+            },
+        ) => vec![
+            ("Secret/ESXi/login", username.clone()),
+            ("Secret/ESXi/password", password.clone()),
+        ],
+        (
+            Service::SNMP,
+            CredentialType::SNMP {
+                username,
+                password,
+                community,
+                privacy_password,
+                ..
+            },
+        ) => vec![
+            ("Secret/SNMP/community", community.clone()),
+            ("Secret/SNMP/v3/username", username.clone()),
+            ("Secret/SNMP/v3/password", password.clone()),
+            ("Secret/SNMP/v3/privacy_password", privacy_password.clone()),
+        ],
+        _ => vec![],
+    }
+}
+
 #[derive(Debug)]
 pub struct PreferenceHandler<'a, H> {
     scan_config: Scan,
     redis_connector: &'a mut H,
     nvt_params: HashMap<String, String>,
+    expand_env: bool,
 }
 
 impl<'a, H> PreferenceHandler<'a, H>
@@ -45,10 +247,107 @@ where
             scan_config,
             redis_connector,
             nvt_params: HashMap::new(),
+            expand_env: false,
+        }
+    }
+
+    /// Sets whether references to environment variables like `$SCAN_USER` within string
+    /// preferences are replaced by their values.
+    ///
+    /// A referenced variable that is not set is an error, `$$` results in a literal `$`.
+    /// Disabled by default.
+    pub fn with_env_expansion(mut self, expand_env: bool) -> Self {
+        self.expand_env = expand_env;
+        self
+    }
+
+    /// Expands the environment variables within the string preferences of the scan
+    fn expand_scan_preferences(
+        &mut self,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), PreferenceError> {
+        for pref in self.scan_config.scan_preferences.iter_mut() {
+            let is_string = PREFERENCES
+                .iter()
+                .find(|x| x.id == pref.id)
+                .is_some_and(|x| matches!(x.default, PreferenceValue::String(_)));
+            if !is_string {
+                continue;
+            }
+            pref.value = expand_env_vars(&pref.value, &env).map_err(|variable| {
+                PreferenceError::UnsetVariable {
+                    id: pref.id.clone(),
+                    variable,
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the values of the known scan preferences have the expected type.
+    ///
+    /// Preferences that are not known, e.g. the ones of a newer openvas, are passed through
+    /// unchecked with a warning. They are returned as well, but only mismatched and malformed
+    /// preferences are fatal, see [`InvalidPreferences::is_fatal`].
+    pub fn validate_scan_preferences(&self) -> InvalidPreferences {
+        let mut invalid = InvalidPreferences::default();
+        for pref in &self.scan_config.scan_preferences {
+            let malformed = match pref.id.as_str() {
+                PREF_PORT_RANGE => parse_openvas_port_range(&pref.value)
+                    .err()
+                    .map(|e| e.to_string()),
+                PREF_ALIVE_TEST => parse_alive_test(&pref.value).err().map(|e| e.to_string()),
+                _ => None,
+            };
+            if let Some(reason) = malformed {
+                invalid.malformed.push((pref.id.clone(), reason));
+                continue;
+            }
+            if [PREF_PORT_RANGE, PREF_ALIVE_TEST].contains(&pref.id.as_str()) {
+                continue;
+            }
+            match PREFERENCES.iter().find(|x| x.id == pref.id) {
+                None => {
+                    tracing::warn!(id = pref.id, "passing unknown scan preference through");
+                    invalid.unknown.push(pref.id.clone());
+                }
+                Some(known) => {
+                    if let Some(expected) = type_mismatch(&known.default, &pref.value) {
+                        invalid.mismatched.push((pref.id.clone(), expected));
+                    }
+                }
+            }
+        }
+        invalid
+    }
+
+    fn scan_preference(&self, id: &str) -> Option<&str> {
+        self.scan_config
+            .scan_preferences
+            .iter()
+            .find(|x| x.id == id)
+            .map(|x| x.value.as_str())
+    }
+
+    /// Alive test methods of the target extended by the ones of the `alive_test` preference
+    fn alive_test_methods(&self) -> Vec<AliveTestMethods> {
+        let mut methods = self.scan_config.target.alive_test_methods.clone();
+        if let Some(value) = self.scan_preference(PREF_ALIVE_TEST) {
+            // malformed values are rejected by validate_scan_preferences
+            methods.extend(parse_alive_test(value).unwrap_or_default());
         }
+        methods
     }
 
-    pub async fn prepare_preferences_for_openvas(&mut self) -> RedisStorageResult<()> {
+    pub async fn prepare_preferences_for_openvas(&mut self) -> Result<(), PreferenceError> {
+        // nothing must be stored when the scan is misconfigured
+        let invalid = self.validate_scan_preferences();
+        if invalid.is_fatal() {
+            return Err(PreferenceError::Invalid(invalid));
+        }
+        if self.expand_env {
+            self.expand_scan_preferences(|x| std::env::var(x).ok())?;
+        }
         self.prepare_scan_id_for_openvas().await?;
         self.prepare_target_for_openvas().await?;
         self.prepare_ports_for_openvas().await?;
@@ -64,7 +363,8 @@ where
         // since alive tests preferences have to be able to overwrite default
         // preferences of ping_host.nasl for the classic method.
         self.prepare_nvt_preferences().await?;
-        self.prepare_boreas_alive_test().await
+        self.prepare_boreas_alive_test().await?;
+        Ok(())
     }
 
     async fn prepare_main_kbindex_for_openvas(&mut self) -> RedisStorageResult<()> {
@@ -169,7 +469,7 @@ where
         let mut alive_test = ALIVE_TEST_SCAN_CONFIG_DEFAULT;
         let mut value: &str = "no";
 
-        let methods = self.scan_config.target.alive_test_methods.clone();
+        let methods = self.alive_test_methods();
         for m in methods {
             alive_test |= m as u8;
         }
@@ -256,7 +556,7 @@ where
             }
         }
 
-        let methods = self.scan_config.target.alive_test_methods.clone();
+        let methods = self.alive_test_methods();
 
         let mut alive_test = ALIVE_TEST_SCAN_CONFIG_DEFAULT;
         for m in methods {
@@ -327,8 +627,12 @@ where
     }
 
     async fn prepare_ports_for_openvas(&mut self) -> RedisStorageResult<()> {
-        let ports = self.scan_config.target.ports.clone();
-        if let Some(ports) = ports_to_openvas_port_list(ports) {
+        // a port range given as preference replaces the ports of the target
+        let ports = match self.scan_preference(PREF_PORT_RANGE) {
+            Some(port_range) => Some(port_range.trim().to_string()),
+            None => ports_to_openvas_port_list(self.scan_config.target.ports.clone()),
+        };
+        if let Some(ports) = ports {
             self.redis_connector.push_kb_item(
                 format!("internal/{}/scanprefs", self.scan_config.scan_id.clone()).as_str(),
                 format!("port_range|||{}", ports),
@@ -350,12 +654,24 @@ where
         )
     }
 
+    /// Scan preferences of the scan filled up with the defaults of all
+    /// preferences not supplied by the scan.
+    fn merged_scan_preferences(&self) -> Vec<ScanPreference> {
+        let supplied = &self.scan_config.scan_preferences;
+        let mut merged: Vec<ScanPreference> = defaults()
+            .into_iter()
+            .filter(|d| !supplied.iter().any(|x| x.id == d.id))
+            .collect();
+        merged.extend(supplied.iter().cloned());
+        merged
+    }
+
     async fn prepare_scan_params_for_openvas(&mut self) -> RedisStorageResult<()> {
         let options = self
-            .scan_config
-            .scan_preferences
-            .clone()
+            .merged_scan_preferences()
             .iter()
+            // typed preferences are handled separately
+            .filter(|x| ![PREF_PORT_RANGE, PREF_ALIVE_TEST].contains(&x.id.as_str()))
             .map(|x| format!("{}|||{}", x.id, x.value))
             .collect::<Vec<String>>();
 
@@ -372,7 +688,11 @@ where
         let credentials = self.scan_config.target.credentials.clone();
 
         let mut credential_preferences: Vec<String> = vec![];
+        let mut secrets = vec![];
         for credential in credentials {
+            // The Debug implementation of a credential redacts the secrets
+            tracing::debug!(?credential, "preparing credential");
+            let credential_secrets = secret_kb_items(&credential);
             match credential.service {
                 Service::SSH => {
                     if let Some(port) = credential.port {
@@ -519,6 +839,7 @@ where
                     }
                 }
             }
+            secrets.extend(credential_secrets);
         }
 
         if !credential_preferences.is_empty() {
@@ -527,20 +848,23 @@ where
                 credential_preferences,
             )?;
         }
+        for (key, value) in secrets {
+            tracing::trace!(key, "storing credential");
+            self.redis_connector.push_kb_item(key, value)?;
+        }
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use crate::models::{
         AliveTestMethods, Credential, CredentialType, Port, PortRange, Protocol, Scan, Service,
     };
 
-    use super::PreferenceHandler;
+    use super::{expand_env_vars, InvalidPreferences, PreferenceError, PreferenceHandler};
     use crate::openvas::openvas_redis::{FakeRedis, KbAccess};
+    use crate::openvas::PREFERENCES;
 
     #[tokio::test]
     async fn test_prefs() {
@@ -597,9 +921,7 @@ mod tests {
             ],
         }];
 
-        let mut rc = FakeRedis {
-            data: HashMap::new(),
-        };
+        let mut rc = FakeRedis::default();
 
         let mut prefh = PreferenceHandler::new(scan, &mut rc);
         assert_eq!(prefh.redis_connector.kb_id().unwrap(), 3);
@@ -717,4 +1039,223 @@ mod tests {
             .redis_connector
             .item_exists("internal/123-456/scanprefs", "ALIVE_TEST|||18"));
     }
+
+    fn scan_with_preferences(prefs: &[(&str, &str)]) -> Scan {
+        Scan {
+            scan_id: "123-456".to_string(),
+            scan_preferences: prefs
+                .iter()
+                .map(|(id, value)| crate::models::ScanPreference {
+                    id: id.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn unknown_preference() {
+        let scan = scan_with_preferences(&[("safe_checks", "yes"), ("not_a_pref", "1")]);
+        let mut rc = FakeRedis::default();
+        let mut prefh = PreferenceHandler::new(scan, &mut rc);
+        let invalid = prefh.validate_scan_preferences();
+        assert!(!invalid.is_fatal());
+        assert_eq!(invalid.unknown, ["not_a_pref"]);
+        prefh.prepare_scan_params_for_openvas().await.unwrap();
+        assert!(rc.item_exists("internal/123-456/scanprefs", "not_a_pref|||1"));
+        assert!(logs_contain("passing unknown scan preference through"));
+    }
+
+    #[test]
+    fn type_mismatched_preference() {
+        let scan = scan_with_preferences(&[
+            ("checks_read_timeout", "five"),
+            ("safe_checks", "maybe"),
+            ("cgi_path", "/cgi-bin"),
+        ]);
+        let mut rc = FakeRedis::default();
+        let prefh = PreferenceHandler::new(scan, &mut rc);
+        assert_eq!(
+            prefh.validate_scan_preferences(),
+            InvalidPreferences {
+                mismatched: vec![
+                    ("checks_read_timeout".to_string(), "integer"),
+                    ("safe_checks".to_string(), "bool"),
+                ],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn credentials_as_secrets() {
+        let mut scan = scan_with_preferences(&[]);
+        scan.target.credentials = vec![
+            Credential {
+                service: Service::SSH,
+                port: None,
+                credential_type: CredentialType::UP {
+                    username: "user".to_string(),
+                    password: "ssh-s3cr3t".to_string(),
+                    privilege: None,
+                },
+            },
+            Credential {
+                service: Service::SMB,
+                port: None,
+                credential_type: CredentialType::UP {
+                    username: "admin".to_string(),
+                    password: "smb-s3cr3t".to_string(),
+                    privilege: None,
+                },
+            },
+        ];
+        let mut rc = FakeRedis::default();
+        let mut prefh = PreferenceHandler::new(scan, &mut rc);
+        prefh.prepare_credentials_for_openvas().await.unwrap();
+        assert!(rc.item_exists("Secret/SSH/login", "user"));
+        assert!(rc.item_exists("Secret/SSH/password", "ssh-s3cr3t"));
+        assert!(rc.item_exists("Secret/SMB/login", "admin"));
+        assert!(rc.item_exists("Secret/SMB/password", "smb-s3cr3t"));
+        // secrets are not part of a dump and are removed with the KB
+        assert!(rc
+            .dump_kb()
+            .unwrap()
+            .iter()
+            .all(|x| !x.key.starts_with("Secret/") && !x.key.starts_with("internal/")));
+        rc.release().unwrap();
+        assert!(!rc.item_exists("Secret/SSH/password", "ssh-s3cr3t"));
+
+        assert!(logs_contain("Secret/SSH/password"));
+        assert!(!logs_contain("ssh-s3cr3t"));
+        assert!(!logs_contain("smb-s3cr3t"));
+    }
+
+    #[tokio::test]
+    async fn port_range_and_alive_test_preferences() {
+        let scan =
+            scan_with_preferences(&[("port_range", "T:1-1024,U:53"), ("alive_test", "icmp,arp")]);
+        let mut rc = FakeRedis::default();
+        let mut prefh = PreferenceHandler::new(scan, &mut rc);
+        assert_eq!(
+            prefh.validate_scan_preferences(),
+            InvalidPreferences::default()
+        );
+
+        prefh.prepare_ports_for_openvas().await.unwrap();
+        assert!(prefh
+            .redis_connector
+            .item_exists("internal/123-456/scanprefs", "port_range|||T:1-1024,U:53"));
+
+        prefh.prepare_alive_test_option_for_openvas().await.unwrap();
+        prefh.prepare_nvt_preferences().await.unwrap();
+        assert!(prefh.redis_connector.item_exists(
+            "internal/123-456/scanprefs",
+            "1.3.6.1.4.1.25623.1.0.100315:3:checkbox:Do an ICMP ping|||yes"
+        ));
+        assert!(prefh.redis_connector.item_exists(
+            "internal/123-456/scanprefs",
+            "1.3.6.1.4.1.25623.1.0.100315:4:checkbox:Use ARP|||yes"
+        ));
+        assert!(prefh.redis_connector.item_exists(
+            "internal/123-456/scanprefs",
+            "1.3.6.1.4.1.25623.1.0.100315:1:checkbox:Do a TCP ping|||no"
+        ));
+    }
+
+    #[test]
+    fn malformed_port_range_and_alive_test() {
+        let scan = scan_with_preferences(&[
+            ("port_range", "T:1-1024,U:53-1"),
+            ("alive_test", "icmp,ping"),
+        ]);
+        let mut rc = FakeRedis::default();
+        let prefh = PreferenceHandler::new(scan, &mut rc);
+        assert_eq!(
+            prefh.validate_scan_preferences(),
+            InvalidPreferences {
+                malformed: vec![
+                    (
+                        "port_range".to_string(),
+                        "Start port 53 is greater than end port 1".to_string()
+                    ),
+                    (
+                        "alive_test".to_string(),
+                        "Invalid alive test method: ping".to_string()
+                    ),
+                ],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn merge_with_default_preferences() {
+        let scan = scan_with_preferences(&[("checks_read_timeout", "10")]);
+        let mut rc = FakeRedis::default();
+        let mut prefh = PreferenceHandler::new(scan, &mut rc);
+        let merged = prefh.merged_scan_preferences();
+        assert_eq!(merged.len(), PREFERENCES.len());
+        let value = |id: &str| merged.iter().find(|x| x.id == id).map(|x| x.value.as_str());
+        assert_eq!(value("checks_read_timeout"), Some("10"));
+        assert_eq!(value("cgi_path"), Some("/cgi-bin:/scripts"));
+        assert_eq!(value("auto_enable_dependencies"), Some("yes"));
+        assert_eq!(value("dry_run"), Some("no"));
+
+        prefh.prepare_scan_params_for_openvas().await.unwrap();
+        assert!(prefh
+            .redis_connector
+            .item_exists("internal/123-456/scanprefs", "checks_read_timeout|||10"));
+        assert!(!prefh
+            .redis_connector
+            .item_exists("internal/123-456/scanprefs", "checks_read_timeout|||5"));
+        assert!(prefh
+            .redis_connector
+            .item_exists("internal/123-456/scanprefs", "results_per_host|||10"));
+    }
+
+    #[test]
+    fn env_vars() {
+        let env = |x: &str| match x {
+            "SCAN_USER" => Some("admin".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(expand_env_vars("$SCAN_USER", env), Ok("admin".to_string()));
+        assert_eq!(
+            expand_env_vars("/home/$SCAN_USER/x$EMPTY", env),
+            Ok("/home/admin/x".to_string())
+        );
+        assert_eq!(expand_env_vars("$UNSET", env), Err("UNSET".to_string()));
+        assert_eq!(
+            expand_env_vars("$$SCAN_USER", env),
+            Ok("$SCAN_USER".to_string())
+        );
+        assert_eq!(expand_env_vars("a$ $1 $", env), Ok("a$ $1 $".to_string()));
+    }
+
+    #[tokio::test]
+    async fn env_expansion() {
+        let scan =
+            scan_with_preferences(&[("cgi_path", "/$CGI_DIR"), ("checks_read_timeout", "5")]);
+        let mut rc = FakeRedis::default();
+        let mut prefh = PreferenceHandler::new(scan, &mut rc);
+        prefh
+            .expand_scan_preferences(|x| (x == "CGI_DIR").then(|| "cgi-bin".to_string()))
+            .unwrap();
+        let value = |id: &str| prefh.scan_preference(id).map(|x| x.to_string());
+        assert_eq!(value("cgi_path").as_deref(), Some("/cgi-bin"));
+
+        let scan = scan_with_preferences(&[("cgi_path", "$OPENVAS_TEST_UNSET_VARIABLE")]);
+        let mut prefh = PreferenceHandler::new(scan, &mut rc).with_env_expansion(true);
+        assert!(matches!(
+            prefh.prepare_preferences_for_openvas().await,
+            Err(PreferenceError::UnsetVariable { id, variable })
+                if id == "cgi_path" && variable == "OPENVAS_TEST_UNSET_VARIABLE"
+        ));
+        assert!(rc.data.is_empty());
+    }
 }
@@ -5,13 +5,21 @@
 /// This file contains structs and methods for retrieve scan information from redis
 /// and store it into the given storage to be collected later for the clients.
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Cursor},
     str::FromStr,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
+use futures::{stream, Stream};
+use quick_xml::events::{BytesText, Event};
+
+use crate::models::cvss_base_score;
 use crate::openvas::openvas_redis::{KbAccess, VtHelper};
 use crate::osp::{OspResultType, OspScanResult};
+use crate::storage::item::{Nvt, TagKey, TagValue};
 use crate::storage::redis::RedisStorageResult;
 
 /// Structure to hold the results retrieve from redis main kb
@@ -35,9 +43,263 @@ pub struct Results {
     pub scan_status: String,
 }
 
+/// Severity of the VT a result belongs to
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Severity {
+    /// The CVSS base vector of the VT
+    pub vector: String,
+    /// The base score calculated from the vector, None if it cannot be calculated
+    pub score: Option<f32>,
+}
+
+impl Severity {
+    /// Returns the severity of a VT, None if it has no severity metadata
+    pub fn of(vt: &Nvt) -> Option<Self> {
+        let vector = [TagKey::SeverityVector, TagKey::CvssBaseVector]
+            .iter()
+            .find_map(|key| match vt.tag.get(key) {
+                Some(TagValue::String(x)) if !x.is_empty() => Some(x.clone()),
+                _ => None,
+            })?;
+        Some(Self {
+            score: cvss_base_score(&vector),
+            vector,
+        })
+    }
+}
+
+/// A result joined with the severity of its VT
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnrichedResult {
+    pub result: OspScanResult,
+    /// None if the VT is unknown or has no severity metadata
+    pub severity: Option<Severity>,
+}
+
+/// Criteria for selecting results, all given criteria must match.
+#[derive(Debug, Clone, Default)]
+pub struct ResultFilter {
+    /// IP address of the host of a result
+    pub host: Option<String>,
+    /// Minimal severity score, results without a score do not match
+    pub min_severity: Option<f32>,
+    /// OIDs of the VTs of a result, empty allows every OID
+    pub oids: Vec<String>,
+}
+
+impl ResultFilter {
+    pub fn matches(&self, result: &EnrichedResult) -> bool {
+        let host = match &self.host {
+            Some(host) => result.result.host.as_ref() == Some(host),
+            None => true,
+        };
+        let severity = match self.min_severity {
+            Some(min) => result
+                .severity
+                .as_ref()
+                .and_then(|x| x.score)
+                .is_some_and(|x| x >= min),
+            None => true,
+        };
+        let oid = self.oids.is_empty()
+            || result
+                .result
+                .test_id
+                .as_ref()
+                .is_some_and(|x| self.oids.contains(x));
+        host && severity && oid
+    }
+}
+
+/// Version of the JSON schema produced by [`to_json`]. It must be increased
+/// on every change of the schema.
+pub const RESULTS_SCHEMA_VERSION: u32 = 1;
+
+/// JSON representation of collected results
+#[derive(Debug, serde::Serialize)]
+struct JsonResults<'a> {
+    schema_version: u32,
+    results: Vec<JsonResult<'a>>,
+}
+
+/// JSON representation of a single result
+#[derive(Debug, serde::Serialize)]
+struct JsonResult<'a> {
+    oid: Option<&'a str>,
+    host: Option<&'a str>,
+    port: Option<&'a str>,
+    severity: Option<&'a Severity>,
+    message: &'a str,
+}
+
+impl<'a> From<&'a EnrichedResult> for JsonResult<'a> {
+    fn from(value: &'a EnrichedResult) -> Self {
+        let non_empty = |x: &'a Option<String>| x.as_deref().filter(|x| !x.is_empty());
+        Self {
+            oid: non_empty(&value.result.test_id),
+            host: non_empty(&value.result.host),
+            port: non_empty(&value.result.port),
+            severity: value.severity.as_ref(),
+            message: &value.result.description,
+        }
+    }
+}
+
+/// Serializes results into JSON.
+///
+/// The schema is
+/// `{"schema_version": 1, "results": [{"oid", "host", "port", "severity": {"vector", "score"}, "message"}]}`,
+/// fields without a value are null.
+pub fn to_json(results: &[EnrichedResult]) -> serde_json::Result<String> {
+    serde_json::to_string(&JsonResults {
+        schema_version: RESULTS_SCHEMA_VERSION,
+        results: results.iter().map(JsonResult::from).collect(),
+    })
+}
+
+/// Replaces characters that are not allowed in XML 1.0, even when escaped, by U+FFFD.
+///
+/// Descriptions may contain e.g. raw banners of a service, which can contain any control
+/// character.
+fn xml_text(text: &str) -> Cow<'_, str> {
+    let allowed = |c: char| {
+        matches!(c, '\t' | '\n' | '\r') || (c >= ' ' && c != '\u{FFFE}' && c != '\u{FFFF}')
+    };
+    if text.chars().all(allowed) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(
+            text.chars()
+                .map(|c| {
+                    if allowed(c) {
+                        c
+                    } else {
+                        char::REPLACEMENT_CHARACTER
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+type XmlWriter = quick_xml::Writer<Cursor<Vec<u8>>>;
+
+fn write_text_element(writer: &mut XmlWriter, name: &str, text: &str) -> io::Result<()> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(&xml_text(text)))?;
+    Ok(())
+}
+
+fn write_xml_result(writer: &mut XmlWriter, result: &EnrichedResult) -> io::Result<()> {
+    let non_empty = |x: &Option<String>| x.clone().filter(|x| !x.is_empty());
+    let osp = &result.result;
+    if let Some(host) = non_empty(&osp.host) {
+        writer
+            .create_element("host")
+            .write_inner_content(|writer| {
+                writer.write_event(Event::Text(BytesText::new(&xml_text(&host))))?;
+                if let Some(hostname) = non_empty(&osp.hostname) {
+                    write_text_element(writer, "hostname", &hostname)?;
+                }
+                Ok(())
+            })?;
+    }
+    if let Some(port) = non_empty(&osp.port) {
+        write_text_element(writer, "port", &port)?;
+    }
+    if let Some(oid) = non_empty(&osp.test_id) {
+        writer
+            .create_element("nvt")
+            .with_attribute(("oid", xml_text(&oid).as_ref()))
+            .write_empty()?;
+    }
+    if let Some(score) = result.severity.as_ref().and_then(|x| x.score) {
+        write_text_element(writer, "severity", &format!("{score:.1}"))?;
+    }
+    write_text_element(writer, "description", &osp.description)
+}
+
+/// Serializes results into an XML report in the shape of a GMP report.
+///
+/// The document is `<report><results><result>...</result></results></report>`, each result
+/// contains `<host>` with an optional `<hostname>`, `<port>`, `<nvt oid="..."/>`, `<severity>`
+/// with the base score and `<description>`. Elements without a value are omitted. All text
+/// is escaped.
+pub fn to_xml(results: &[EnrichedResult]) -> io::Result<String> {
+    let mut writer = XmlWriter::new(Cursor::new(Vec::new()));
+    writer
+        .create_element("report")
+        .write_inner_content(|writer| {
+            writer
+                .create_element("results")
+                .write_inner_content(|writer| {
+                    for result in results {
+                        writer
+                            .create_element("result")
+                            .write_inner_content(|writer| write_xml_result(writer, result))?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+    String::from_utf8(writer.into_inner().into_inner())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Header of the CSV produced by [`to_csv`], the columns must stay stable.
+pub const RESULTS_CSV_HEADER: &str = "host,port,protocol,oid,severity,name";
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Serializes results into CSV as described in RFC 4180.
+///
+/// The first line is [`RESULTS_CSV_HEADER`]. The port of a result (e.g. `22/tcp`) is split into
+/// port and protocol, missing values are empty cells.
+pub fn to_csv(results: &[EnrichedResult]) -> String {
+    let mut csv = format!("{RESULTS_CSV_HEADER}\r\n");
+    for result in results {
+        let osp = &result.result;
+        let (port, protocol) = match osp.port.as_deref().unwrap_or_default().split_once('/') {
+            Some((port, protocol)) => (port, protocol),
+            None => (osp.port.as_deref().unwrap_or_default(), ""),
+        };
+        let severity = result
+            .severity
+            .as_ref()
+            .and_then(|x| x.score)
+            .map(|x| format!("{x:.1}"))
+            .unwrap_or_default();
+        let fields = [
+            osp.host.as_deref().unwrap_or_default(),
+            port,
+            protocol,
+            osp.test_id.as_deref().unwrap_or_default(),
+            &severity,
+            &osp.name,
+        ];
+        let row = fields.map(csv_field).join(",");
+        csv.push_str(&row);
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Identity of a result: OID, host, port and description
+type ResultKey = (Option<String>, Option<String>, Option<String>, String);
+
 pub struct ResultHelper<'a, H> {
     pub redis_connector: &'a mut H,
     pub results: Arc<Mutex<Results>>,
+    /// Identities of the results seen so far, only tracked when deduplicating
+    seen: Option<HashSet<ResultKey>>,
 }
 
 impl<'a, H> ResultHelper<'a, H>
@@ -48,13 +310,42 @@ where
         Self {
             redis_connector,
             results: Arc::new(Mutex::new(Results::default())),
+            seen: None,
+        }
+    }
+
+    /// Drops results which are exact duplicates of an already collected
+    /// result for the same OID, host and port. The first occurrence is kept.
+    pub fn with_dedup(mut self) -> Self {
+        self.seen = Some(HashSet::new());
+        self
+    }
+
+    fn is_duplicate(&mut self, result: &OspScanResult) -> bool {
+        match &mut self.seen {
+            Some(seen) => !seen.insert((
+                result.test_id.clone(),
+                result.host.clone(),
+                result.port.clone(),
+                result.description.clone(),
+            )),
+            None => false,
         }
     }
 
     fn process_results(&mut self, ov_results: Vec<String>) -> RedisStorageResult<()> {
+        let scan_results = self.parse_results(ov_results)?;
+        if let Ok(mut results) = Arc::as_ref(&self.results).lock() {
+            results.results = scan_results;
+        }
+        Ok(())
+    }
+
+    /// Parses the results retrieved from redis and updates the host counts.
+    fn parse_results(&mut self, ov_results: Vec<String>) -> RedisStorageResult<Vec<OspScanResult>> {
         let mut new_dead = 0;
-        let mut count_total = 0;
-        let mut count_excluded = 0;
+        let mut count_total = None;
+        let mut count_excluded = None;
 
         let mut scan_results: Vec<OspScanResult> = Vec::new();
         for result in ov_results.iter() {
@@ -137,19 +428,24 @@ where
             } else if result_type == "DEADHOST" {
                 new_dead += i64::from_str(&value).expect("Valid amount of dead hosts");
             } else if host_count {
-                count_total = i64::from_str(&value).expect("Valid amount of dead hosts");
+                count_total = Some(i64::from_str(&value).expect("Valid amount of dead hosts"));
             } else if excluded_hosts {
-                count_excluded = i64::from_str(&value).expect("Valid amount of excluded hosts");
+                count_excluded =
+                    Some(i64::from_str(&value).expect("Valid amount of excluded hosts"));
             }
         }
         if let Ok(mut results) = Arc::as_ref(&self.results).lock() {
-            results.results = scan_results;
             results.count_dead += new_dead;
-            results.count_excluded = count_excluded;
-            results.count_total = count_total;
+            if let Some(count_excluded) = count_excluded {
+                results.count_excluded = count_excluded;
+            }
+            if let Some(count_total) = count_total {
+                results.count_total = count_total;
+            }
         }
 
-        Ok(())
+        scan_results.retain(|x| !self.is_duplicate(x));
+        Ok(scan_results)
     }
 
     pub async fn collect_results(&mut self) -> RedisStorageResult<()> {
@@ -159,6 +455,81 @@ where
         Ok(())
     }
 
+    /// Joins a result with the severity of its VT.
+    pub fn enrich(&self, result: OspScanResult) -> RedisStorageResult<EnrichedResult> {
+        let severity = match result.test_id.as_deref().filter(|x| !x.is_empty()) {
+            Some(oid) => self
+                .redis_connector
+                .get_vt(oid)?
+                .and_then(|vt| Severity::of(&vt)),
+            None => None,
+        };
+        Ok(EnrichedResult { result, severity })
+    }
+
+    /// Returns the collected results joined with the severity of their VTs.
+    pub fn enriched_results(&self) -> RedisStorageResult<Vec<EnrichedResult>> {
+        let results = Arc::as_ref(&self.results)
+            .lock()
+            .map(|x| x.results.clone())
+            .unwrap_or_default();
+        results.into_iter().map(|x| self.enrich(x)).collect()
+    }
+
+    /// Returns the collected results matching the given filter.
+    pub fn filter(&self, filter: ResultFilter) -> RedisStorageResult<Vec<EnrichedResult>> {
+        let mut results = self.enriched_results()?;
+        results.retain(|x| filter.matches(x));
+        Ok(results)
+    }
+
+    /// Streams the results of a scan as soon as they are retrieved from redis.
+    ///
+    /// In contrast to [`Self::collect_results`] the results are not gathered
+    /// in [`Results`], only the host counts are updated. Redis is polled every
+    /// `poll_interval` while no results are available. The stream ends once
+    /// openvas finished or stopped the scan and all results have been yielded.
+    pub fn into_stream(
+        self,
+        scan_id: String,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = RedisStorageResult<OspScanResult>> + 'a {
+        stream::unfold(
+            (self, VecDeque::new(), false),
+            move |(mut helper, mut pending, mut finished)| {
+                let scan_id = scan_id.clone();
+                async move {
+                    loop {
+                        if let Some(result) = pending.pop_front() {
+                            return Some((Ok(result), (helper, pending, finished)));
+                        }
+                        if finished {
+                            return None;
+                        }
+                        // The status is read before the results, so that no
+                        // result stored before the scan finished gets lost.
+                        let status = match helper.redis_connector.scan_status(scan_id.clone()) {
+                            Ok(status) => status,
+                            Err(e) => return Some((Err(e), (helper, pending, true))),
+                        };
+                        finished = status == "finished" || status == "stopped";
+                        match helper
+                            .redis_connector
+                            .results()
+                            .and_then(|x| helper.parse_results(x))
+                        {
+                            Ok(results) => pending.extend(results),
+                            Err(e) => return Some((Err(e), (helper, pending, true))),
+                        }
+                        if pending.is_empty() && !finished {
+                            tokio::time::sleep(poll_interval).await;
+                        }
+                    }
+                }
+            },
+        )
+    }
+
     fn process_status(&self, redis_status: Vec<String>) -> RedisStorageResult<()> {
         enum ScanProgress {
             DeadHost = -1,
@@ -224,10 +595,18 @@ where
 mod tests {
 
     use crate::models::{self, Protocol, Result, ResultType};
-    use crate::openvas::openvas_redis::FakeRedis;
+    use crate::openvas::openvas_redis::{FakeRedis, KbAccess, VtHelper};
+    use crate::osp::{OspResultType, OspScanResult};
+    use crate::storage::item::{Nvt, TagKey, TagValue};
+    use crate::storage::redis::RedisStorageResult;
+    use crate::storage::Kb;
+    use futures::StreamExt;
     use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
-    use super::ResultHelper;
+    use super::{to_csv, to_json, to_xml, EnrichedResult, ResultFilter, ResultHelper, Severity};
     #[test]
     fn test_results() {
         let results = vec![
@@ -241,9 +620,7 @@ mod tests {
 
         ];
 
-        let mut rc = FakeRedis {
-            data: HashMap::new(),
-        };
+        let mut rc = FakeRedis::default();
 
         let mut resh = ResultHelper::init(&mut rc);
 
@@ -338,9 +715,7 @@ mod tests {
             "127.0.0.5/0/-1".to_string(),
         ];
 
-        let mut rc = FakeRedis {
-            data: HashMap::new(),
-        };
+        let mut rc = FakeRedis::default();
 
         let resh = ResultHelper::init(&mut rc);
         resh.process_status(status).unwrap();
@@ -356,4 +731,346 @@ mod tests {
         assert_eq!(resh.results.as_ref().lock().unwrap().count_alive, 1);
         assert_eq!(resh.results.as_ref().lock().unwrap().count_dead, 2);
     }
+
+    /// Redis of a running openvas scan, which can be finished and filled with
+    /// results from the outside.
+    #[derive(Clone, Default)]
+    struct SimulatedScan {
+        results: Arc<Mutex<Vec<String>>>,
+        finished: Arc<AtomicBool>,
+    }
+
+    impl KbAccess for SimulatedScan {
+        fn push_kb_item<T: redis::ToRedisArgs>(&mut self, _: &str, _: T) -> RedisStorageResult<()> {
+            Ok(())
+        }
+
+        fn dispatch_with_ttl<T: redis::ToRedisArgs>(
+            &mut self,
+            _: &str,
+            _: T,
+            _: Duration,
+        ) -> RedisStorageResult<()> {
+            Ok(())
+        }
+
+        fn kb_item(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
+            Ok(vec![])
+        }
+
+        fn kb_items_by_prefix(&mut self, _: &str) -> RedisStorageResult<Vec<Kb>> {
+            Ok(vec![])
+        }
+
+        fn scan_status(&mut self, _: String) -> RedisStorageResult<String> {
+            match self.finished.load(Ordering::SeqCst) {
+                true => Ok("finished".to_string()),
+                false => Ok("ready".to_string()),
+            }
+        }
+
+        fn kb_id(&self) -> RedisStorageResult<u32> {
+            Ok(3)
+        }
+
+        fn results(&mut self) -> RedisStorageResult<Vec<String>> {
+            Ok(std::mem::take(&mut *self.results.lock().unwrap()))
+        }
+    }
+
+    impl VtHelper for SimulatedScan {
+        fn get_vt(&self, oid: &str) -> RedisStorageResult<Option<Nvt>> {
+            let vector = match oid {
+                "1.2.3" => "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H",
+                "1.2.4" => "",
+                _ => return Ok(None),
+            };
+            Ok(Some(Nvt {
+                oid: oid.to_string(),
+                name: "test".to_string(),
+                tag: [(TagKey::SeverityVector, TagValue::from(vector))].into(),
+                ..Default::default()
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_results_before_scan_finished() {
+        let scan = SimulatedScan::default();
+        scan.results
+            .lock()
+            .unwrap()
+            .push("LOG|||127.0.0.1||| localhost ||||||||| HOST_START".to_string());
+
+        let mut rc = scan.clone();
+        let resh = ResultHelper::init(&mut rc);
+        let counts = resh.results.clone();
+        let mut stream =
+            Box::pin(resh.into_stream("123-456".to_string(), Duration::from_millis(1)));
+
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.description, "HOST_START");
+        assert!(!scan.finished.load(Ordering::SeqCst));
+
+        scan.results.lock().unwrap().extend([
+            "DEADHOST||| ||| ||| ||| |||2".to_string(),
+            "LOG|||127.0.0.1||| localhost ||||||||| HOST_END".to_string(),
+        ]);
+        scan.finished.store(true, Ordering::SeqCst);
+
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!(second.description, "HOST_END");
+        assert!(stream.next().await.is_none());
+        assert_eq!(counts.lock().unwrap().count_dead, 2);
+        assert!(counts.lock().unwrap().results.is_empty());
+    }
+
+    #[test]
+    fn dedup_results() {
+        let results = vec![
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.3||| Something wrong".to_string(),
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.3||| Something wrong".to_string(),
+            "ALARM|||127.0.0.1||| example.com |||80/tcp|||1.2.3||| Something wrong".to_string(),
+        ];
+        let mut rc = FakeRedis::default();
+
+        let mut resh = ResultHelper::init(&mut rc);
+        resh.process_results(results.clone()).unwrap();
+        assert_eq!(resh.results.lock().unwrap().results.len(), 3);
+
+        let mut resh = resh.with_dedup();
+        resh.process_results(results).unwrap();
+        let ports = resh
+            .results
+            .lock()
+            .unwrap()
+            .results
+            .iter()
+            .map(|x| x.port.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ports, vec!["22/tcp", "80/tcp"]);
+    }
+
+    #[test]
+    fn enrich_results_with_severity() {
+        let results = vec![
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.3||| Something wrong".to_string(),
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.4||| No severity".to_string(),
+            "LOG|||127.0.0.1||| localhost ||||||||| HOST_START".to_string(),
+        ];
+        let mut rc = SimulatedScan::default();
+        let mut resh = ResultHelper::init(&mut rc);
+        resh.process_results(results).unwrap();
+
+        let enriched = resh.enriched_results().unwrap();
+        assert_eq!(enriched.len(), 3);
+        assert_eq!(enriched[0].result.description, "Something wrong");
+        assert_eq!(
+            enriched[0].severity,
+            Some(Severity {
+                vector: "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H".to_string(),
+                score: Some(9.8),
+            })
+        );
+        assert_eq!(enriched[1].severity, None);
+        assert_eq!(enriched[2].severity, None);
+    }
+
+    #[test]
+    fn results_to_json() {
+        let results = vec![
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.3||| Something wrong".to_string(),
+            "LOG|||127.0.0.1||| localhost ||||||||| HOST_START".to_string(),
+        ];
+        let mut rc = SimulatedScan::default();
+        let mut resh = ResultHelper::init(&mut rc);
+        resh.process_results(results).unwrap();
+
+        let json = to_json(&resh.enriched_results().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!({
+                "schema_version": 1,
+                "results": [
+                    {
+                        "oid": "1.2.3",
+                        "host": "127.0.0.1",
+                        "port": "22/tcp",
+                        "severity": {
+                            "vector": "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H",
+                            "score": 9.8
+                        },
+                        "message": "Something wrong"
+                    },
+                    {
+                        "oid": null,
+                        "host": "127.0.0.1",
+                        "port": null,
+                        "severity": null,
+                        "message": "HOST_START"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn results_to_xml() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Report {
+            results: XmlResults,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct XmlResults {
+            result: Vec<XmlResult>,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct XmlResult {
+            host: Option<XmlHost>,
+            port: Option<String>,
+            nvt: Option<XmlNvt>,
+            severity: Option<f32>,
+            description: String,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct XmlHost {
+            #[serde(rename = "$text")]
+            address: String,
+            hostname: Option<String>,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct XmlNvt {
+            #[serde(rename = "@oid")]
+            oid: String,
+        }
+
+        let results = vec![
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.3||| <b>\"Something\"</b> & 'wrong'"
+                .to_string(),
+            "LOG|||127.0.0.1||| localhost ||||||||| HOST_START".to_string(),
+            "LOG|||127.0.0.1||| a&b |||80/tcp|||1.2.4||| SSH-2.0\u{7}\u{0}]]>".to_string(),
+        ];
+        let mut rc = SimulatedScan::default();
+        let mut resh = ResultHelper::init(&mut rc);
+        resh.process_results(results).unwrap();
+
+        let xml = to_xml(&resh.enriched_results().unwrap()).unwrap();
+        let mut reader = quick_xml::Reader::from_str(&xml);
+        loop {
+            match reader.read_event() {
+                Ok(quick_xml::events::Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => panic!("{xml} is not well-formed: {e}"),
+            }
+        }
+        let report: Report = quick_xml::de::from_str(&xml).unwrap();
+        let results = report.results.result;
+        assert_eq!(results.len(), 3);
+
+        let host = results[0].host.as_ref().unwrap();
+        assert_eq!(host.address, "127.0.0.1");
+        assert_eq!(host.hostname.as_deref(), Some("example.com"));
+        assert_eq!(results[0].port.as_deref(), Some("22/tcp"));
+        assert_eq!(results[0].nvt.as_ref().unwrap().oid, "1.2.3");
+        assert_eq!(results[0].severity, Some(9.8));
+        assert_eq!(results[0].description, "<b>\"Something\"</b> & 'wrong'");
+
+        assert_eq!(results[1].port, None);
+        assert!(results[1].nvt.is_none());
+        assert_eq!(results[1].severity, None);
+        assert_eq!(results[1].description, "HOST_START");
+
+        let host = results[2].host.as_ref().unwrap();
+        assert_eq!(host.hostname.as_deref(), Some("a&b"));
+        assert_eq!(results[2].description, "SSH-2.0\u{FFFD}\u{FFFD}]]>");
+    }
+
+    #[test]
+    fn results_to_csv() {
+        let results = vec![
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.3||| Something wrong".to_string(),
+            "LOG|||127.0.0.1||| localhost ||||||||| HOST_START".to_string(),
+        ];
+        let mut rc = SimulatedScan::default();
+        let mut resh = ResultHelper::init(&mut rc);
+        resh.process_results(results).unwrap();
+        let mut results = resh.enriched_results().unwrap();
+        results.push(EnrichedResult {
+            result: OspScanResult {
+                host: Some("127.0.0.2".to_string()),
+                hostname: None,
+                severity: None,
+                port: Some("general/tcp".to_string()),
+                test_id: Some("1.2.5".to_string()),
+                name: "Weak ciphers, \"RC4\"\nand DES".to_string(),
+                result_type: OspResultType::Log,
+                description: String::new(),
+            },
+            severity: None,
+        });
+
+        assert_eq!(
+            to_csv(&results),
+            "host,port,protocol,oid,severity,name\r\n\
+            127.0.0.1,22,tcp,1.2.3,9.8,test\r\n\
+            127.0.0.1,,,,,\r\n\
+            127.0.0.2,general,tcp,1.2.5,,\"Weak ciphers, \"\"RC4\"\"\nand DES\"\r\n"
+        );
+    }
+
+    #[test]
+    fn filter_results() {
+        let results = vec![
+            "ALARM|||127.0.0.1||| example.com |||22/tcp|||1.2.3||| Something wrong".to_string(),
+            "ALARM|||127.0.0.2||| example.com |||22/tcp|||1.2.4||| No severity".to_string(),
+            "LOG|||127.0.0.2||| localhost ||||||||| HOST_START".to_string(),
+        ];
+        let mut rc = SimulatedScan::default();
+        let mut resh = ResultHelper::init(&mut rc);
+        resh.process_results(results).unwrap();
+        let messages = |filter| {
+            resh.filter(filter)
+                .unwrap()
+                .into_iter()
+                .map(|x| x.result.description)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            messages(ResultFilter::default()),
+            vec!["Something wrong", "No severity", "HOST_START"]
+        );
+        assert_eq!(
+            messages(ResultFilter {
+                min_severity: Some(7.0),
+                ..Default::default()
+            }),
+            vec!["Something wrong"]
+        );
+        assert!(messages(ResultFilter {
+            min_severity: Some(9.9),
+            ..Default::default()
+        })
+        .is_empty());
+        assert_eq!(
+            messages(ResultFilter {
+                oids: vec!["1.2.4".to_string(), "1.2.5".to_string()],
+                ..Default::default()
+            }),
+            vec!["No severity"]
+        );
+        assert_eq!(
+            messages(ResultFilter {
+                host: Some("127.0.0.2".to_string()),
+                ..Default::default()
+            }),
+            vec!["No severity", "HOST_START"]
+        );
+        assert!(messages(ResultFilter {
+            host: Some("127.0.0.2".to_string()),
+            oids: vec!["1.2.3".to_string()],
+            ..Default::default()
+        })
+        .is_empty());
+    }
 }
@@ -28,6 +28,16 @@ pub struct Notus {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Redis {
     pub url: String,
+    /// TLS settings of the openvas scanner for `rediss://` urls
+    #[serde(default)]
+    pub tls: RedisTls,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RedisTls {
+    pub ca_cert: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -58,6 +68,8 @@ pub struct Scanner {
     pub scanner_type: ScannerType,
     #[serde(default)]
     pub ospd: OspdWrapper,
+    #[serde(default)]
+    pub openvas: OpenvasWrapper,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -107,6 +119,15 @@ pub struct OspdWrapper {
     pub read_timeout: Option<Duration>,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct OpenvasWrapper {
+    /// Path to the openvas config file, when not set the environment is used
+    pub config: Option<PathBuf>,
+    /// Replaces references to environment variables in string scan preferences
+    #[serde(default)]
+    pub expand_env: bool,
+}
+
 impl Default for OspdWrapper {
     fn default() -> Self {
         OspdWrapper {
@@ -139,6 +160,7 @@ impl Default for Redis {
     fn default() -> Self {
         Redis {
             url: "unix:///run/redis-openvas/redis.sock".to_string(),
+            tls: RedisTls::default(),
         }
     }
 }
@@ -395,6 +417,30 @@ impl Config {
                     //.value_parser(clap::builder::PathBufValueParser::new())
                     .action(ArgAction::Set)
                     .help("Redis url. Either unix:// or redis://"))
+            .arg(
+                clap::Arg::new("redis-tls-ca-cert")
+                    .env("REDIS_TLS_CA_CERT")
+                    .long("redis-tls-ca-cert")
+                    .value_parser(clap::builder::PathBufValueParser::new())
+                    .action(ArgAction::Set)
+                    .help("path to the CA certificate to verify redis with"),
+            )
+            .arg(
+                clap::Arg::new("redis-tls-client-cert")
+                    .env("REDIS_TLS_CLIENT_CERT")
+                    .long("redis-tls-client-cert")
+                    .value_parser(clap::builder::PathBufValueParser::new())
+                    .action(ArgAction::Set)
+                    .help("path to the client certificate for redis"),
+            )
+            .arg(
+                clap::Arg::new("redis-tls-client-key")
+                    .env("REDIS_TLS_CLIENT_KEY")
+                    .long("redis-tls-client-key")
+                    .value_parser(clap::builder::PathBufValueParser::new())
+                    .action(ArgAction::Set)
+                    .help("path to the client key for redis"),
+            )
             .arg(
                 clap::Arg::new("tls-certs")
                     .env("TLS_CERTS")
@@ -480,6 +526,13 @@ impl Config {
                     .help("socket to ospd")
                     .value_parser(clap::builder::PathBufValueParser::new()),
             )
+            .arg(
+                clap::Arg::new("openvas-config")
+                    .env("OPENVAS_CONFIG")
+                    .long("openvas-config")
+                    .help("path to the openvas config file")
+                    .value_parser(clap::builder::PathBufValueParser::new()),
+            )
             .arg(
                 clap::Arg::new("read-timeout")
                     .env("READ_TIMEOUT")
@@ -575,6 +628,9 @@ impl Config {
         if let Some(path) = cmds.get_one::<PathBuf>("ospd-socket") {
             config.scanner.ospd.socket.clone_from(path);
         }
+        if let Some(path) = cmds.get_one::<PathBuf>("openvas-config") {
+            config.scanner.openvas.config = Some(path.clone());
+        }
         if let Some(interval) = cmds.get_one::<u64>("read-timeout") {
             config.scanner.ospd.read_timeout = Some(Duration::from_secs(*interval));
         }
@@ -591,6 +647,15 @@ impl Config {
         if let Some(path) = cmds.get_one::<String>("redis-url") {
             config.storage.redis.url.clone_from(path);
         }
+        if let Some(path) = cmds.get_one::<PathBuf>("redis-tls-ca-cert") {
+            config.storage.redis.tls.ca_cert = Some(path.clone());
+        }
+        if let Some(path) = cmds.get_one::<PathBuf>("redis-tls-client-cert") {
+            config.storage.redis.tls.client_cert = Some(path.clone());
+        }
+        if let Some(path) = cmds.get_one::<PathBuf>("redis-tls-client-key") {
+            config.storage.redis.tls.client_key = Some(path.clone());
+        }
         if let Some(path) = cmds.get_one::<PathBuf>("tls-certs") {
             config.tls.certs = Some(path.clone());
         }
@@ -682,6 +747,10 @@ mod tests {
         [storage.fs]
         path = "/var/lib/openvasd/storage/test"
         key = "changeme"
+        [storage.redis]
+        url = "rediss://localhost:6380"
+        [storage.redis.tls]
+        ca_cert = "/etc/openvasd/tls/redis-ca.pem"
         "#;
         let config: super::Config = toml::from_str(cfg).unwrap();
         assert_eq!(config.log.level, "DEBUG");
@@ -691,5 +760,10 @@ mod tests {
         );
         assert_eq!(config.storage.fs.key, Some("changeme".to_string()));
         assert_eq!(config.storage.storage_type, StorageType::FileSystem);
+        assert_eq!(
+            config.storage.redis.tls.ca_cert,
+            Some(PathBuf::from("/etc/openvasd/tls/redis-ca.pem"))
+        );
+        assert!(config.storage.redis.tls.client_cert.is_none());
     }
 }
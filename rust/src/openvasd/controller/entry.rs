@@ -769,6 +769,8 @@ pub mod client {
             self.ctx.scheduler.sync_scans().await?;
             let start = std::time::SystemTime::now();
             loop {
+                // the scan may yield before it is finished, fetch its status like the scheduler
+                self.ctx.scheduler.sync_scans().await?;
                 let response = self.scan_status(&id).await?;
                 if response.is_done() {
                     let mut abort = Arc::as_ref(&self.ctx).abort.write().unwrap();
@@ -18,6 +18,7 @@ use scannerlib::openvas::{self, cmd};
 use scannerlib::osp;
 use scannerlib::scanner::ScannerStackWithStorage;
 use scannerlib::storage::infisto::{ChaCha20IndexFileStorer, IndexedFileStorer};
+use scannerlib::storage::redis::RedisTlsConfig;
 use storage::{FromConfigAndFeeds, Storage};
 use tls::tls_config;
 use tracing::{info, metadata::LevelFilter, warn};
@@ -77,14 +78,32 @@ fn make_osp_scanner(config: &Config) -> osp::Scanner {
     )
 }
 
+/// Discovers the openvas executable from the openvas config before any command runs.
+fn init_openvas(config: &Config) -> Result<()> {
+    let openvas_config = match &config.scanner.openvas.config {
+        Some(path) => openvas::Config::load(path)?,
+        None => openvas::Config::default().with_env_overrides(|x| std::env::var(x).ok())?,
+    };
+    let path = cmd::init_openvas(openvas_config.openvas_path.as_deref())?;
+    info!(path = %path.display(), "Using openvas executable.");
+    Ok(())
+}
+
 fn make_openvas_scanner(mut config: Config) -> openvas::Scanner {
     let redis_url = check_redis_url(&mut config);
+    let tls = &config.storage.redis.tls;
     openvas::Scanner::new(
         config.scheduler.min_free_mem,
         None,
         cmd::check_sudo(),
         redis_url,
     )
+    .with_redis_tls(RedisTlsConfig {
+        ca_cert: tls.ca_cert.clone(),
+        client_cert: tls.client_cert.clone(),
+        client_key: tls.client_key.clone(),
+    })
+    .with_env_expansion(config.scanner.openvas.expand_env)
 }
 
 fn make_openvasd_scanner<S>(
@@ -157,6 +176,7 @@ where
             run_with_scanner_and_storage(scanner, storage, config).await
         }
         ScannerType::Openvas => {
+            init_openvas(config)?;
             let scanner = make_openvas_scanner(config.clone());
             run_with_scanner_and_storage(scanner, storage, config).await
         }
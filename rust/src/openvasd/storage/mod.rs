@@ -488,6 +488,15 @@ where
         self.underlying_storage().retrieve(key, scope)
     }
 
+    fn retrieve_kb(
+        &self,
+        key: &ContextKey,
+        scope: Retrieve,
+        max_size: Option<usize>,
+    ) -> Result<Vec<Kb>, StorageError> {
+        self.underlying_storage().retrieve_kb(key, scope, max_size)
+    }
+
     fn retrieve_by_field(&self, field: Field, scope: Retrieve) -> FieldKeyResult {
         // We should never try to return results without an ID
         self.underlying_storage().retrieve_by_field(field, scope)
@@ -2,26 +2,57 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
-use crate::models::{Host, Protocol};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::nasl::interpreter::InterpretError;
-use crate::scheduling::Stage;
+use crate::models::{self, Host, Protocol, ResultType};
+
+use crate::nasl::interpreter::{InterpretError, InterpretErrorKind};
+use crate::nasl::syntax::LoadError;
+use crate::scheduling::{Stage, VTError};
+use crate::storage::item::PreferenceType;
+use crate::storage::StorageError;
 
 #[derive(thiserror::Error, Debug, Clone)]
 /// An error occurred while executing the script
 pub enum ExecuteError {
     #[error("storage error occurred: {0}")]
-    /// Storage error
-    Storage(#[from] crate::storage::StorageError),
+    /// The storage failed, e.g. because the connection to the database was lost
+    Storage(#[from] StorageError),
     #[error("Scheduling error occurred: {0}")]
-    /// An error while scheduling
-    Scheduling(#[from] crate::scheduling::VTError),
+    /// The VTs could not be scheduled, e.g. because of a missing dependency
+    ///
+    /// Storage and load errors while scheduling are returned as `Storage` and `Load`.
+    Scheduling(VTError),
     #[error("unable to load: {0}")]
-    /// Script was not found
-    NotFound(#[from] crate::nasl::syntax::LoadError),
+    /// A script could not be loaded, the error contains the filename
+    Load(#[from] LoadError),
     #[error("unable to handle parameter: {0}")]
     /// The parameter could not be processed
     Parameter(crate::models::Parameter),
+    #[error("invalid parameter {0}, expected a {} value", .1.as_ref())]
+    /// The value of the parameter does not match the type of the preference of the VT
+    InvalidParameter(crate::models::Parameter, PreferenceType),
+}
+
+impl From<VTError> for ExecuteError {
+    fn from(value: VTError) -> Self {
+        match value {
+            VTError::DB(e) => ExecuteError::Storage(e),
+            VTError::NotFound(e) => ExecuteError::Load(e),
+            e => ExecuteError::Scheduling(e),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+/// An error occurred while resuming a scan from a checkpoint
+pub enum CheckpointError {
+    #[error("the checkpoint was created for a different schedule or target")]
+    /// The hosts or the scheduled VTs differ from the ones the checkpoint was created for
+    ScheduleMismatch,
 }
 
 #[derive(Debug)]
@@ -30,8 +61,18 @@ pub enum ScriptResultKind {
     /// Contains the code provided by exit call or 0 when script finished successful without exit
     /// call
     ReturnCode(i64),
+    /// Contains the code provided by the exit call of an additional forked interpreter
+    ///
+    /// When a script forks (e.g. via get_kb_item on a key with multiple values) each fork exits
+    /// on its own. The exit of the first interpreter is reported as `ReturnCode` while each
+    /// additional fork is reported as `Forked`.
+    Forked(i64),
     /// Is missing a port
     MissingPort(Protocol, String),
+    /// Script did not run because a required port is filtered
+    ///
+    /// Only returned for UDP ports when filtered ports are rejected.
+    PortFiltered(Protocol, String),
     /// Script did not run because an excluded key is set
     ContainsExcludedKey(String),
     /// Script did not run because of missing required keys
@@ -42,10 +83,117 @@ pub enum ScriptResultKind {
     ///
     /// It contains the first not found key.
     MissingMandatoryKey(String),
+    /// Script did not run because the storage failed while verifying the keys
+    ///
+    /// It contains the message of the storage error.
+    StorageError(String),
+    /// Script did not run because it is marked as deprecated
+    Deprecated,
+    /// Script did not run because the deadline of the scan passed
+    ///
+    /// It contains the amount of remaining VTs that were not run, including this one.
+    DeadlineExceeded(usize),
+    /// Script was aborted because it exceeded the contained timeout
+    ///
+    /// The timeout is either declared by the script via `script_timeout` or the default of the scan.
+    TimedOut(Duration),
+    /// Script was aborted because it sent more messages than the contained limit
+    ///
+    /// The messages up to the limit are kept, all further messages are dropped.
+    ResultLimitExceeded(usize),
+    /// Script was aborted because the interpreter panicked
+    ///
+    /// It contains the message of the panic.
+    Panic(String),
     /// Contains the error the script returned
     Error(InterpretError),
 }
 
+impl fmt::Display for ScriptResultKind {
+    /// Writes the canonical form of the kind, e.g. `return_code:1` or
+    /// `missing_required_key:foo`
+    ///
+    /// The data of a kind follows its name separated by a colon. Ports are written as
+    /// `<protocol>:<port>` and timeouts in milliseconds, e.g. `timed_out:1500ms`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptResultKind::ReturnCode(x) => write!(f, "return_code:{x}"),
+            ScriptResultKind::Forked(x) => write!(f, "forked:{x}"),
+            ScriptResultKind::MissingPort(p, x) => write!(f, "missing_port:{p}:{x}"),
+            ScriptResultKind::PortFiltered(p, x) => write!(f, "port_filtered:{p}:{x}"),
+            ScriptResultKind::ContainsExcludedKey(x) => write!(f, "contains_excluded_key:{x}"),
+            ScriptResultKind::MissingRequiredKey(x) => write!(f, "missing_required_key:{x}"),
+            ScriptResultKind::MissingMandatoryKey(x) => write!(f, "missing_mandatory_key:{x}"),
+            ScriptResultKind::StorageError(x) => write!(f, "storage_error:{x}"),
+            ScriptResultKind::Deprecated => write!(f, "deprecated"),
+            ScriptResultKind::DeadlineExceeded(x) => write!(f, "deadline_exceeded:{x}"),
+            ScriptResultKind::TimedOut(x) => write!(f, "timed_out:{}ms", x.as_millis()),
+            ScriptResultKind::ResultLimitExceeded(x) => write!(f, "result_limit_exceeded:{x}"),
+            ScriptResultKind::Panic(x) => write!(f, "panic:{x}"),
+            ScriptResultKind::Error(x) => write!(f, "error:{x}"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+/// A string is not the canonical form of a `ScriptResultKind`
+pub enum ParseScriptResultKindError {
+    #[error("unknown script result kind: {0}")]
+    /// The name of the kind is unknown or its data is malformed
+    Invalid(String),
+    #[error("script errors can't be restored from their message: {0}")]
+    /// The kind is an `Error`, whose interpreter error is only kept as message
+    Error(String),
+}
+
+impl FromStr for ScriptResultKind {
+    type Err = ParseScriptResultKindError;
+
+    /// Parses the canonical form as written by `Display`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseScriptResultKindError::Invalid(s.to_string());
+        let (name, data) = s.split_once(':').unwrap_or((s, ""));
+        let number = |x: &str| x.parse::<i64>().map_err(|_| invalid());
+        let port = |x: &str| -> Result<(Protocol, String), ParseScriptResultKindError> {
+            let (protocol, port) = x.split_once(':').ok_or_else(invalid)?;
+            let protocol = Protocol::try_from(protocol).map_err(|_| invalid())?;
+            Ok((protocol, port.to_string()))
+        };
+        Ok(match (name, data) {
+            ("deprecated", "") => ScriptResultKind::Deprecated,
+            (_, "") if !s.contains(':') => return Err(invalid()),
+            ("return_code", x) => ScriptResultKind::ReturnCode(number(x)?),
+            ("forked", x) => ScriptResultKind::Forked(number(x)?),
+            ("missing_port", x) => {
+                let (protocol, port) = port(x)?;
+                ScriptResultKind::MissingPort(protocol, port)
+            }
+            ("port_filtered", x) => {
+                let (protocol, port) = port(x)?;
+                ScriptResultKind::PortFiltered(protocol, port)
+            }
+            ("contains_excluded_key", x) => ScriptResultKind::ContainsExcludedKey(x.to_string()),
+            ("missing_required_key", x) => ScriptResultKind::MissingRequiredKey(x.to_string()),
+            ("missing_mandatory_key", x) => ScriptResultKind::MissingMandatoryKey(x.to_string()),
+            ("storage_error", x) => ScriptResultKind::StorageError(x.to_string()),
+            ("timed_out", x) => {
+                let millis = x.strip_suffix("ms").ok_or_else(invalid)?;
+                let millis = millis.parse().map_err(|_| invalid())?;
+                ScriptResultKind::TimedOut(Duration::from_millis(millis))
+            }
+            ("deadline_exceeded", x) => {
+                ScriptResultKind::DeadlineExceeded(x.parse().map_err(|_| invalid())?)
+            }
+            ("result_limit_exceeded", x) => {
+                ScriptResultKind::ResultLimitExceeded(x.parse().map_err(|_| invalid())?)
+            }
+            ("panic", x) => ScriptResultKind::Panic(x.to_string()),
+            ("error", x) => return Err(ParseScriptResultKindError::Error(x.to_string())),
+            _ => return Err(invalid()),
+        })
+    }
+}
+
 #[derive(Debug)]
 /// Contains meta data of the script and its result
 pub struct ScriptResult {
@@ -59,12 +207,71 @@ pub struct ScriptResult {
     pub kind: ScriptResultKind,
     /// The target of the result
     pub target: Host,
+    /// Messages sent by the script via `log_message`, `security_message` or `error_message`
+    ///
+    /// The type of a message tells its severity, e.g. `security_message` results in
+    /// [`ResultType::Alarm`]. Messages of forked interpreters are part of the first result.
+    pub messages: Vec<models::Result>,
 }
 
 impl ScriptResult {
     /// Returns true when the return code of the script is 0.
     pub fn has_succeeded(&self) -> bool {
-        matches!(&self.kind, ScriptResultKind::ReturnCode(0))
+        matches!(
+            &self.kind,
+            ScriptResultKind::ReturnCode(0) | ScriptResultKind::Forked(0)
+        )
+    }
+
+    /// Returns true when the script ran but did not succeed, e.g. because it returned an
+    /// error, timed out or exited with a code other than 0.
+    pub fn has_failed(&self) -> bool {
+        !self.has_succeeded() && !self.has_not_run()
+    }
+
+    /// Returns true when the script sent a message of the given type
+    pub fn has_message(&self, r_type: ResultType) -> bool {
+        self.messages.iter().any(|x| x.r_type == r_type)
+    }
+
+    /// Returns true when the result was produced by an additional forked interpreter
+    pub fn is_forked(&self) -> bool {
+        matches!(&self.kind, ScriptResultKind::Forked(_))
+    }
+
+    /// Returns true when the script was aborted because it exceeded its timeout
+    ///
+    /// A timed out script has failed, but not errored.
+    pub fn has_timed_out(&self) -> bool {
+        matches!(self.kind, ScriptResultKind::TimedOut(_))
+    }
+
+    /// Returns true when the script was aborted by an error of the interpreter, a panic or
+    /// by exceeding the result limit
+    ///
+    /// An errored script has failed. Unlike a script exiting with a code other than 0 or a
+    /// timed out script it did not end on its own terms.
+    pub fn errored(&self) -> bool {
+        matches!(
+            self.kind,
+            ScriptResultKind::Error(_)
+                | ScriptResultKind::Panic(_)
+                | ScriptResultKind::ResultLimitExceeded(_)
+        )
+    }
+
+    /// Returns true when the script didn't run because of its requirements
+    ///
+    /// These are the missing or excluded keys and ports as well as the deprecation. A skipped
+    /// script has not run, but a `StorageError` while verifying the requirements is not
+    /// considered as skipped, as the requirements are unknown. Neither is a script that was
+    /// not run because of the deadline of the scan.
+    pub fn skipped(&self) -> bool {
+        self.has_not_run()
+            && !matches!(
+                self.kind,
+                ScriptResultKind::StorageError(_) | ScriptResultKind::DeadlineExceeded(_)
+            )
     }
 
     /// Returns true when the script didn't run
@@ -75,6 +282,195 @@ impl ScriptResult {
                 | ScriptResultKind::MissingMandatoryKey(_)
                 | ScriptResultKind::ContainsExcludedKey(_)
                 | ScriptResultKind::MissingPort(..)
+                | ScriptResultKind::PortFiltered(..)
+                | ScriptResultKind::StorageError(_)
+                | ScriptResultKind::Deprecated
+                | ScriptResultKind::DeadlineExceeded(_)
         )
     }
+
+    /// Returns the line and column of the statement the script failed on
+    ///
+    /// On syntax errors the position of the offending token is returned instead. Returns None
+    /// when the script did not fail or when the error is not bound to a position.
+    pub fn error_position(&self) -> Option<(usize, usize)> {
+        let ScriptResultKind::Error(e) = &self.kind else {
+            return None;
+        };
+        match &e.kind {
+            InterpretErrorKind::SyntaxError(se) => se.as_token().map(|t| t.line_column),
+            _ => e.origin.as_ref().map(|stmt| stmt.as_token().line_column),
+        }
+    }
+}
+
+impl From<&ScriptResult> for models::Result {
+    /// Converts a script result into a result of the scan to persist it
+    ///
+    /// Failed scripts are of the type `Error`, all others of the type `Log`. The message
+    /// contains the canonical form of the kind of the script result.
+    fn from(value: &ScriptResult) -> Self {
+        let r_type = if value.has_failed() {
+            ResultType::Error
+        } else {
+            ResultType::Log
+        };
+        let (ip_address, hostname) = match value.target.parse::<IpAddr>() {
+            Ok(_) => (Some(value.target.clone()), None),
+            Err(_) => (None, Some(value.target.clone())),
+        };
+        models::Result {
+            r_type,
+            ip_address,
+            hostname,
+            oid: Some(value.oid.clone()),
+            message: Some(value.kind.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::models::Protocol;
+    use crate::nasl::interpreter::{InterpretError, InterpretErrorKind};
+
+    use crate::scheduling::Stage;
+
+    use super::{ParseScriptResultKindError, ScriptResult, ScriptResultKind};
+
+    fn result(kind: ScriptResultKind) -> ScriptResult {
+        ScriptResult {
+            oid: "1".into(),
+            filename: "1.nasl".into(),
+            stage: Stage::End,
+            kind,
+            target: "test.host".into(),
+            messages: vec![],
+        }
+    }
+
+    #[test]
+    fn predicates() {
+        let error = InterpretError::new(InterpretErrorKind::NotFound("a".into()), None);
+        // kind, succeeded, failed, not run, timed out, errored, skipped
+        let cases = [
+            (
+                ScriptResultKind::ReturnCode(0),
+                [true, false, false, false, false, false],
+            ),
+            (
+                ScriptResultKind::Forked(0),
+                [true, false, false, false, false, false],
+            ),
+            (
+                ScriptResultKind::ReturnCode(1),
+                [false, true, false, false, false, false],
+            ),
+            (
+                ScriptResultKind::TimedOut(Duration::from_secs(1)),
+                [false, true, false, true, false, false],
+            ),
+            (
+                ScriptResultKind::Error(error),
+                [false, true, false, false, true, false],
+            ),
+            (
+                ScriptResultKind::Panic("a".into()),
+                [false, true, false, false, true, false],
+            ),
+            (
+                ScriptResultKind::ResultLimitExceeded(1),
+                [false, true, false, false, true, false],
+            ),
+            (
+                ScriptResultKind::Deprecated,
+                [false, false, true, false, false, true],
+            ),
+            (
+                ScriptResultKind::MissingRequiredKey("a".into()),
+                [false, false, true, false, false, true],
+            ),
+            (
+                ScriptResultKind::MissingPort(Protocol::TCP, "80".into()),
+                [false, false, true, false, false, true],
+            ),
+            (
+                ScriptResultKind::StorageError("a".into()),
+                [false, false, true, false, false, false],
+            ),
+            (
+                ScriptResultKind::DeadlineExceeded(2),
+                [false, false, true, false, false, false],
+            ),
+        ];
+        for (kind, expected) in cases {
+            let result = result(kind);
+            let actual = [
+                result.has_succeeded(),
+                result.has_failed(),
+                result.has_not_run(),
+                result.has_timed_out(),
+                result.errored(),
+                result.skipped(),
+            ];
+            assert_eq!(actual, expected, "{:?}", result.kind);
+        }
+    }
+
+    #[test]
+    fn kind_round_trip() {
+        let kinds = [
+            ScriptResultKind::ReturnCode(-1),
+            ScriptResultKind::Forked(0),
+            ScriptResultKind::MissingPort(Protocol::TCP, "Services/www".into()),
+            ScriptResultKind::PortFiltered(Protocol::UDP, "161".into()),
+            ScriptResultKind::ContainsExcludedKey("Settings/disable".into()),
+            ScriptResultKind::MissingRequiredKey("foo".into()),
+            ScriptResultKind::MissingMandatoryKey("Host/runs_unixoide".into()),
+            ScriptResultKind::StorageError("Connection lost: redis".into()),
+            ScriptResultKind::Deprecated,
+            ScriptResultKind::DeadlineExceeded(3),
+            ScriptResultKind::TimedOut(Duration::from_millis(1500)),
+            ScriptResultKind::ResultLimitExceeded(100),
+            ScriptResultKind::Panic("not implemented: a:b".into()),
+        ];
+        for kind in kinds {
+            let parsed = kind.to_string().parse::<ScriptResultKind>();
+            assert_eq!(format!("{parsed:?}"), format!("{:?}", Ok::<_, ()>(kind)));
+        }
+        assert_eq!(
+            ScriptResultKind::MissingRequiredKey("foo".into()).to_string(),
+            "missing_required_key:foo"
+        );
+        assert_eq!(ScriptResultKind::ReturnCode(1).to_string(), "return_code:1");
+    }
+
+    #[test]
+    fn kind_error() {
+        let error = InterpretError::new(InterpretErrorKind::NotFound("a".into()), None);
+        let kind = ScriptResultKind::Error(error);
+        assert!(matches!(
+            kind.to_string().parse::<ScriptResultKind>(),
+            Err(ParseScriptResultKindError::Error(x)) if x.contains("Key not found: a")
+        ));
+    }
+
+    #[test]
+    fn kind_invalid() {
+        for x in [
+            "",
+            "unknown:1",
+            "return_code",
+            "return_code:a",
+            "missing_port:http:80",
+        ] {
+            assert!(matches!(
+                x.parse::<ScriptResultKind>(),
+                Err(ParseScriptResultKindError::Invalid(_))
+            ));
+        }
+    }
 }
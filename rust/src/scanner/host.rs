@@ -0,0 +1,97 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// A host of a scan target parsed into an IP address or a hostname
+///
+/// The hosts of a scan target are given as strings. To not parse them again for each VT they are
+/// parsed once when the `ScanRunner` is created. IPv6 addresses may be given with or without
+/// brackets (`[fe80::1]`) and in non canonical notation; the display of an address is always
+/// its canonical representation, which is used within KB keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TargetHost {
+    /// An IPv4 address
+    V4(Ipv4Addr),
+    /// An IPv6 address
+    V6(Ipv6Addr),
+    /// A hostname, which is kept as given without surrounding whitespace
+    Name(String),
+}
+
+impl From<&str> for TargetHost {
+    fn from(value: &str) -> Self {
+        let host = value.trim();
+        let unbracketed = host
+            .strip_prefix('[')
+            .and_then(|x| x.strip_suffix(']'))
+            .unwrap_or(host);
+        match unbracketed.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => TargetHost::V4(ip),
+            Ok(IpAddr::V6(ip)) => TargetHost::V6(ip),
+            Err(_) => TargetHost::Name(host.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for TargetHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetHost::V4(ip) => write!(f, "{ip}"),
+            TargetHost::V6(ip) => write!(f, "{ip}"),
+            TargetHost::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl TargetHost {
+    /// Returns the IP address of the host, if it was given as one
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            TargetHost::V4(ip) => Some(IpAddr::V4(*ip)),
+            TargetHost::V6(ip) => Some(IpAddr::V6(*ip)),
+            TargetHost::Name(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::TargetHost;
+
+    #[test]
+    fn parse() {
+        let v6 = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        assert_eq!(
+            TargetHost::from("127.0.0.1"),
+            TargetHost::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert_eq!(TargetHost::from("fe80::1"), TargetHost::V6(v6));
+        assert_eq!(TargetHost::from("[fe80::1]"), TargetHost::V6(v6));
+        assert_eq!(TargetHost::from(" fe80:0:0:0:0:0:0:1 "), TargetHost::V6(v6));
+        assert_eq!(
+            TargetHost::from("test.host"),
+            TargetHost::Name("test.host".to_owned())
+        );
+        assert_eq!(
+            TargetHost::from(" test.host\t"),
+            TargetHost::Name("test.host".to_owned())
+        );
+        assert_eq!(
+            TargetHost::from("[test.host]"),
+            TargetHost::Name("[test.host]".to_owned())
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(TargetHost::from("[fe80:0::1]").to_string(), "fe80::1");
+        assert_eq!(TargetHost::from("127.0.0.1").to_string(), "127.0.0.1");
+        assert_eq!(TargetHost::from("test.host").to_string(), "test.host");
+        assert_eq!(TargetHost::from("test.host").ip(), None);
+    }
+}
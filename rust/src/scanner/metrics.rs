@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::time::Duration;
+
+use super::error::{ExecuteError, ScriptResult};
+
+/// Outcome of a single VT on a host as counted by [`ScanMetrics`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VtOutcome {
+    /// The VT exited with 0
+    Succeeded,
+    /// The VT returned an error, panicked or exited with a code other than 0
+    Failed,
+    /// The VT did not run, e.g. because of missing keys or the deadline of the scan
+    Skipped,
+    /// The VT was aborted because it exceeded its timeout
+    TimedOut,
+}
+
+impl VtOutcome {
+    /// Returns the outcome of a single result of a VT
+    pub fn of(result: &Result<ScriptResult, ExecuteError>) -> Self {
+        match result {
+            Ok(x) if x.has_succeeded() => VtOutcome::Succeeded,
+            Ok(x) if x.has_timed_out() => VtOutcome::TimedOut,
+            Ok(x) if x.has_not_run() => VtOutcome::Skipped,
+            Ok(_) | Err(_) => VtOutcome::Failed,
+        }
+    }
+
+    /// Returns the worst outcome of the results of a VT
+    ///
+    /// Additional forks belong to the same run of the VT, so the run is counted once. It
+    /// failed when any of its forks failed.
+    pub fn worst<'a, I>(results: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Result<ScriptResult, ExecuteError>>,
+    {
+        results.into_iter().map(Self::of).max_by_key(|x| x.rank())
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            VtOutcome::Skipped => 0,
+            VtOutcome::Succeeded => 1,
+            VtOutcome::TimedOut => 2,
+            VtOutcome::Failed => 3,
+        }
+    }
+}
+
+/// Is called by the [`ScanRunner`](super::ScanRunner) after each VT, see
+/// [`ScanRunner::with_metrics`](super::ScanRunner::with_metrics).
+pub trait ScanMetrics: Send + Sync {
+    /// Called once per VT and host with the outcome and the time it took
+    fn vt_finished(&self, outcome: VtOutcome, duration: Duration);
+}
+
+/// Ignores all metrics, this is the default of a `ScanRunner`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoMetrics;
+
+impl ScanMetrics for NoMetrics {
+    fn vt_finished(&self, _: VtOutcome, _: Duration) {}
+}
+
+/// Reports the metrics to the recorder installed for the `metrics` crate
+///
+/// With e.g. `metrics-exporter-prometheus` as recorder the following metrics can be scraped:
+/// - `scanner_vts_executed_total`
+/// - `scanner_vts_succeeded_total`
+/// - `scanner_vts_failed_total`
+/// - `scanner_vts_skipped_total`
+/// - `scanner_vts_timed_out_total`
+/// - `scanner_vt_duration_seconds`, only for VTs that did run
+///
+/// Without an installed recorder nothing is recorded.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlobalMetrics;
+
+#[cfg(feature = "metrics")]
+impl ScanMetrics for GlobalMetrics {
+    fn vt_finished(&self, outcome: VtOutcome, duration: Duration) {
+        metrics::counter!("scanner_vts_executed_total").increment(1);
+        let counter = match outcome {
+            VtOutcome::Succeeded => "scanner_vts_succeeded_total",
+            VtOutcome::Failed => "scanner_vts_failed_total",
+            VtOutcome::Skipped => "scanner_vts_skipped_total",
+            VtOutcome::TimedOut => "scanner_vts_timed_out_total",
+        };
+        metrics::counter!(counter).increment(1);
+        if outcome != VtOutcome::Skipped {
+            metrics::histogram!("scanner_vt_duration_seconds").record(duration.as_secs_f64());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scanner::error::{ScriptResult, ScriptResultKind};
+    use crate::scheduling::Stage;
+
+    use super::VtOutcome;
+
+    fn result(kind: ScriptResultKind) -> Result<ScriptResult, super::ExecuteError> {
+        Ok(ScriptResult {
+            oid: "1".into(),
+            filename: "1.nasl".into(),
+            stage: Stage::End,
+            kind,
+            target: "test.host".into(),
+            messages: vec![],
+        })
+    }
+
+    #[test]
+    fn worst_outcome_of_forks() {
+        let succeeded = [
+            result(ScriptResultKind::ReturnCode(0)),
+            result(ScriptResultKind::Forked(0)),
+        ];
+        assert_eq!(VtOutcome::worst(&succeeded), Some(VtOutcome::Succeeded));
+        let failed = [
+            result(ScriptResultKind::ReturnCode(0)),
+            result(ScriptResultKind::Forked(1)),
+            result(ScriptResultKind::Forked(0)),
+        ];
+        assert_eq!(VtOutcome::worst(&failed), Some(VtOutcome::Failed));
+        assert_eq!(VtOutcome::worst(&[]), None);
+    }
+}
@@ -16,15 +16,33 @@
 //! VT is then run to completion using the `VTRunner`.
 
 mod error;
+mod host;
+mod metrics;
+mod report;
 mod running_scan;
 mod scan_runner;
 mod scanner_stack;
+#[cfg(any(test, feature = "test-util"))]
+mod test_harness;
 mod vt_runner;
 
-pub use error::ExecuteError;
-pub use scan_runner::ScanRunner;
-pub use scanner_stack::ScannerStack;
+pub use error::{
+    CheckpointError, ExecuteError, ParseScriptResultKindError, ScriptResult, ScriptResultKind,
+};
+pub use host::TargetHost;
+#[cfg(feature = "metrics")]
+pub use metrics::GlobalMetrics;
+pub use metrics::{NoMetrics, ScanMetrics, VtOutcome};
+pub use report::{ResultCounts, ScanReport};
+pub use scan_runner::{
+    Checkpoint, DryRunEntry, ExecutionOrder, PlannedStage, ScanProgress, ScanRunner, ScanTarget,
+    HOST_FINISHED_KEY,
+};
 pub use scanner_stack::ScannerStackWithStorage;
+pub use scanner_stack::{ScannerStack, Schedule};
+#[cfg(any(test, feature = "test-util"))]
+pub use test_harness::{InMemoryLoader, TestScanHarness, TestScannerStack};
+pub use vt_runner::FilteredPorts;
 
 use async_trait::async_trait;
 use std::{collections::HashMap, path::Path, sync::Arc};
@@ -0,0 +1,77 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::collections::HashMap;
+
+use crate::models::Host;
+
+use super::error::{ExecuteError, ScriptResult};
+
+/// Number of script results per outcome
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResultCounts {
+    /// Scripts that finished with return code 0
+    pub succeeded: usize,
+    /// Scripts that ran but did not succeed
+    pub failed: usize,
+    /// Scripts that did not run, e.g. because of a missing key
+    pub not_run: usize,
+}
+
+impl ResultCounts {
+    fn count(&mut self, result: &ScriptResult) {
+        if result.has_succeeded() {
+            self.succeeded += 1;
+        } else if result.has_not_run() {
+            self.not_run += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+}
+
+/// Contains the aggregated results of a whole scan run
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// Counts over all hosts
+    pub total: ResultCounts,
+    /// Counts per host
+    pub hosts: HashMap<Host, ResultCounts>,
+    /// All script results in the order they were produced
+    pub results: Vec<ScriptResult>,
+    /// Errors that prevented scripts from being executed
+    pub errors: Vec<ExecuteError>,
+}
+
+impl ScanReport {
+    /// Adds a single result of a scan run to the report
+    pub fn add(&mut self, result: Result<ScriptResult, ExecuteError>) {
+        match result {
+            Ok(result) => {
+                self.total.count(&result);
+                self.hosts
+                    .entry(result.target.clone())
+                    .or_default()
+                    .count(&result);
+                self.results.push(result);
+            }
+            Err(e) => self.errors.push(e),
+        }
+    }
+
+    /// Returns the script results grouped by the host they were produced for
+    ///
+    /// The results of each host keep the order in which they were produced, independent of how
+    /// the results of different hosts were interleaved.
+    pub fn group_by_host(self) -> HashMap<Host, Vec<ScriptResult>> {
+        let mut grouped: HashMap<_, Vec<_>> = HashMap::new();
+        for result in self.results {
+            grouped
+                .entry(result.target.clone())
+                .or_default()
+                .push(result);
+        }
+        grouped
+    }
+}
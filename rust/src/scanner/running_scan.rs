@@ -12,6 +12,7 @@ use std::{
 
 use crate::models::{scanner::Error, HostInfo, Phase, Scan, Status};
 use crate::nasl::utils::Executor;
+use crate::storage::{ContextKey, Storage};
 use crate::{
     scanner::scan_runner::ScanRunner,
     scheduling::{ExecutionPlan, ExecutionPlaner, VTError},
@@ -84,6 +85,10 @@ impl<S: ScannerStack> RunningScan<S> {
         self.update_status_at_beginning_of_run(runner.host_info())
             .await;
         let end_phase = self.run_to_completion(runner).await;
+        let key = ContextKey::Scan(self.scan.scan_id.clone(), None);
+        if let Err(e) = self.storage.scan_finished(&key) {
+            warn!(error=?e, "unable to clean up the knowledge base of the scan");
+        }
 
         self.update_status_at_end_of_run(end_phase).await;
         Ok(())
@@ -103,14 +108,17 @@ impl<S: ScannerStack> RunningScan<S> {
             .storage
             .execution_plan::<T>(&self.scan)
             .map_err(make_scheduling_error)?;
-        ScanRunner::new(
+        let runner = ScanRunner::new(
             &*self.storage,
             &*self.loader,
             &self.function_executor,
             schedule,
             &self.scan,
         )
-        .map_err(make_scheduling_error)
+        .map_err(make_scheduling_error)?;
+        #[cfg(feature = "metrics")]
+        let runner = runner.with_metrics(&super::GlobalMetrics);
+        Ok(runner)
     }
 
     async fn run_to_completion(&self, runner: ScanRunner<'_, S>) -> Phase {
@@ -120,9 +128,12 @@ impl<S: ScannerStack> RunningScan<S> {
             match it {
                 Ok(result) => {
                     trace!(target = result.target, targets=?self.scan.target.hosts);
-                    let mut status = self.status.write().await;
-                    if let Some(host_info) = status.host_info.as_mut() {
-                        host_info.register_finished_script(&result.target);
+                    // additional forks belong to an already registered script
+                    if !result.is_forked() {
+                        let mut status = self.status.write().await;
+                        if let Some(host_info) = status.host_info.as_mut() {
+                            host_info.register_finished_script(&result.target);
+                        }
                     }
                     debug!(result=?result, "script finished");
 
@@ -189,24 +200,25 @@ mod tests {
         scanner::{ScanResultFetcher, ScanResults, ScanStarter},
         Scan,
     };
-    use crate::storage::{item::Nvt, DefaultDispatcher};
+    use crate::storage::item::Nvt;
     use tracing_test::traced_test;
 
     use crate::scanner::{
-        scan_runner::tests::{setup, setup_success, GenerateScript},
-        Scanner,
+        scan_runner::tests::{only_success, GenerateScript},
+        Scanner, TestScanHarness, TestScannerStack as TestStack,
     };
 
-    type TestStack = (DefaultDispatcher, fn(&str) -> String);
-
     fn make_scanner_and_scan_success() -> (Scanner<TestStack>, Scan) {
-        let ((storage, loader, executor), scan) = setup_success();
-        (Scanner::new(storage, loader, executor), scan)
+        make_scanner_and_scan(&only_success())
     }
 
+    /// The scripts are described by the given Nvts, their code is the one of `only_success`
     fn make_scanner_and_scan(scripts: &[(String, Nvt)]) -> (Scanner<TestStack>, Scan) {
-        let ((storage, loader, executor), scan) = setup(scripts);
-        (Scanner::new(storage, loader, executor), scan)
+        let scripts = scripts
+            .iter()
+            .zip(only_success())
+            .map(|((_, nvt), (code, _))| (code, nvt.clone()));
+        TestScanHarness::new(scripts).into_scanner()
     }
 
     /// Blocks until given id is in given phase or panics after 1 second
@@ -2,36 +2,259 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
-use crate::models::{Host, HostInfo, Scan};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::models::{Host, HostInfo, Scan, ScanId};
 use crate::nasl::utils::Executor;
-use futures::{stream, Stream};
+use crate::storage::{ContextKey, Dispatcher, Field, Retrieve, Retriever};
+use futures::{stream, Stream, StreamExt};
+use tracing::Instrument;
 
+use crate::models::Parameter;
 use crate::scanner::ScannerStack;
-use crate::scheduling::{ConcurrentVT, VTError};
+use crate::scheduling::{ConcurrentVT, Stage, VTError};
+use crate::storage::item::Nvt;
 
-use super::error::{ExecuteError, ScriptResult};
+use super::error::{CheckpointError, ExecuteError, ScriptResult, ScriptResultKind};
+use super::host::TargetHost;
+use super::metrics::{NoMetrics, ScanMetrics, VtOutcome};
+use super::report::ScanReport;
 use super::scanner_stack::Schedule;
-use super::vt_runner::VTRunner;
+use super::vt_runner::{FilteredPorts, VTRunner};
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct Position {
     host: usize,
     stage: usize,
     vt: usize,
 }
 
-/// Provides an iterator over all hosts, stages and vts within the stage
-fn all_positions(hosts: Vec<Host>, vts: Vec<ConcurrentVT>) -> impl Iterator<Item = Position> {
-    hosts.into_iter().enumerate().flat_map(move |(host, _)| {
-        let vts = vts.clone();
-        vts.into_iter()
-            .enumerate()
-            .flat_map(move |(stage, (_, vts))| {
-                vts.into_iter()
-                    .enumerate()
-                    .map(move |(vt, _)| Position { host, stage, vt })
-            })
-    })
+/// KB key that marks a host on which all VTs of a scan were run, see
+/// [`ScanRunner::with_skip_finished_hosts`].
+///
+/// As an internal item it is not part of [`Retriever::dump_kb`](crate::storage::Retriever::dump_kb).
+pub const HOST_FINISHED_KEY: &str = "internal/host_finished";
+
+/// Order in which the VTs of a scan are run on its hosts, see [`ScanRunner::with_execution_order`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExecutionOrder {
+    /// All stages are run on a host before the next host is started
+    #[default]
+    PerHost,
+    /// A stage is run on all hosts before the next stage is started
+    ///
+    /// This returns results of every host early on instead of finishing one host after another.
+    Interleaved,
+}
+
+impl ExecutionOrder {
+    /// Returns a key to compare positions in the order of execution
+    fn key(self, pos: Position) -> (usize, usize, usize) {
+        match self {
+            ExecutionOrder::PerHost => (pos.host, pos.stage, pos.vt),
+            ExecutionOrder::Interleaved => (pos.stage, pos.host, pos.vt),
+        }
+    }
+}
+
+/// Identifies a single host within a scan.
+///
+/// KB items are scoped to a scan as well as a host. To not depend on the
+/// format of the host string the `ContextKey` is built from this structure
+/// instead of formatting both parts into one string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanTarget {
+    /// The id of the scan
+    pub scan_id: ScanId,
+    /// The index of the host within the hosts of the scan target
+    pub host_index: usize,
+    /// The host as given by the scan target
+    pub host: Host,
+    /// The parsed host
+    pub address: TargetHost,
+}
+
+impl ScanTarget {
+    /// Creates a target by parsing the given host.
+    pub fn new(scan_id: ScanId, host_index: usize, host: Host) -> Self {
+        let address = TargetHost::from(host.as_str());
+        Self {
+            scan_id,
+            host_index,
+            host,
+            address,
+        }
+    }
+
+    /// Returns the host in the form used within KB keys.
+    ///
+    /// To store and retrieve KB items of the same host consistently, IP addresses are
+    /// normalized to their canonical representation. Hostnames are returned unchanged.
+    pub fn kb_host(&self) -> Host {
+        self.address.to_string()
+    }
+}
+
+impl From<&ScanTarget> for ContextKey {
+    fn from(value: &ScanTarget) -> Self {
+        ContextKey::Scan(value.scan_id.clone(), Some(value.kb_host()))
+    }
+}
+
+/// Position within a scan run to resume it later on, see [`ScanRunner::resume_from`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Checkpoint {
+    /// Index of the host within the expanded hosts of the scan target
+    pub host: usize,
+    /// Index of the concurrent VTs within the schedule
+    pub stage: usize,
+    /// Index of the VT within the concurrent VTs
+    pub vt: usize,
+    /// Hash over the hosts and the schedule the checkpoint was created for
+    pub schedule_hash: u64,
+}
+
+/// Tracks the progress of a scan run, see [`ScanRunner::progress`].
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    next: Arc<Mutex<Position>>,
+    schedule_hash: u64,
+}
+
+impl ScanProgress {
+    fn set(&self, position: Position) {
+        *self.next.lock().unwrap() = position;
+    }
+
+    /// Returns a checkpoint of the first VT whose results were not yet returned.
+    ///
+    /// Resuming from it continues the scan without executing any VT whose results were already
+    /// returned by the stream of the runner.
+    pub fn checkpoint(&self) -> Checkpoint {
+        let next = self.next.lock().unwrap();
+        Checkpoint {
+            host: next.host,
+            stage: next.stage,
+            vt: next.vt,
+            schedule_hash: self.schedule_hash,
+        }
+    }
+}
+
+/// Calculates a hash over the hosts and the oids of each stage.
+///
+/// As long as the same hosts and VTs are scanned the hash is the same, which allows to verify
+/// that a checkpoint belongs to a rebuilt schedule.
+fn schedule_hash(hosts: &[Host], concurrent_vts: &[ConcurrentVT], order: ExecutionOrder) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    order.hash(&mut hasher);
+    hosts.hash(&mut hasher);
+    for (stage, vts) in concurrent_vts {
+        usize::from(*stage).hash(&mut hasher);
+        vts.len().hash(&mut hasher);
+        for (vt, _) in vts {
+            vt.oid.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Parses the hosts and removes hosts having the same KB host as an earlier one.
+///
+/// The order of the first occurrences is kept, see [`ScanTarget::kb_host`] for the comparison.
+fn unique_hosts(scan_id: &str, hosts: Vec<Host>) -> (Vec<Host>, Vec<TargetHost>) {
+    let mut seen = HashSet::new();
+    let mut duplicates = vec![];
+    let (hosts, addresses) = hosts
+        .into_iter()
+        .map(|host| {
+            let address = TargetHost::from(host.as_str());
+            (host, address)
+        })
+        .filter(|(host, address)| {
+            let first = seen.insert(address.clone());
+            if !first {
+                duplicates.push(host.clone());
+            }
+            first
+        })
+        .unzip();
+    if !duplicates.is_empty() {
+        tracing::warn!(scan_id, ?duplicates, "removed duplicated hosts");
+    }
+    (hosts, addresses)
+}
+
+/// A set of VTs of a schedule that may run concurrently, as returned by [`ScanRunner::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedStage {
+    /// The stage of the VTs
+    pub stage: Stage,
+    /// Object identifiers of the VTs in the order they are run
+    pub oids: Vec<String>,
+}
+
+impl From<&ConcurrentVT> for PlannedStage {
+    fn from((stage, vts): &ConcurrentVT) -> Self {
+        Self {
+            stage: *stage,
+            oids: vts.iter().map(|(vt, _)| vt.oid.clone()).collect(),
+        }
+    }
+}
+
+/// Describes whether a VT would run on a host, as returned by [`ScanRunner::dry_run`].
+#[derive(Debug)]
+pub struct DryRunEntry {
+    /// The host the VT would run on
+    pub host: Host,
+    /// Object identifier of the VT
+    pub oid: String,
+    /// The stage of the VT
+    pub stage: Stage,
+    /// The reason why the VT would not run
+    ///
+    /// It is the same `ScriptResultKind` that a real run reports for a skipped VT.
+    pub reason: Option<ScriptResultKind>,
+}
+
+impl DryRunEntry {
+    /// Returns true when the VT would be executed.
+    pub fn would_run(&self) -> bool {
+        self.reason.is_none()
+    }
+}
+
+/// Provides an iterator over all hosts, stages and vts within the stage in the given order
+fn all_positions(
+    hosts: usize,
+    vts: &[ConcurrentVT],
+    order: ExecutionOrder,
+) -> Box<dyn Iterator<Item = Position> + Send> {
+    let stages = vts.iter().map(|(_, vts)| vts.len()).collect::<Vec<_>>();
+    match order {
+        ExecutionOrder::PerHost => Box::new((0..hosts).flat_map(move |host| {
+            let stages = stages.clone();
+            stages
+                .into_iter()
+                .enumerate()
+                .flat_map(move |(stage, len)| (0..len).map(move |vt| Position { host, stage, vt }))
+        })),
+        ExecutionOrder::Interleaved => Box::new(stages.into_iter().enumerate().flat_map(
+            move |(stage, len)| {
+                (0..hosts)
+                    .flat_map(move |host| (0..len).map(move |vt| Position { host, stage, vt }))
+            },
+        )),
+    }
 }
 
 /// Runs a single scan by executing all the VTs within a given schedule.
@@ -40,10 +263,26 @@ fn all_positions(hosts: Vec<Host>, vts: Vec<ConcurrentVT>) -> impl Iterator<Item
 /// starting and stopping it), use `RunningScan` instead.
 pub struct ScanRunner<'a, S: ScannerStack> {
     scan: &'a Scan,
+    hosts: Vec<Host>,
+    addresses: Vec<TargetHost>,
     storage: &'a S::Storage,
     loader: &'a S::Loader,
     executor: &'a Executor,
     concurrent_vts: Vec<ConcurrentVT>,
+    excluded_keys: Vec<String>,
+    default_timeout: Option<Duration>,
+    filtered_udp_ports: FilteredPorts,
+    order: ExecutionOrder,
+    persist_results: bool,
+    max_results: Option<usize>,
+    max_kb_value_size: Option<usize>,
+    fail_fast: bool,
+    deadline: Option<Instant>,
+    skip_finished_hosts: bool,
+    start: Position,
+    progress: ScanProgress,
+    span: tracing::Span,
+    metrics: &'a dyn ScanMetrics,
 }
 
 impl<'a, Stack: ScannerStack> ScanRunner<'a, Stack> {
@@ -58,86 +297,616 @@ impl<'a, Stack: ScannerStack> ScanRunner<'a, Stack> {
         Sched: Schedule + 'a,
     {
         let concurrent_vts = schedule.cache()?;
+        let (hosts, addresses) = unique_hosts(&scan.scan_id, scan.target.expanded_hosts()?);
+        let progress = ScanProgress {
+            next: Arc::default(),
+            schedule_hash: schedule_hash(&hosts, &concurrent_vts, ExecutionOrder::default()),
+        };
         Ok(Self {
             scan,
+            hosts,
+            addresses,
             storage,
             loader,
             executor,
             concurrent_vts,
+            excluded_keys: vec![],
+            default_timeout: None,
+            filtered_udp_ports: FilteredPorts::default(),
+            order: ExecutionOrder::default(),
+            persist_results: false,
+            max_results: None,
+            max_kb_value_size: None,
+            fail_fast: false,
+            deadline: None,
+            skip_finished_hosts: false,
+            start: Position::default(),
+            progress,
+            span: tracing::error_span!(
+                "scan",
+                scan_id = %scan.scan_id,
+                correlation_id = tracing::field::Empty
+            ),
+            metrics: &NoMetrics,
         })
     }
 
+    /// Sets an external correlation id, e.g. the trace id of the request that started the scan.
+    ///
+    /// The logs of a scan are within a `scan` span carrying the `scan_id` and the correlation id,
+    /// the logs of a VT are nested within a `host` span carrying the `host` and a `vt` span
+    /// carrying the `oid` and the `stage`.
+    pub fn with_correlation_id(self, correlation_id: &str) -> Self {
+        self.span
+            .record("correlation_id", tracing::field::display(correlation_id));
+        self
+    }
+
+    /// Sets the metrics that are informed about the outcome and duration of each VT.
+    ///
+    /// By default no metrics are collected.
+    pub fn with_metrics(mut self, metrics: &'a dyn ScanMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Sets whether a filtered UDP port satisfies the required UDP ports of a VT.
+    ///
+    /// By default filtered ports are accepted.
+    pub fn with_filtered_udp_ports(mut self, filtered_udp_ports: FilteredPorts) -> Self {
+        self.filtered_udp_ports = filtered_udp_ports;
+        self
+    }
+
+    /// Sets whether each script result is stored as a result of the scan as soon as it is
+    /// produced.
+    ///
+    /// This keeps the results of a scan that crashes midway, but doubles the writes for callers
+    /// that store the returned results themselves. Therefore it is disabled by default.
+    /// The ids continue after the results already stored for the scan, e.g. by a run that is
+    /// resumed.
+    pub fn with_persisted_results(mut self, persist_results: bool) -> Self {
+        self.persist_results = persist_results;
+        self
+    }
+
+    /// Sets the maximum number of messages a single VT may send on a host.
+    ///
+    /// A VT exceeding it is aborted with `ResultLimitExceeded`, so that e.g. a VT sending
+    /// messages in an endless loop doesn't flood the storage. By default it is unlimited.
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Sets the maximum size in bytes of a KB value that is read to verify the keys of a VT.
+    ///
+    /// A VT depending on a larger value doesn't run and is marked as `StorageError`, so that
+    /// the contents of a host can't exhaust the memory of the scanner. By default it is
+    /// unlimited.
+    pub fn with_max_kb_value_size(mut self, max_kb_value_size: usize) -> Self {
+        self.max_kb_value_size = Some(max_kb_value_size);
+        self
+    }
+
+    /// Sets whether the scan stops at the first failed VT.
+    ///
+    /// When enabled, the stream ends after the first result that has failed or the first
+    /// `ExecuteError`. The hosts that were started are still finished. This is meant for e.g.
+    /// CI runs; by default all VTs are run regardless of failures.
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Sets a point in time after which no further VT is started.
+    ///
+    /// The deadline is checked before each VT, a running VT is only bounded by its timeout.
+    /// Once it passed, the started hosts are finished and the stream ends with a
+    /// `DeadlineExceeded` for each remaining VT on each host, containing the amount of the
+    /// VTs that were not run from this one on. Those VTs don't move the progress on, so that
+    /// the scan can be resumed from a checkpoint.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets whether hosts that were finished by a previous run of the scan are skipped.
+    ///
+    /// After all VTs were run on a host, the KB item [`HOST_FINISHED_KEY`] is set for it. When
+    /// enabled, the hosts having that item in the storage are skipped entirely, which allows to
+    /// re-run a scan that was interrupted without a checkpoint. Hosts that were finished early by
+    /// a fail fast or the deadline are not marked.
+    pub fn with_skip_finished_hosts(mut self, skip_finished_hosts: bool) -> Self {
+        self.skip_finished_hosts = skip_finished_hosts;
+        self
+    }
+
+    /// Sets whether hosts that are contained multiple times in the expanded hosts of the scan
+    /// target are scanned repeatedly.
+    ///
+    /// By default only the first occurrence of a host is scanned, the hosts are compared by
+    /// their [`ScanTarget::kb_host`]. As it changes the hosts, it must be set before limiting
+    /// them and before resuming from a checkpoint.
+    pub fn with_duplicate_hosts(mut self, keep_duplicate_hosts: bool) -> Self {
+        // the expansion already succeeded when the runner was created
+        let Some(hosts) = self
+            .scan
+            .target
+            .expanded_hosts()
+            .ok()
+            .filter(|_| keep_duplicate_hosts)
+        else {
+            return self;
+        };
+        self.addresses = hosts.iter().map(|x| TargetHost::from(x.as_str())).collect();
+        self.hosts = hosts;
+        self.progress.schedule_hash = schedule_hash(&self.hosts, &self.concurrent_vts, self.order);
+        self
+    }
+
+    /// Limits the scan to the first `max_hosts` hosts of the scan target.
+    ///
+    /// This is meant for smoke tests and quick validations of a scan without changing the scan
+    /// itself. As the limit changes the VTs that are run, it must be set before resuming from a
+    /// checkpoint.
+    pub fn with_max_hosts(mut self, max_hosts: usize) -> Self {
+        self.hosts.truncate(max_hosts);
+        self.addresses.truncate(max_hosts);
+        self.progress.schedule_hash = schedule_hash(&self.hosts, &self.concurrent_vts, self.order);
+        self
+    }
+
+    /// Limits the scan to the first `max_vts` VTs of the schedule.
+    ///
+    /// The VTs are counted in the order of the schedule, so that the dependencies of the
+    /// remaining VTs are kept. Each host is still finished after its last remaining VT. As the
+    /// limit changes the VTs that are run, it must be set before resuming from a checkpoint.
+    pub fn with_max_vts(mut self, max_vts: usize) -> Self {
+        let mut remaining = max_vts;
+        for (_, vts) in self.concurrent_vts.iter_mut() {
+            vts.truncate(remaining);
+            remaining -= vts.len();
+        }
+        self.concurrent_vts.retain(|(_, vts)| !vts.is_empty());
+        self.progress.schedule_hash = schedule_hash(&self.hosts, &self.concurrent_vts, self.order);
+        self
+    }
+
+    /// Sets the order in which the VTs are run on the hosts.
+    ///
+    /// By default all VTs are run on a host before the next host is started. Independent of
+    /// the order, a VT is only run on a host after the VTs of the earlier stages were run on it.
+    /// As the order defines which VTs were already run, it must be set before resuming from a
+    /// checkpoint and must be the same as in the run the checkpoint was taken from.
+    pub fn with_execution_order(mut self, order: ExecutionOrder) -> Self {
+        self.order = order;
+        self.progress.schedule_hash = schedule_hash(&self.hosts, &self.concurrent_vts, order);
+        self
+    }
+
+    /// Continues a previous run of the same scan at the given checkpoint.
+    ///
+    /// VTs before the checkpoint are skipped. The schedule must be built the same way as for
+    /// the run the checkpoint was taken from, otherwise a `ScheduleMismatch` is returned.
+    pub fn resume_from(mut self, checkpoint: Checkpoint) -> Result<Self, CheckpointError> {
+        if checkpoint.schedule_hash != self.progress.schedule_hash {
+            return Err(CheckpointError::ScheduleMismatch);
+        }
+        self.start = Position {
+            host: checkpoint.host,
+            stage: checkpoint.stage,
+            vt: checkpoint.vt,
+        };
+        self.progress.set(self.start);
+        Ok(self)
+    }
+
+    /// Returns a handle to create checkpoints while the scan is running.
+    pub fn progress(&self) -> ScanProgress {
+        self.progress.clone()
+    }
+
+    /// Sets keys that are known to be present for every host of the scan.
+    ///
+    /// A VT excluding any of those keys is marked as `ContainsExcludedKey` for
+    /// all hosts without looking up the key in the storage per host.
+    pub fn with_excluded_keys(mut self, excluded_keys: Vec<String>) -> Self {
+        self.excluded_keys = excluded_keys;
+        self
+    }
+
+    /// Sets the timeout of VTs that don't declare an own timeout via `script_timeout`.
+    ///
+    /// Without a default timeout those VTs run without time limit.
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the planned stages of the scan with the VTs of each stage
+    ///
+    /// A stage may occur multiple times when its VTs depend on each other. The plan is the same
+    /// for every host. To get the plan of a schedule without creating a runner use
+    /// [`Schedule::materialize_plan`].
+    pub fn plan(&self) -> Vec<PlannedStage> {
+        self.concurrent_vts.iter().map(PlannedStage::from).collect()
+    }
+
     pub fn host_info(&self) -> HostInfo {
-        HostInfo::from_hosts_and_num_vts(&self.scan.target.hosts, self.concurrent_vts.len())
-    }
-
-    pub fn stream(self) -> impl Stream<Item = Result<ScriptResult, ExecuteError>> + 'a {
-        let data = all_positions(self.scan.target.hosts.clone(), self.concurrent_vts.clone()).map(
-            move |pos| {
-                let (stage, vts) = &self.concurrent_vts[pos.stage];
-                let (vt, param) = &vts[pos.vt];
-                let host = &self.scan.target.hosts[pos.host];
-                (
-                    *stage,
-                    vt.clone(),
-                    param.clone(),
-                    host.clone(),
-                    self.scan.scan_id.clone(),
-                )
+        HostInfo::from_hosts_and_num_vts(&self.hosts, self.concurrent_vts.len())
+    }
+
+    /// Runs all VTs on all hosts and aggregates the results into a `ScanReport`.
+    pub async fn run_to_completion(self) -> ScanReport {
+        let mut report = ScanReport::default();
+        let mut results = Box::pin(self.stream());
+        while let Some(result) = results.next().await {
+            report.add(result);
+        }
+        report
+    }
+
+    /// Returns the target of a host without parsing the host again.
+    fn target(&self, host: usize) -> ScanTarget {
+        ScanTarget {
+            scan_id: self.scan.scan_id.clone(),
+            host_index: host,
+            host: self.hosts[host].clone(),
+            address: self.addresses[host].clone(),
+        }
+    }
+
+    /// Returns the VT, its parameter and target as well as a globally excluded key of a position.
+    fn at(
+        &self,
+        pos: Position,
+    ) -> (
+        Stage,
+        Nvt,
+        Option<Vec<Parameter>>,
+        ScanTarget,
+        Option<String>,
+    ) {
+        let (stage, vts) = &self.concurrent_vts[pos.stage];
+        let (vt, param) = &vts[pos.vt];
+        let target = self.target(pos.host);
+        let excluded = self
+            .excluded_keys
+            .iter()
+            .find(|k| vt.excluded_keys.contains(k))
+            .cloned();
+        (*stage, vt.clone(), param.clone(), target, excluded)
+    }
+
+    /// Checks which VTs would run on which host without executing any of them.
+    ///
+    /// The same key and port checks as in a real run are applied, but against the
+    /// current state of the storage. KB items that are set by VTs of earlier stages
+    /// during a real run are therefore not taken into account.
+    pub fn dry_run(&self) -> Vec<DryRunEntry> {
+        all_positions(self.hosts.len(), &self.concurrent_vts, self.order)
+            .map(|pos| {
+                let (stage, vt, _, target, excluded) = self.at(pos);
+                let reason = match excluded {
+                    Some(key) => Some(ScriptResultKind::ContainsExcludedKey(key)),
+                    None => VTRunner::<Stack>::check(
+                        self.storage,
+                        self.loader,
+                        self.executor,
+                        &target,
+                        &vt,
+                        stage,
+                        self.filtered_udp_ports,
+                        self.max_kb_value_size,
+                    )
+                    .err(),
+                };
+                DryRunEntry {
+                    host: target.host,
+                    oid: vt.oid,
+                    stage,
+                    reason,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the indices of the hosts that are marked as finished in the storage
+    fn finished_hosts(&self) -> BTreeSet<usize> {
+        if !self.skip_finished_hosts {
+            return BTreeSet::new();
+        }
+        (0..self.hosts.len())
+            .filter(|&host| {
+                let key = ContextKey::from(&self.target(host));
+                match self
+                    .storage
+                    .retrieve(&key, Retrieve::KB(HOST_FINISHED_KEY.to_owned()))
+                {
+                    Ok(mut x) => x.next().is_some(),
+                    Err(e) => {
+                        tracing::warn!(error=%e, %key, "unable to check if host is finished");
+                        false
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the id following the ids of the results already stored for the scan
+    ///
+    /// This keeps the ids unique when a scan is resumed.
+    fn next_result_id(&self) -> usize {
+        let key = ContextKey::Scan(self.scan.scan_id.clone(), None);
+        match self.storage.results(&key) {
+            Ok(x) => x.map(|x| x.id + 1).max().unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!(error=%e, %key, "unable to get the stored results");
+                0
+            }
+        }
+    }
+
+    pub fn stream(self) -> impl Stream<Item = Result<ScriptResult, ExecuteError>> + 'a
+    where
+        Stack: 'a,
+    {
+        let (storage, loader, executor) = (self.storage, self.loader, self.executor);
+        let (default_timeout, filtered_udp_ports) = (self.default_timeout, self.filtered_udp_ports);
+        let (persist_results, max_results) = (self.persist_results, self.max_results);
+        let (max_kb_value_size, fail_fast) = (self.max_kb_value_size, self.fail_fast);
+        let (deadline, metrics) = (self.deadline, self.metrics);
+        let first_id = if persist_results {
+            self.next_result_id()
+        } else {
+            0
+        };
+        let progress = self.progress();
+        let (order, start) = (self.order, self.start);
+        let end = match order {
+            ExecutionOrder::PerHost => Position {
+                host: self.hosts.len(),
+                ..Default::default()
             },
-        );
+            ExecutionOrder::Interleaved => Position {
+                stage: self.concurrent_vts.len(),
+                ..Default::default()
+            },
+        };
+        // the stage and vt of the last position of each host
+        let last = self
+            .concurrent_vts
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, (_, vts))| !vts.is_empty())
+            .map(|(stage, (_, vts))| (stage, vts.len() - 1));
+        let finished = self.finished_hosts();
+        let host_spans = self
+            .hosts
+            .iter()
+            .map(|host| tracing::error_span!(parent: &self.span, "host", %host))
+            .collect::<Vec<_>>();
+        let data = all_positions(self.hosts.len(), &self.concurrent_vts, order)
+            .filter(move |pos| order.key(*pos) >= order.key(start))
+            .filter(move |pos| !finished.contains(&pos.host))
+            .map(move |pos| {
+                // the last VT of a set of concurrent VTs finishes the stage on the host
+                let stage_finished = pos.vt + 1 == self.concurrent_vts[pos.stage].1.len();
+                let at = self.at(pos);
+                let span = tracing::error_span!(
+                    parent: &host_spans[pos.host],
+                    "vt",
+                    oid = %at.1.oid,
+                    stage = %at.0
+                );
+                (pos, stage_finished, span, at)
+            })
+            .peekable();
         // The usage of unfold here will prevent any real asynchronous running of VTs
         // and automatically guarantee that we stick to the scheduling requirements.
         // If this is changed, make sure to uphold the scheduling requirements in the
         // new implementation.
-        stream::unfold(data, move |mut data| async move {
-            if let Some((stage, vt, param, host, scan_id)) = data.next() {
-                let result = VTRunner::<Stack>::run(
-                    self.storage,
-                    self.loader,
-                    self.executor,
-                    &host,
-                    &vt,
-                    stage,
-                    param.as_ref(),
-                    &scan_id,
-                )
-                .await;
-                Some((result, data))
-            } else {
-                None
+        // A single VT may produce multiple results when it forks, therefore the results
+        // of each VT are flattened into the stream.
+        // The progress moves on with the last result of a VT, so that a checkpoint never
+        // contains a VT whose results were already returned.
+        // The started hosts are kept until they are finished, so that a fail fast can finish
+        // the hosts that are still running.
+        let finish = move |key: &ContextKey| {
+            if let Err(e) = storage.host_finished(key) {
+                tracing::warn!(error=%e, %key, "unable to finish host");
             }
-        })
+        };
+        stream::unfold(
+            (data, BTreeMap::new(), first_id, false, progress),
+            move |(mut data, mut started, mut persisted, stopped, progress)| async move {
+                if stopped {
+                    return None;
+                }
+                if let Some((pos, stage_finished, span, (stage, vt, param, target, excluded))) =
+                    data.next()
+                {
+                    let next = data.peek().map(|(pos, _, _, _)| *pos).unwrap_or(end);
+                    let key = ContextKey::from(&target);
+                    let expired = deadline.is_some_and(|x| Instant::now() >= x);
+                    if expired && !started.is_empty() {
+                        tracing::debug!(parent: &span, "deadline of the scan passed");
+                        started.values().for_each(finish);
+                        started.clear();
+                    }
+                    if !expired && !started.contains_key(&pos.host) {
+                        started.insert(pos.host, key.clone());
+                        if let Err(e) = storage.host_started(&key) {
+                            tracing::warn!(parent: &span, error=%e, "unable to start host");
+                        }
+                    }
+                    let result = |kind| ScriptResult {
+                        oid: vt.oid.clone(),
+                        filename: vt.filename.clone(),
+                        stage,
+                        kind,
+                        target: target.host.clone(),
+                        messages: vec![],
+                    };
+                    let vt_start = Instant::now();
+                    let mut results = match excluded {
+                        _ if expired => {
+                            // each remaining VT is reported, so that it is counted as not run
+                            // on its host
+                            let remaining = data.by_ref().map(|(_, _, _, (stage, vt, _, x, _))| {
+                                (stage, vt, x.host)
+                            });
+                            let not_run = std::iter::once((stage, vt.clone(), target.host.clone()))
+                                .chain(remaining)
+                                .collect::<Vec<_>>();
+                            let skipped = not_run.len();
+                            not_run
+                                .into_iter()
+                                .enumerate()
+                                .map(|(i, (stage, vt, host))| {
+                                    Ok(ScriptResult {
+                                        oid: vt.oid,
+                                        filename: vt.filename,
+                                        stage,
+                                        kind: ScriptResultKind::DeadlineExceeded(skipped - i),
+                                        target: host,
+                                        messages: vec![],
+                                    })
+                                })
+                                .collect()
+                        }
+                        Some(key) => vec![Ok(result(ScriptResultKind::ContainsExcludedKey(key)))],
+                        None => {
+                            let run = VTRunner::<Stack>::run(
+                                storage,
+                                loader,
+                                executor,
+                                &target,
+                                &vt,
+                                stage,
+                                param.as_ref(),
+                                filtered_udp_ports,
+                                max_results,
+                                max_kb_value_size,
+                            )
+                            .instrument(span.clone());
+                            let results = match vt.timeout().or(default_timeout) {
+                                Some(timeout) => tokio::time::timeout(timeout, run)
+                                    .await
+                                    .unwrap_or_else(|_| {
+                                        Ok(vec![result(ScriptResultKind::TimedOut(timeout))])
+                                    }),
+                                None => run.await,
+                            };
+                            match results {
+                                Ok(results) => results.into_iter().map(Ok).collect(),
+                                Err(e) => vec![Err(e)],
+                            }
+                        }
+                    };
+                    if let Some(outcome) = VtOutcome::worst(&results) {
+                        metrics.vt_finished(outcome, vt_start.elapsed());
+                    }
+                    let failed = results
+                        .iter()
+                        .position(|x| x.as_ref().map_or(true, ScriptResult::has_failed))
+                        .filter(|_| fail_fast);
+                    if let Some(i) = failed {
+                        tracing::debug!(parent: &span, "failing fast");
+                        results.truncate(i + 1);
+                    }
+                    if persist_results {
+                        for result in results.iter().filter_map(|x| x.as_ref().ok()) {
+                            let result = Field::Result(Box::new((persisted, result).into()));
+                            persisted += 1;
+                            if let Err(e) = storage.retry_dispatch(5, &key, result) {
+                                tracing::warn!(parent: &span, error=%e, "unable to persist result");
+                            }
+                        }
+                    }
+                    if stage_finished && !expired && failed.is_none() {
+                        if let Err(e) = storage.stage_finished(&key, stage) {
+                            tracing::warn!(parent: &span, error=%e, "unable to finish stage");
+                        }
+                    }
+                    if last == Some((pos.stage, pos.vt)) && started.remove(&pos.host).is_some() {
+                        if failed.is_none() {
+                            let marker = Field::KB((HOST_FINISHED_KEY, 1).into());
+                            if let Err(e) = storage.dispatch(&key, marker) {
+                                tracing::warn!(parent: &span, error=%e, "unable to mark host as finished");
+                            }
+                        }
+                        finish(&key);
+                    }
+                    if failed.is_some() {
+                        started.values().for_each(finish);
+                        started.clear();
+                    }
+                    let (last, current) = (results.len(), progress.clone());
+                    let results = results.into_iter().enumerate().map(move |(i, result)| {
+                        if i + 1 == last && !expired {
+                            current.set(next);
+                        }
+                        result
+                    });
+                    let stopped = failed.is_some() || expired;
+                    let state = (data, started, persisted, stopped, progress);
+                    Some((stream::iter(results), state))
+                } else {
+                    None
+                }
+            },
+        )
+        .flatten()
     }
 }
 
 #[cfg(test)]
 pub(super) mod tests {
+    use std::time::Duration;
+
+    use crate::models::Parameter;
     use crate::models::Protocol;
+    use crate::models::ResultType;
     use crate::models::Scan;
     use crate::models::Target;
     use crate::models::VT;
+    use crate::nasl::interpreter::InterpretErrorKind;
+    use crate::nasl::prelude::{function_set, nasl_function};
+    use crate::nasl::syntax::extract_metadata;
+    use crate::nasl::syntax::LoadError;
     use crate::nasl::syntax::NaslValue;
     use crate::nasl::utils::context::Target as ContextTarget;
     use crate::nasl::utils::Context;
-    use crate::nasl::utils::Executor;
     use crate::nasl::utils::Register;
     use crate::nasl::{interpreter::CodeInterpreter, nasl_std_functions};
     use crate::scanner::{
-        error::{ExecuteError, ScriptResult},
-        scan_runner::ScanRunner,
-        vt_runner::generate_port_kb_key,
+        error::{CheckpointError, ExecuteError, ScriptResult, ScriptResultKind},
+        scan_runner::{Checkpoint, ScanRunner, ScanTarget},
+        scanner_stack::Schedule,
+        vt_runner::{generate_port_kb_key, FilteredPorts},
+        ExecutionOrder, InMemoryLoader, ScanMetrics, ScannerStack, TestScanHarness,
+        TestScannerStack, VtOutcome, HOST_FINISHED_KEY,
     };
-    use crate::scheduling::{ExecutionPlaner, WaveExecutionPlan};
+    use crate::scheduling::{ExecutionPlaner, Stage, VTError, WaveExecutionPlan};
     use crate::storage::item::NVTField;
     use crate::storage::item::Nvt;
+    use crate::storage::item::NvtPreference;
+    use crate::storage::item::PreferenceType;
     use crate::storage::ContextKey;
     use crate::storage::DefaultDispatcher;
     use crate::storage::Dispatcher;
     use crate::storage::Field;
     use crate::storage::Field::NVT;
+    use crate::storage::FieldKeyResult;
+    use crate::storage::Kb;
+    use crate::storage::Remover;
     use crate::storage::Retrieve;
     use crate::storage::Retriever;
+    use crate::storage::Storage;
+    use crate::storage::StorageError;
     use futures::StreamExt;
 
     pub fn only_success() -> [(String, Nvt); 3] {
@@ -148,47 +917,6 @@ pub(super) mod tests {
         ]
     }
 
-    fn loader(s: &str) -> String {
-        let only_success = only_success();
-        let stou = |s: &str| s.split('.').next().unwrap().parse::<usize>().unwrap();
-        only_success[stou(s)].0.clone()
-    }
-
-    pub fn setup(
-        scripts: &[(String, Nvt)],
-    ) -> ((DefaultDispatcher, fn(&str) -> String, Executor), Scan) {
-        let storage = DefaultDispatcher::new();
-        scripts.iter().map(|(_, v)| v).for_each(|n| {
-            storage
-                .dispatch(
-                    &ContextKey::FileName(n.filename.clone()),
-                    NVT(NVTField::Nvt(n.clone())),
-                )
-                .expect("sending")
-        });
-        let scan = Scan {
-            scan_id: "sid".to_string(),
-            target: Target {
-                hosts: vec!["test.host".to_string()],
-                ..Default::default()
-            },
-            scan_preferences: vec![],
-            vts: scripts
-                .iter()
-                .map(|(_, v)| VT {
-                    oid: v.oid.clone(),
-                    parameters: vec![],
-                })
-                .collect(),
-        };
-        let executor = nasl_std_functions();
-        ((storage, loader, executor), scan)
-    }
-
-    pub fn setup_success() -> ((DefaultDispatcher, fn(&str) -> String, Executor), Scan) {
-        setup(&only_success())
-    }
-
     #[derive(Debug, Default)]
     pub struct GenerateScript {
         pub id: String,
@@ -199,6 +927,8 @@ pub(super) mod tests {
         pub required_tcp_ports: Vec<String>,
         pub required_udp_ports: Vec<String>,
         pub exclude: Vec<String>,
+        pub deprecated: bool,
+        pub timeout: u64,
     }
 
     impl GenerateScript {
@@ -285,6 +1015,16 @@ pub(super) mod tests {
             let exclude = printable("script_exclude_keys", &self.exclude);
             let require_ports = printable("script_require_ports", &self.required_tcp_ports);
             let require_udp_ports = printable("script_require_udp_ports", &self.required_udp_ports);
+            let timeout = if self.timeout > 0 {
+                format!("script_timeout({});", self.timeout)
+            } else {
+                String::default()
+            };
+            let deprecated = if self.deprecated {
+                r#"script_tag(name: "deprecated", value: TRUE);"#
+            } else {
+                ""
+            };
 
             let rc = self.rc;
             let id = &self.id;
@@ -301,6 +1041,8 @@ if (description)
   {exclude}
   {require_ports}
   {require_udp_ports}
+  {deprecated}
+  {timeout}
   exit(0);
 }}
 log_message(data: "Hello world.");
@@ -406,9 +1148,44 @@ exit({rc});
         (success, failure)
     }
 
-    #[tokio::test]
-    #[tracing_test::traced_test]
-    async fn required_ports() {
+    async fn run_harness<S>(
+        harness: &TestScanHarness<S>,
+    ) -> Result<Vec<Result<ScriptResult, ExecuteError>>, ExecuteError>
+    where
+        S: Storage + Send + 'static,
+    {
+        let runner = harness.runner()?;
+        Ok(runner.stream().collect::<Vec<_>>().await)
+    }
+
+    /// Returns the results of the runner, skipping VTs that could not be executed
+    async fn results<S: ScannerStack>(runner: ScanRunner<'_, S>) -> Vec<ScriptResult> {
+        runner
+            .stream()
+            .filter_map(|x| async { x.ok() })
+            .collect()
+            .await
+    }
+
+    async fn get_all_harness_results<S>(
+        harness: &TestScanHarness<S>,
+    ) -> (Vec<ScriptResult>, Vec<ScriptResult>)
+    where
+        S: Storage + Send + 'static,
+    {
+        let result = run_harness(harness).await.expect("success run");
+        let (success, rest): (Vec<_>, Vec<_>) = result
+            .into_iter()
+            .filter_map(|x| x.ok())
+            .partition(|x| x.has_succeeded());
+        let failure = rest
+            .into_iter()
+            .filter(|x| !x.has_succeeded() && x.has_not_run())
+            .collect();
+        (success, failure)
+    }
+
+    fn required_ports_setup() -> TestScanHarness {
         let vts = [
             GenerateScript::with_required_ports(
                 "0",
@@ -436,7 +1213,7 @@ exit({rc});
             )
             .generate(),
         ];
-        let dispatcher = prepare_vt_storage(&vts);
+        let harness = TestScanHarness::new(vts);
         [
             (Protocol::TCP, "20", 1),   // TCP 20 is considered enabled
             (Protocol::TCP, "22", 0),   // TCP 22 is considered disabled
@@ -445,66 +1222,1599 @@ exit({rc});
         ]
         .into_iter()
         .for_each(|(p, port, enabled)| {
-            dispatcher
+            harness
+                .storage()
                 .dispatch(
-                    &ContextKey::Scan("sid".into(), Some("test.host".into())),
+                    &harness.host_key("localhost"),
                     Field::KB((&generate_port_kb_key(p, port), enabled).into()),
                 )
                 .expect("store kb");
         });
-        let (success, failure) = get_all_results(&vts, dispatcher).await;
-        assert_eq!(success.len(), 1);
-        assert_eq!(failure.len(), 4);
+        harness
     }
 
-    fn make_test_dispatcher(vts: &[(String, Nvt)]) -> DefaultDispatcher {
-        let dispatcher = prepare_vt_storage(vts);
-        dispatcher
+    #[test]
+    fn extract_metadata_without_execution() {
+        let (code, nvt) = GenerateScript {
+            id: "1".to_string(),
+            dependencies: vec!["0.nasl".to_string()],
+            mandatory_keys: vec!["key/mandatory".to_string()],
+            ..Default::default()
+        }
+        .generate();
+        let metadata = extract_metadata(&code).unwrap();
+        assert_eq!(metadata.oid, Some(nvt.oid));
+        assert_eq!(metadata.dependencies, nvt.dependencies);
+        assert_eq!(metadata.mandatory_keys, nvt.mandatory_keys);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn filtered_udp_port() {
+        let harness = TestScanHarness::new([GenerateScript::with_required_ports(
+            "0",
+            &[(Protocol::UDP, "161")],
+        )
+        .generate()]);
+        harness
+            .storage()
             .dispatch(
-                &ContextKey::Scan("sid".into(), Some("test.host".into())),
-                Field::KB(("key/exists", 1).into()),
+                &harness.host_key("localhost"),
+                Field::KB((&generate_port_kb_key(Protocol::UDP, "161"), "filtered").into()),
             )
             .expect("store kb");
-        dispatcher
+        let run = |policy| {
+            results(
+                harness
+                    .runner()
+                    .expect("runner")
+                    .with_filtered_udp_ports(policy),
+            )
+        };
+        let accepted = run(FilteredPorts::Accept).await;
+        assert!(accepted[0].has_succeeded());
+        let rejected = run(FilteredPorts::Reject).await;
+        assert!(rejected[0].has_not_run());
+        assert!(matches!(
+            &rejected[0].kind,
+            ScriptResultKind::PortFiltered(Protocol::UDP, port) if port == "161"
+        ));
     }
 
     #[tokio::test]
     #[tracing_test::traced_test]
-    async fn exclude_keys() {
-        let only_success = [
-            GenerateScript::with_excluded_keys("0", &["key/not"]).generate(),
-            GenerateScript::with_excluded_keys("1", &["key/not"]).generate(),
-            GenerateScript::with_excluded_keys("2", &["key/exists"]).generate(),
-        ];
-        let dispatcher = make_test_dispatcher(&only_success);
-        let (success, failure) = get_all_results(&only_success, dispatcher).await;
-        assert_eq!(success.len(), 2);
-        assert_eq!(failure.len(), 1);
+    async fn any_required_port() {
+        let harness = TestScanHarness::new([GenerateScript::with_required_ports(
+            "0",
+            &[(Protocol::TCP, "*")],
+        )
+        .generate()]);
+        let run = || results(harness.runner().expect("runner"));
+        let store = |port, enabled| {
+            harness
+                .storage()
+                .dispatch(
+                    &harness.host_key("localhost"),
+                    Field::KB((&generate_port_kb_key(Protocol::TCP, port), enabled).into()),
+                )
+                .expect("store kb")
+        };
+        assert!(matches!(
+            &run().await[0].kind,
+            ScriptResultKind::MissingPort(Protocol::TCP, port) if port == "*"
+        ));
+        store("21", 0);
+        assert!(run().await[0].has_not_run());
+        store("22", 1);
+        assert!(run().await[0].has_succeeded());
     }
 
     #[tokio::test]
     #[tracing_test::traced_test]
-    async fn required_keys() {
-        let only_success = [
-            GenerateScript::with_required_keys("0", &["key/not"]).generate(),
-            GenerateScript::with_required_keys("1", &["key/exists"]).generate(),
-        ];
-        let dispatcher = make_test_dispatcher(&only_success);
-        let (success, failure) = get_all_results(&only_success, dispatcher).await;
-        assert_eq!(success.len(), 1);
-        assert_eq!(failure.len(), 1);
+    async fn max_kb_value_size() {
+        let harness = TestScanHarness::new([
+            GenerateScript::with_required_keys("0", &["huge"]).generate(),
+            GenerateScript::with_required_ports("1", &[(Protocol::TCP, "*")]).generate(),
+        ]);
+        for kb in [
+            ("huge", "x".repeat(1024)),
+            ("Ports/tcp/22", "x".repeat(1024)),
+        ] {
+            harness
+                .storage()
+                .dispatch(&harness.host_key("localhost"), Field::KB(kb.into()))
+                .expect("store kb");
+        }
+        let run = |max| {
+            results(
+                harness
+                    .runner()
+                    .expect("runner")
+                    .with_max_kb_value_size(max),
+            )
+        };
+        assert!(run(1024).await.iter().all(|x| x.has_succeeded()));
+        let rejected = run(1023).await;
+        let kind = |oid: &str| &rejected.iter().find(|x| x.oid == oid).expect("result").kind;
+        assert!(matches!(
+            kind("0"),
+            ScriptResultKind::StorageError(x) if x.contains("huge")
+        ));
+        assert!(matches!(
+            kind("1"),
+            ScriptResultKind::StorageError(x) if x.contains("Ports/tcp/22")
+        ));
     }
 
     #[tokio::test]
     #[tracing_test::traced_test]
-    async fn mandatory_keys() {
-        let only_success = [
-            GenerateScript::with_mandatory_keys("0", &["key/not"]).generate(),
-            GenerateScript::with_mandatory_keys("1", &["key/exists"]).generate(),
-        ];
-        let dispatcher = make_test_dispatcher(&only_success);
-        let (success, failure) = get_all_results(&only_success, dispatcher).await;
-        assert_eq!(success.len(), 1);
-        assert_eq!(failure.len(), 1);
+    async fn required_ports() {
+        let vts = [
+            GenerateScript::with_required_ports(
+                "0",
+                &[(Protocol::UDP, "2000"), (Protocol::TCP, "20")],
+            )
+            .generate(),
+            GenerateScript::with_required_ports(
+                "1",
+                &[(Protocol::UDP, "2000"), (Protocol::TCP, "2")],
+            )
+            .generate(),
+            GenerateScript::with_required_ports(
+                "2",
+                &[(Protocol::UDP, "200"), (Protocol::TCP, "20")],
+            )
+            .generate(),
+            GenerateScript::with_required_ports(
+                "3",
+                &[(Protocol::UDP, "2000"), (Protocol::TCP, "22")],
+            )
+            .generate(),
+            GenerateScript::with_required_ports(
+                "4",
+                &[(Protocol::UDP, "2002"), (Protocol::TCP, "20")],
+            )
+            .generate(),
+        ];
+        let dispatcher = prepare_vt_storage(&vts);
+        [
+            (Protocol::TCP, "20", 1),   // TCP 20 is considered enabled
+            (Protocol::TCP, "22", 0),   // TCP 22 is considered disabled
+            (Protocol::UDP, "2000", 1), // UDP 2000 is considered enabled
+            (Protocol::UDP, "2002", 0), // UDP 2002 is considered disabled
+        ]
+        .into_iter()
+        .for_each(|(p, port, enabled)| {
+            dispatcher
+                .dispatch(
+                    &ContextKey::Scan("sid".into(), Some("test.host".into())),
+                    Field::KB((&generate_port_kb_key(p, port), enabled).into()),
+                )
+                .expect("store kb");
+        });
+        let (success, failure) = get_all_results(&vts, dispatcher).await;
+        assert_eq!(success.len(), 1);
+        assert_eq!(failure.len(), 4);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn run_to_completion() {
+        let harness = required_ports_setup();
+        let (success, failure) = get_all_harness_results(&harness).await;
+
+        let report = harness.runner().expect("runner").run_to_completion().await;
+        assert_eq!(report.total.succeeded, success.len());
+        assert_eq!(report.total.not_run, failure.len());
+        assert_eq!(report.total.failed, 0);
+        assert_eq!(report.hosts.len(), 1);
+        assert_eq!(report.hosts["localhost"], report.total);
+        assert_eq!(report.results.len(), harness.scan().vts.len());
+        assert!(report.errors.is_empty());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn duplicate_host() {
+        let vts = only_success();
+        let harness = TestScanHarness::new(vts.clone()).with_hosts(&[
+            "test.host",
+            "2001:db8::1",
+            " test.host",
+            "[2001:db8:0::1]",
+        ]);
+        let report = harness.runner().expect("runner").run_to_completion().await;
+        assert_eq!(report.hosts.len(), 2);
+        assert_eq!(report.results.len(), 2 * vts.len());
+        assert!(report.results.iter().all(|x| x.has_succeeded()));
+        assert!(logs_contain(
+            r#"duplicates=[" test.host", "[2001:db8:0::1]"]"#
+        ));
+
+        let report = harness
+            .runner()
+            .expect("runner")
+            .with_duplicate_hosts(true)
+            .run_to_completion()
+            .await;
+        assert_eq!(report.results.len(), 4 * vts.len());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn group_by_host() {
+        let hosts = ["first.host", "second.host"];
+        let harness = TestScanHarness::new(only_success()).with_hosts(&hosts);
+        let grouped = harness
+            .runner()
+            .expect("runner")
+            .run_to_completion()
+            .await
+            .group_by_host();
+        assert_eq!(grouped.len(), hosts.len());
+        for host in hosts {
+            let results = &grouped[host];
+            assert!(results.iter().all(|x| x.target == host));
+            let oids = results.iter().map(|x| x.oid.as_str()).collect::<Vec<_>>();
+            assert_eq!(oids, ["0", "1", "2"]);
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn resume_from_checkpoint() {
+        let vts = only_success();
+        let harness = TestScanHarness::new(vts.clone()).with_hosts(&["first.host", "second.host"]);
+        let runner = || harness.runner().expect("runner");
+
+        let first = runner();
+        let progress = first.progress();
+        let mut executed = first
+            .stream()
+            .take(vts.len())
+            .filter_map(|x| async { x.ok() })
+            .map(|x| (x.target, x.oid))
+            .collect::<Vec<_>>()
+            .await;
+        let checkpoint = progress.checkpoint();
+        assert_eq!(
+            (checkpoint.host, checkpoint.stage, checkpoint.vt),
+            (1, 0, 0)
+        );
+
+        let resumed = runner().resume_from(checkpoint).expect("same schedule");
+        executed.extend(
+            resumed
+                .stream()
+                .filter_map(|x| async { x.ok() })
+                .map(|x| (x.target, x.oid))
+                .collect::<Vec<_>>()
+                .await,
+        );
+        assert_eq!(executed.len(), 2 * vts.len());
+        executed.sort();
+        executed.dedup();
+        assert_eq!(executed.len(), 2 * vts.len());
+
+        let other = Checkpoint {
+            schedule_hash: checkpoint.schedule_hash.wrapping_add(1),
+            ..checkpoint
+        };
+        assert!(matches!(
+            runner().resume_from(other),
+            Err(CheckpointError::ScheduleMismatch)
+        ));
+    }
+
+    /// Storage that fails on every KB lookup while serving everything else
+    struct FailingKbStorage(DefaultDispatcher);
+
+    impl Dispatcher for FailingKbStorage {
+        fn dispatch(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+            self.0.dispatch(key, scope)
+        }
+
+        fn dispatch_replace(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+            self.0.dispatch_replace(key, scope)
+        }
+
+        fn on_exit(&self, key: &ContextKey) -> Result<(), StorageError> {
+            self.0.on_exit(key)
+        }
+    }
+
+    impl Retriever for FailingKbStorage {
+        fn retrieve(
+            &self,
+            key: &ContextKey,
+            scope: Retrieve,
+        ) -> Result<Box<dyn Iterator<Item = Field>>, StorageError> {
+            match scope {
+                Retrieve::KB(_) => Err(StorageError::ConnectionLost("unreachable".into())),
+                scope => self.0.retrieve(key, scope),
+            }
+        }
+
+        fn retrieve_by_field(&self, field: Field, scope: Retrieve) -> FieldKeyResult {
+            self.0.retrieve_by_field(field, scope)
+        }
+
+        fn retrieve_by_fields(&self, field: Vec<Field>, scope: Retrieve) -> FieldKeyResult {
+            self.0.retrieve_by_fields(field, scope)
+        }
+    }
+
+    impl Remover for FailingKbStorage {
+        fn remove_kb(
+            &self,
+            key: &ContextKey,
+            kb_key: Option<String>,
+        ) -> Result<Option<Vec<Kb>>, StorageError> {
+            self.0.remove_kb(key, kb_key)
+        }
+
+        fn remove_result(
+            &self,
+            key: &ContextKey,
+            result_id: Option<usize>,
+        ) -> Result<Option<Vec<crate::models::Result>>, StorageError> {
+            self.0.remove_result(key, result_id)
+        }
+    }
+
+    /// Storage that records the calls of the host hooks
+    struct RecordingStorage(
+        DefaultDispatcher,
+        std::sync::Mutex<Vec<(&'static str, ContextKey)>>,
+        std::sync::Mutex<Vec<(ContextKey, Stage)>>,
+    );
+
+    impl RecordingStorage {
+        fn new(storage: DefaultDispatcher) -> Self {
+            Self(storage, Default::default(), Default::default())
+        }
+    }
+
+    impl Dispatcher for RecordingStorage {
+        fn dispatch(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+            self.0.dispatch(key, scope)
+        }
+
+        fn dispatch_replace(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+            self.0.dispatch_replace(key, scope)
+        }
+
+        fn on_exit(&self, key: &ContextKey) -> Result<(), StorageError> {
+            self.0.on_exit(key)
+        }
+
+        fn host_started(&self, key: &ContextKey) -> Result<(), StorageError> {
+            self.1.lock().unwrap().push(("started", key.clone()));
+            Ok(())
+        }
+
+        fn host_finished(&self, key: &ContextKey) -> Result<(), StorageError> {
+            self.1.lock().unwrap().push(("finished", key.clone()));
+            Ok(())
+        }
+
+        fn stage_finished(&self, key: &ContextKey, stage: Stage) -> Result<(), StorageError> {
+            self.2.lock().unwrap().push((key.clone(), stage));
+            Ok(())
+        }
+    }
+
+    impl Retriever for RecordingStorage {
+        fn retrieve(
+            &self,
+            key: &ContextKey,
+            scope: Retrieve,
+        ) -> Result<Box<dyn Iterator<Item = Field>>, StorageError> {
+            self.0.retrieve(key, scope)
+        }
+
+        fn retrieve_by_field(&self, field: Field, scope: Retrieve) -> FieldKeyResult {
+            self.0.retrieve_by_field(field, scope)
+        }
+
+        fn retrieve_by_fields(&self, field: Vec<Field>, scope: Retrieve) -> FieldKeyResult {
+            self.0.retrieve_by_fields(field, scope)
+        }
+    }
+
+    impl Remover for RecordingStorage {
+        fn remove_kb(
+            &self,
+            key: &ContextKey,
+            kb_key: Option<String>,
+        ) -> Result<Option<Vec<Kb>>, StorageError> {
+            self.0.remove_kb(key, kb_key)
+        }
+
+        fn remove_result(
+            &self,
+            key: &ContextKey,
+            result_id: Option<usize>,
+        ) -> Result<Option<Vec<crate::models::Result>>, StorageError> {
+            self.0.remove_result(key, result_id)
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn correlation_id() {
+        let vts = only_success();
+        let harness = TestScanHarness::new(vts.clone());
+        let runner = harness
+            .runner()
+            .expect("runner")
+            .with_correlation_id("trace-1");
+        let results = runner.stream().collect::<Vec<_>>().await;
+        assert_eq!(results.len(), vts.len());
+        let oids = vts.map(|(_, vt)| vt.oid);
+        logs_assert(|lines: &[&str]| {
+            let running = lines
+                .iter()
+                .filter(|x| x.ends_with(": running"))
+                .collect::<Vec<_>>();
+            if running.len() != oids.len() {
+                return Err(format!("expected a running line per VT: {running:?}"));
+            }
+            for (line, oid) in running.iter().zip(&oids) {
+                let expected = format!(
+                    "scan{{scan_id=test correlation_id=trace-1}}:host{{host=localhost}}:vt{{oid={oid} "
+                );
+                if !line.contains(&expected) {
+                    return Err(format!("{line} does not contain {expected}"));
+                }
+            }
+            Ok(())
+        });
+    }
+
+    fn make_test_dispatcher(vts: &[(String, Nvt)]) -> DefaultDispatcher {
+        let dispatcher = prepare_vt_storage(vts);
+        dispatcher
+            .dispatch(
+                &ContextKey::Scan("sid".into(), Some("test.host".into())),
+                Field::KB(("key/exists", 1).into()),
+            )
+            .expect("store kb");
+        dispatcher
+    }
+
+    fn make_test_harness<I>(vts: I) -> TestScanHarness
+    where
+        I: IntoIterator<Item = (String, Nvt)>,
+    {
+        let harness = TestScanHarness::new(vts);
+        harness
+            .storage()
+            .dispatch(
+                &harness.host_key("localhost"),
+                Field::KB(("key/exists", 1).into()),
+            )
+            .expect("store kb");
+        harness
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn host_hooks() {
+        let hosts = ["first.host", "second.host"];
+        let harness = TestScanHarness::new(only_success())
+            .with_hosts(&hosts)
+            .map_storage(RecordingStorage::new);
+        let results = run_harness(&harness).await.expect("success run");
+        assert_eq!(results.len(), harness.scan().vts.len() * hosts.len());
+        let key = |host: &str| harness.host_key(host);
+        assert_eq!(
+            *harness.storage().1.lock().unwrap(),
+            [
+                ("started", key("first.host")),
+                ("finished", key("first.host")),
+                ("started", key("second.host")),
+                ("finished", key("second.host")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn max_hosts_and_vts() {
+        let harness = TestScanHarness::new(only_success())
+            .with_hosts(&["first.host", "second.host", "third.host"])
+            .map_storage(RecordingStorage::new);
+        let runner = harness
+            .runner()
+            .expect("runner")
+            .with_max_hosts(2)
+            .with_max_vts(2);
+        let results = runner
+            .stream()
+            .map(|x| x.expect("result"))
+            .map(|x| (x.target, x.oid))
+            .collect::<Vec<_>>()
+            .await;
+        let expected = |host: &str| {
+            [
+                (host.to_owned(), "0".to_owned()),
+                (host.to_owned(), "1".to_owned()),
+            ]
+        };
+        assert_eq!(
+            results,
+            [expected("first.host"), expected("second.host")].concat()
+        );
+        let key = |host: &str| harness.host_key(host);
+        assert_eq!(
+            *harness.storage().1.lock().unwrap(),
+            [
+                ("started", key("first.host")),
+                ("finished", key("first.host")),
+                ("started", key("second.host")),
+                ("finished", key("second.host")),
+            ]
+        );
+        assert_eq!(
+            harness
+                .storage()
+                .retrieve(&key("first.host"), Retrieve::KB(HOST_FINISHED_KEY.into()))
+                .unwrap()
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn stage_hooks() {
+        let hosts = ["first.host", "second.host"];
+        let harness = TestScanHarness::new(only_success())
+            .with_hosts(&hosts)
+            .map_storage(RecordingStorage::new);
+        let results = run_harness(&harness).await.expect("success run");
+        assert_eq!(results.len(), harness.scan().vts.len() * hosts.len());
+        // each VT depends on the previous one and is therefore in its own set
+        let expected = results
+            .iter()
+            .map(|x| x.as_ref().expect("result"))
+            .map(|x| (harness.host_key(&x.target), x.stage))
+            .collect::<Vec<_>>();
+        assert_eq!(expected.len(), 6);
+        assert_eq!(*harness.storage().2.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn fail_fast() {
+        let vts = [
+            GenerateScript::with_dependencies("0", &[]).generate(),
+            GenerateScript {
+                rc: 1,
+                ..GenerateScript::with_dependencies("1", &["0.nasl"])
+            }
+            .generate(),
+            GenerateScript::with_dependencies("2", &["1.nasl"]).generate(),
+        ];
+        let harness = TestScanHarness::new(vts)
+            .with_hosts(&["first.host", "second.host"])
+            .map_storage(RecordingStorage::new);
+        let runner = harness.runner().expect("runner").with_fail_fast(true);
+        let results = runner
+            .stream()
+            .map(|x| x.expect("result"))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].has_succeeded());
+        assert!(results[1].has_failed());
+        assert_eq!(results[1].oid, "1");
+        let key = |host: &str| harness.host_key(host);
+        assert_eq!(
+            *harness.storage().1.lock().unwrap(),
+            [
+                ("started", key("first.host")),
+                ("finished", key("first.host")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn deadline() {
+        let vts = only_success();
+        let harness = TestScanHarness::new(vts.clone())
+            .with_hosts(&["first.host", "second.host"])
+            .map_storage(RecordingStorage::new);
+        let runner = harness
+            .runner()
+            .expect("runner")
+            .with_deadline(std::time::Instant::now());
+        let progress = runner.progress();
+        let results = runner
+            .stream()
+            .map(|x| x.expect("result"))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results.len(), vts.len() * 2);
+        for (i, result) in results.iter().enumerate() {
+            assert!(matches!(
+                result.kind,
+                ScriptResultKind::DeadlineExceeded(x) if x == vts.len() * 2 - i
+            ));
+        }
+        let targets = results
+            .iter()
+            .map(|x| (x.target.as_str(), x.oid.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [
+                ("first.host", "0"),
+                ("first.host", "1"),
+                ("first.host", "2"),
+                ("second.host", "0"),
+                ("second.host", "1"),
+                ("second.host", "2"),
+            ]
+        );
+        assert!(harness.storage().1.lock().unwrap().is_empty());
+        let checkpoint = progress.checkpoint();
+        assert_eq!(
+            (checkpoint.host, checkpoint.stage, checkpoint.vt),
+            (0, 0, 0)
+        );
+
+        let report = harness
+            .runner()
+            .expect("runner")
+            .with_deadline(std::time::Instant::now())
+            .run_to_completion()
+            .await;
+        assert_eq!(report.total.not_run, vts.len() * 2);
+        assert_eq!(report.hosts["first.host"].not_run, vts.len());
+        assert_eq!(report.hosts["second.host"].not_run, vts.len());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn deadline_finishes_started_host() {
+        // the deadline passes while the first VT is running
+        let (code, vt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(r#"log_message(data: "Hello world.");"#, "usleep(200000);");
+        let vts = [
+            (code, vt),
+            GenerateScript::with_dependencies("1", &["0.nasl"]).generate(),
+        ];
+        let harness = TestScanHarness::new(vts).map_storage(RecordingStorage::new);
+        let runner = harness
+            .runner()
+            .expect("runner")
+            .with_deadline(std::time::Instant::now() + Duration::from_millis(50));
+        let results = runner
+            .stream()
+            .map(|x| x.expect("result"))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].has_succeeded());
+        assert!(matches!(
+            results[1].kind,
+            ScriptResultKind::DeadlineExceeded(1)
+        ));
+        let key = harness.host_key("localhost");
+        assert_eq!(
+            *harness.storage().1.lock().unwrap(),
+            [("started", key.clone()), ("finished", key)]
+        );
+    }
+
+    #[test]
+    fn plan() {
+        let harness = TestScanHarness::new(only_success());
+        let plan = harness
+            .storage()
+            .execution_plan::<WaveExecutionPlan>(harness.scan())
+            .expect("schedule")
+            .materialize_plan()
+            .expect("plan");
+        assert_eq!(
+            plan.iter().map(|x| x.oids.clone()).collect::<Vec<_>>(),
+            [["0"], ["1"], ["2"]]
+        );
+        assert!(plan.iter().all(|x| x.stage == plan[0].stage));
+        assert_eq!(harness.runner().expect("runner").plan(), plan);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn excluded_hosts() {
+        let mut harness = TestScanHarness::new(only_success())
+            .with_hosts(&["10.0.0.1-3"])
+            .map_storage(RecordingStorage::new);
+        harness.scan_mut().target.excluded_hosts = vec!["10.0.0.2".to_string()];
+        let results = harness
+            .runner()
+            .expect("runner")
+            .stream()
+            .map(|x| x.expect("result").target)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results.len(), harness.scan().vts.len() * 2);
+        assert!(!results.iter().any(|x| x == "10.0.0.2"));
+        let key = |host: &str| harness.host_key(host);
+        assert_eq!(
+            *harness.storage().1.lock().unwrap(),
+            [
+                ("started", key("10.0.0.1")),
+                ("finished", key("10.0.0.1")),
+                ("started", key("10.0.0.3")),
+                ("finished", key("10.0.0.3")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn skip_finished_hosts() {
+        let harness = TestScanHarness::new(only_success())
+            .with_hosts(&["first.host", "second.host"])
+            .map_storage(RecordingStorage::new);
+        let key = |host: &str| harness.host_key(host);
+        harness
+            .storage()
+            .dispatch(&key("first.host"), Field::KB((HOST_FINISHED_KEY, 1).into()))
+            .expect("marker");
+        let run = || async {
+            harness
+                .runner()
+                .expect("runner")
+                .with_skip_finished_hosts(true)
+                .stream()
+                .map(|x| x.expect("result").target)
+                .collect::<Vec<_>>()
+                .await
+        };
+        let results = run().await;
+        assert_eq!(results, vec!["second.host"; harness.scan().vts.len()]);
+        assert_eq!(
+            *harness.storage().1.lock().unwrap(),
+            [
+                ("started", key("second.host")),
+                ("finished", key("second.host")),
+            ]
+        );
+        // the second host is marked as finished by the first run
+        assert!(run().await.is_empty());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn interleaved_execution_order() {
+        let vts = [
+            GenerateScript::with_dependencies("0", &[]).generate(),
+            GenerateScript::with_dependencies("1", &["0.nasl"]).generate(),
+        ];
+        let harness = TestScanHarness::new(vts)
+            .with_hosts(&["first.host", "second.host"])
+            .map_storage(RecordingStorage::new);
+        let runner = || {
+            harness
+                .runner()
+                .expect("runner")
+                .with_execution_order(ExecutionOrder::Interleaved)
+        };
+        async fn executed<S: ScannerStack>(runner: ScanRunner<'_, S>) -> Vec<(String, String)> {
+            results(runner)
+                .await
+                .into_iter()
+                .map(|x| (x.target, x.oid))
+                .collect()
+        }
+        let expected = [
+            ("first.host".to_string(), "0".to_string()),
+            ("second.host".to_string(), "0".to_string()),
+            ("first.host".to_string(), "1".to_string()),
+            ("second.host".to_string(), "1".to_string()),
+        ];
+        assert_eq!(executed(runner()).await, expected);
+        let key = |host: &str| harness.host_key(host);
+        assert_eq!(
+            *harness.storage().1.lock().unwrap(),
+            [
+                ("started", key("first.host")),
+                ("started", key("second.host")),
+                ("finished", key("first.host")),
+                ("finished", key("second.host")),
+            ]
+        );
+
+        let first = runner();
+        let progress = first.progress();
+        let first = first.stream().take(1).collect::<Vec<_>>().await;
+        assert_eq!(first.len(), 1);
+        let checkpoint = progress.checkpoint();
+        assert_eq!(
+            (checkpoint.host, checkpoint.stage, checkpoint.vt),
+            (1, 0, 0)
+        );
+        let resumed = runner().resume_from(checkpoint).expect("same schedule");
+        assert_eq!(executed(resumed).await, expected[1..]);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn persist_results() {
+        let vts = only_success();
+        let harness = TestScanHarness::new(vts.clone());
+        let scan_key = ContextKey::Scan(harness.scan().scan_id.clone(), None);
+        let persisted = |persist| {
+            let runner = harness
+                .runner()
+                .expect("runner")
+                .with_persisted_results(persist);
+            let (storage, scan_key, count) = (harness.storage(), &scan_key, vts.len());
+            async move {
+                let returned = runner.stream().collect::<Vec<_>>().await;
+                assert_eq!(returned.len(), count);
+                storage
+                    .results(scan_key)
+                    .expect("results")
+                    .filter(|x| x.message.as_deref() == Some("return_code:0"))
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert!(persisted(false).await.is_empty());
+        let results = persisted(true).await;
+        assert_eq!(results.len(), vts.len());
+        // the ids follow the messages the scripts stored in the first run
+        for (i, (result, (_, vt))) in results.iter().zip(vts.iter()).enumerate() {
+            assert_eq!(result.id, results[0].id + i);
+            assert_eq!(result.r_type, ResultType::Log);
+            assert_eq!(result.oid.as_ref(), Some(&vt.oid));
+            assert_eq!(result.hostname.as_deref(), Some("localhost"));
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn persist_results_resumed() {
+        let vts = only_success();
+        let harness = TestScanHarness::new(vts.clone());
+        let runner = || {
+            harness
+                .runner()
+                .expect("runner")
+                .with_persisted_results(true)
+        };
+        let first = runner();
+        let progress = first.progress();
+        assert_eq!(first.stream().take(2).count().await, 2);
+        let resumed = runner()
+            .resume_from(progress.checkpoint())
+            .expect("same schedule");
+        assert_eq!(resumed.stream().count().await, vts.len() - 2);
+        let scan_key = ContextKey::Scan(harness.scan().scan_id.clone(), None);
+        let ids = harness
+            .storage()
+            .results(&scan_key)
+            .expect("results")
+            .filter(|x| x.message.as_deref() == Some("return_code:0"))
+            .map(|x| x.id)
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(ids.len(), vts.len());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn script_messages() {
+        let (code, vt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(
+            r#"log_message(data: "Hello world.");"#,
+            r#"security_message(data: "vulnerable"); log_message(data: "info");"#,
+        );
+        let harness = TestScanHarness::new([(code, vt)]);
+        let results = run_harness(&harness).await.expect("success run");
+        assert_eq!(results.len(), 1);
+        let result = results[0].as_ref().expect("result");
+        assert!(result.has_succeeded());
+        assert!(result.has_message(ResultType::Alarm));
+        let messages = result
+            .messages
+            .iter()
+            .map(|x| (x.r_type.clone(), x.message.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                (ResultType::Alarm, Some("vulnerable")),
+                (ResultType::Log, Some("info"))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn max_results() {
+        let (code, vt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(
+            r#"log_message(data: "Hello world.");"#,
+            r#"while (TRUE) security_message(data: "flood");"#,
+        );
+        let harness = TestScanHarness::new([(code, vt)]);
+        let runner = harness.runner().expect("runner").with_max_results(3);
+        let results = runner.stream().collect::<Vec<_>>().await;
+        assert_eq!(results.len(), 1);
+        let result = results[0].as_ref().expect("result");
+        assert!(matches!(
+            result.kind,
+            ScriptResultKind::ResultLimitExceeded(3)
+        ));
+        assert_eq!(result.messages.len(), 3);
+        let stored = harness
+            .storage()
+            .results(&ContextKey::Scan(harness.scan().scan_id.clone(), None))
+            .expect("results")
+            .count();
+        assert_eq!(stored, 3);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn max_results_keeps_other_errors() {
+        let (code, vt) = GenerateScript::with_dependencies("0", &[]).generate();
+        // each value of the KB item forks the statement, the forks end with the limit and with
+        // another error
+        let code = code.replace(
+            r#"log_message(data: "Hello world.");"#,
+            r#"{
+  fork = get_kb_item("fork");
+  if (fork == 1) exit(0);
+  if (fork == 2) while (TRUE) security_message(data: "flood");
+  substr();
+}"#,
+        );
+        let harness = TestScanHarness::new([(code, vt)]);
+        for fork in 1..=3 {
+            harness
+                .storage()
+                .dispatch(
+                    &harness.host_key("localhost"),
+                    Field::KB(("fork", fork).into()),
+                )
+                .expect("store kb");
+        }
+        let runner = harness.runner().expect("runner").with_max_results(3);
+        let kinds = results(runner)
+            .await
+            .into_iter()
+            .map(|x| x.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds.len(), 3, "{kinds:?}");
+        assert!(matches!(kinds[1], ScriptResultKind::ResultLimitExceeded(3)));
+        assert!(matches!(kinds[2], ScriptResultKind::Error(_)));
+    }
+
+    struct Panicking;
+
+    #[nasl_function]
+    fn panic_now() {
+        panic!("not implemented");
+    }
+
+    function_set! {
+        Panicking,
+        (panic_now)
+    }
+
+    #[tokio::test]
+    async fn panicking_script() {
+        let (code, vt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(r#"log_message(data: "Hello world.");"#, "panic_now();");
+        let harness = TestScanHarness::new([
+            (code, vt),
+            GenerateScript::with_dependencies("1", &["0.nasl"]).generate(),
+        ])
+        .with_function_set(Panicking);
+        let results = run_harness(&harness)
+            .await
+            .expect("success run")
+            .into_iter()
+            .map(|x| x.expect("result"))
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            &results[0].kind,
+            ScriptResultKind::Panic(x) if x == "not implemented"
+        ));
+        assert!(results[1].has_succeeded());
+    }
+
+    #[tokio::test]
+    async fn undefined_function() {
+        let (code, vt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(r#"log_message(data: "Hello world.");"#, "does_not_exist();");
+        let harness = TestScanHarness::new([
+            (code.clone(), vt),
+            GenerateScript::with_dependencies("1", &["0.nasl"]).generate(),
+        ]);
+        let results = run_harness(&harness).await.expect("success run");
+        let results = results
+            .into_iter()
+            .map(|x| x.expect("result"))
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        match &results[0].kind {
+            ScriptResultKind::Error(e) => {
+                assert!(matches!(
+                    &e.kind,
+                    InterpretErrorKind::UndefinedFunction(x) if x == "does_not_exist"
+                ));
+                let line = code.lines().nth(e.line() - 1).unwrap_or_default();
+                assert!(line.contains("does_not_exist"), "{e}");
+                assert_eq!(e.kind.to_string(), "Undefined function: does_not_exist");
+            }
+            kind => panic!("expected an error, got {kind:?}"),
+        }
+        assert!(results[1].has_succeeded());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn parameters() {
+        let (code, mut nvt) = GenerateScript::with_dependencies("0", &[]).generate();
+        nvt.preferences = vec![
+            NvtPreference {
+                id: Some(1),
+                class: PreferenceType::Integer,
+                name: "count".to_owned(),
+                default: "1".to_owned(),
+            },
+            NvtPreference {
+                id: Some(2),
+                class: PreferenceType::Radio,
+                name: "mode".to_owned(),
+                default: "fast;thorough".to_owned(),
+            },
+        ];
+        let mut harness = TestScanHarness::new([(code, nvt)]);
+        let parameter = |id, value: &str| Parameter {
+            id,
+            value: value.to_owned(),
+        };
+        let cases = [
+            (vec![parameter(1, "99999999999999999999")], false),
+            (vec![parameter(2, "slow")], false),
+            (vec![parameter(1, "42"), parameter(2, "thorough")], true),
+        ];
+        for (parameters, valid) in cases {
+            harness.scan_mut().vts[0].parameters = parameters;
+            let results = run_harness(&harness).await.expect("success run");
+            assert_eq!(results.len(), 1);
+            match valid {
+                true => assert!(results[0].is_ok(), "{results:?}"),
+                false => assert!(
+                    matches!(results[0], Err(ExecuteError::InvalidParameter(_, _))),
+                    "{results:?}"
+                ),
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn list_parameters() {
+        let (code, mut nvt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(
+            r#"log_message(data: "Hello world.");"#,
+            r#"ports = script_get_preference("ports");
+if (typeof(script_get_preference(id: 2)) != "string") exit(1);
+exit(max_index(ports) * 1000 + ports[2]);"#,
+        );
+        nvt.preferences = vec![
+            NvtPreference {
+                id: Some(1),
+                class: PreferenceType::Integer,
+                name: "ports".to_owned(),
+                default: "22".to_owned(),
+            },
+            // entries are kept as string even when they look like a list
+            NvtPreference {
+                id: Some(2),
+                class: PreferenceType::Entry,
+                name: "note".to_owned(),
+                default: "1, 2, 3".to_owned(),
+            },
+        ];
+        let mut harness = TestScanHarness::new([(code, nvt)]);
+        for value in ["22, 80, 443", "[22, 80, 443]"] {
+            harness.scan_mut().vts[0].parameters = vec![Parameter {
+                id: 1,
+                value: value.to_owned(),
+            }];
+            let results = run_harness(&harness).await.expect("success run");
+            assert_eq!(results.len(), 1);
+            let result = results[0].as_ref().expect("result");
+            assert!(
+                matches!(result.kind, ScriptResultKind::ReturnCode(3443)),
+                "{value}: {result:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn execute_error_variants() {
+        let vts = only_success();
+        let mut harness = TestScanHarness::new(vts.clone());
+        let schedule = harness
+            .storage()
+            .execution_plan::<WaveExecutionPlan>(harness.scan())
+            .expect("schedule");
+        let results = ScanRunner::<TestScannerStack>::new(
+            harness.storage(),
+            // does not know any script
+            &InMemoryLoader::default(),
+            &nasl_std_functions(),
+            schedule,
+            harness.scan(),
+        )
+        .expect("runner")
+        .stream()
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(results.len(), vts.len());
+        for result in results {
+            assert!(
+                matches!(result, Err(ExecuteError::Load(LoadError::NotFound(_)))),
+                "{result:?}"
+            );
+        }
+
+        harness.scan_mut().vts[0].parameters = vec![Parameter {
+            id: 1,
+            value: "yes".to_string(),
+        }];
+        let results = run_harness(&harness).await.expect("success run");
+        assert!(matches!(results[0], Err(ExecuteError::Parameter(_))));
+        assert!(results[1..].iter().all(|x| x.is_ok()));
+
+        let missing =
+            TestScanHarness::new([
+                GenerateScript::with_dependencies("0", &["missing.nasl"]).generate()
+            ]);
+        let result = run_harness(&missing).await;
+        assert!(
+            matches!(result, Err(ExecuteError::Scheduling(_))),
+            "{result:?}"
+        );
+
+        let storage = ExecuteError::from(VTError::DB(StorageError::RetryExhausted));
+        assert!(matches!(storage, ExecuteError::Storage(_)));
+        let load = ExecuteError::from(VTError::NotFound(LoadError::NotFound("a".into())));
+        assert!(matches!(load, ExecuteError::Load(_)));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn exclude_keys() {
+        let only_success = [
+            GenerateScript::with_excluded_keys("0", &["key/not"]).generate(),
+            GenerateScript::with_excluded_keys("1", &["key/not"]).generate(),
+            GenerateScript::with_excluded_keys("2", &["key/exists"]).generate(),
+        ];
+        let dispatcher = make_test_dispatcher(&only_success);
+        let (success, failure) = get_all_results(&only_success, dispatcher).await;
+        assert_eq!(success.len(), 2);
+        assert_eq!(failure.len(), 1);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn global_excluded_keys() {
+        let only_success = [
+            GenerateScript::with_excluded_keys("0", &["key/global"]).generate(),
+            GenerateScript::with_excluded_keys("1", &["key/not"]).generate(),
+            GenerateScript::with_excluded_keys("2", &["key/global"]).generate(),
+        ];
+        let hosts = ["a.host", "b.host", "c.host"];
+        let harness = TestScanHarness::new(only_success.clone()).with_hosts(&hosts);
+        let results = results(
+            harness
+                .runner()
+                .expect("runner")
+                .with_excluded_keys(vec!["key/global".to_string()]),
+        )
+        .await;
+        assert_eq!(results.len(), only_success.len() * hosts.len());
+        let (excluded, rest): (Vec<_>, Vec<_>) = results
+            .into_iter()
+            .partition(|x| x.oid != only_success[1].1.oid);
+        assert_eq!(excluded.len(), 2 * hosts.len());
+        for host in hosts {
+            assert_eq!(excluded.iter().filter(|x| x.target == host).count(), 2);
+        }
+        assert!(excluded.iter().all(
+            |x| matches!(&x.kind, ScriptResultKind::ContainsExcludedKey(k) if k == "key/global")
+        ));
+        assert!(rest.iter().all(|x| x.has_succeeded()));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn required_keys() {
+        let only_success = [
+            GenerateScript::with_required_keys("0", &["key/not"]).generate(),
+            GenerateScript::with_required_keys("1", &["key/exists"]).generate(),
+        ];
+        let dispatcher = make_test_dispatcher(&only_success);
+        let (success, failure) = get_all_results(&only_success, dispatcher).await;
+        assert_eq!(success.len(), 1);
+        assert_eq!(failure.len(), 1);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn dry_run() {
+        let vts = [
+            GenerateScript::with_required_keys("0", &["key/not"]).generate(),
+            GenerateScript::with_required_keys("1", &["key/exists"]).generate(),
+        ];
+        let harness = make_test_harness(vts);
+        let runner = harness.runner().expect("runner");
+
+        let mut planned = runner
+            .dry_run()
+            .into_iter()
+            .map(|x| (x.oid.clone(), x.would_run()))
+            .collect::<Vec<_>>();
+        let mut executed = runner
+            .stream()
+            .filter_map(|x| async { x.ok() })
+            .map(|x| (x.oid.clone(), x.has_succeeded()))
+            .collect::<Vec<_>>()
+            .await;
+        planned.sort();
+        executed.sort();
+        assert_eq!(planned, vec![("0".into(), false), ("1".into(), true)]);
+        assert_eq!(planned, executed);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn deprecated() {
+        let vts = [
+            GenerateScript {
+                id: "0".into(),
+                deprecated: true,
+                ..Default::default()
+            }
+            .generate(),
+            GenerateScript::with_dependencies("1", &[]).generate(),
+        ];
+        assert!(vts[0].1.is_deprecated());
+        let harness = TestScanHarness::new(vts);
+        let results = results(harness.runner().expect("runner")).await;
+        assert_eq!(results.len(), 2);
+        let (deprecated, rest): (Vec<_>, Vec<_>) = results.into_iter().partition(|x| x.oid == "0");
+        assert!(matches!(deprecated[0].kind, ScriptResultKind::Deprecated));
+        assert!(deprecated[0].has_not_run());
+        assert!(rest[0].has_succeeded());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn script_timeout() {
+        let slow = |(code, nvt): (String, Nvt)| {
+            let code = code.replace(
+                r#"log_message(data: "Hello world.");"#,
+                "usleep(500000);\nusleep(500000);\nusleep(500000);",
+            );
+            (code, nvt)
+        };
+        let vts = [
+            slow(
+                GenerateScript {
+                    id: "0".into(),
+                    timeout: 1,
+                    ..Default::default()
+                }
+                .generate(),
+            ),
+            slow(GenerateScript::with_dependencies("1", &[]).generate()),
+        ];
+        assert_eq!(vts[0].1.timeout(), Some(Duration::from_secs(1)));
+        assert_eq!(vts[1].1.timeout(), None);
+        let harness = TestScanHarness::new(vts);
+        let results = results(harness.runner().expect("runner")).await;
+        assert_eq!(results.len(), 2);
+        let (timed_out, rest): (Vec<_>, Vec<_>) = results.into_iter().partition(|x| x.oid == "0");
+        assert!(matches!(
+            timed_out[0].kind,
+            ScriptResultKind::TimedOut(x) if x == Duration::from_secs(1)
+        ));
+        assert!(rest[0].has_succeeded());
+    }
+
+    #[derive(Default)]
+    struct RecordingMetrics(std::sync::Mutex<Vec<(VtOutcome, Duration)>>);
+
+    impl ScanMetrics for RecordingMetrics {
+        fn vt_finished(&self, outcome: VtOutcome, duration: Duration) {
+            self.0.lock().unwrap().push((outcome, duration));
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn metrics() {
+        let (slow_code, slow) = GenerateScript {
+            id: "3".into(),
+            timeout: 1,
+            ..Default::default()
+        }
+        .generate();
+        let vts = [
+            GenerateScript::with_dependencies("0", &[]).generate(),
+            GenerateScript {
+                id: "1".into(),
+                rc: 1,
+                ..Default::default()
+            }
+            .generate(),
+            GenerateScript::with_required_keys("2", &["key/not"]).generate(),
+            (
+                slow_code.replace(
+                    r#"log_message(data: "Hello world.");"#,
+                    "usleep(500000);\nusleep(500000);\nusleep(500000);",
+                ),
+                slow,
+            ),
+        ];
+        let harness = TestScanHarness::new(vts.clone());
+        let metrics = RecordingMetrics::default();
+        let runner = harness.runner().expect("runner").with_metrics(&metrics);
+        let results = runner.stream().collect::<Vec<_>>().await;
+        assert_eq!(results.len(), vts.len());
+
+        let recorded = metrics.0.into_inner().unwrap();
+        assert_eq!(recorded.len(), vts.len());
+        let count = |outcome| recorded.iter().filter(|(x, _)| *x == outcome).count();
+        assert_eq!(count(VtOutcome::Succeeded), 1);
+        assert_eq!(count(VtOutcome::Failed), 1);
+        assert_eq!(count(VtOutcome::Skipped), 1);
+        assert_eq!(count(VtOutcome::TimedOut), 1);
+        let (_, timed_out) = recorded
+            .iter()
+            .find(|(x, _)| *x == VtOutcome::TimedOut)
+            .unwrap();
+        assert!(*timed_out >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn mandatory_keys() {
+        let only_success = [
+            GenerateScript::with_mandatory_keys("0", &["key/not"]).generate(),
+            GenerateScript::with_mandatory_keys("1", &["key/exists"]).generate(),
+        ];
+        let dispatcher = make_test_dispatcher(&only_success);
+        let (success, failure) = get_all_results(&only_success, dispatcher).await;
+        assert_eq!(success.len(), 1);
+        assert_eq!(failure.len(), 1);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn kb_key_of_ipv6_target() {
+        let host = "fe80::1";
+        let harness =
+            TestScanHarness::new([
+                GenerateScript::with_required_keys("0", &["key/exists"]).generate()
+            ])
+            .with_hosts(&[host]);
+        let target = ScanTarget::new(harness.scan().scan_id.clone(), 0, host.into());
+        let key = ContextKey::from(&target);
+        assert_eq!(key, harness.host_key(host));
+
+        harness
+            .storage()
+            .dispatch(&key, Field::KB(("key/exists", 1).into()))
+            .expect("store kb");
+        let results = results(harness.runner().expect("runner")).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].target, host);
+        assert!(results[0].has_succeeded());
+    }
+
+    #[test]
+    fn kb_host_normalizes_ipv6() {
+        let target = |host: &str| ScanTarget::new("sid".into(), 0, host.into());
+        assert_eq!(target("fe80::1").kb_host(), "fe80::1");
+        assert_eq!(target("[fe80::1]").kb_host(), "fe80::1");
+        assert_eq!(target("fe80:0:0:0:0:0:0:1").kb_host(), "fe80::1");
+        assert_eq!(target("127.0.0.1").kb_host(), "127.0.0.1");
+        assert_eq!(target("test.host").kb_host(), "test.host");
+        assert_eq!(target(" test.host").kb_host(), "test.host");
+        assert_eq!(
+            ContextKey::from(&target("[fe80::1]")),
+            ContextKey::from(&target("fe80:0::1"))
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn required_ports_on_ipv6_target() {
+        let harness = TestScanHarness::new([
+            GenerateScript::with_required_ports("0", &[(Protocol::TCP, "20")]).generate(),
+            GenerateScript::with_required_ports("1", &[(Protocol::TCP, "22")]).generate(),
+        ])
+        .with_hosts(&["[fe80::1]"]);
+        harness
+            .storage()
+            .dispatch(
+                &harness.host_key("fe80::1"),
+                Field::KB((&generate_port_kb_key(Protocol::TCP, "20"), 1).into()),
+            )
+            .expect("store kb");
+        let results = results(harness.runner().expect("runner")).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.iter().filter(|x| x.has_succeeded()).count(), 1);
+        assert_eq!(results.iter().filter(|x| x.has_not_run()).count(), 1);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn forked_exit() {
+        let (code, nvt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(
+            r#"log_message(data: "Hello world.");"#,
+            r#"get_kb_item("port");"#,
+        );
+        let harness = TestScanHarness::new([(code, nvt)]);
+        [1, 2].into_iter().for_each(|port| {
+            harness
+                .storage()
+                .dispatch(
+                    &harness.host_key("localhost"),
+                    Field::KB(("port", port).into()),
+                )
+                .expect("store kb");
+        });
+        let results = results(harness.runner().expect("runner")).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|x| x.has_succeeded()));
+        assert!(!results[0].is_forked());
+        assert!(results[1].is_forked());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn error_position() {
+        let (code, nvt) = GenerateScript::with_dependencies("0", &[]).generate();
+        let code = code.replace(
+            r#"log_message(data: "Hello world.");"#,
+            "a = 1;\n  b = a + not_defined(a);\nlog_message(data: b);",
+        );
+        let line = code
+            .lines()
+            .position(|x| x.contains("not_defined"))
+            .unwrap()
+            + 1;
+        let harness = TestScanHarness::new([(code, nvt)]);
+        let results = results(harness.runner().expect("runner")).await;
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0].kind, ScriptResultKind::Error(_)));
+        assert_eq!(results[0].error_position(), Some((line, 11)));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn storage_error_on_check_keys() {
+        let vts = [
+            GenerateScript::with_required_keys("0", &["key/exists"]).generate(),
+            GenerateScript::with_mandatory_keys("1", &["key/exists"]).generate(),
+            GenerateScript::with_excluded_keys("2", &["key/exists"]).generate(),
+            GenerateScript::with_required_ports("3", &[(Protocol::TCP, "20")]).generate(),
+        ];
+        let harness = make_test_harness(vts).map_storage(FailingKbStorage);
+        let results = results(harness.runner().expect("runner")).await;
+        assert_eq!(results.len(), 4);
+        assert!(results
+            .iter()
+            .all(|x| matches!(x.kind, ScriptResultKind::StorageError(_))));
+    }
+
+    /// Runs scenarios of the in-memory storage against the SQLite storage
+    #[cfg(feature = "sqlite")]
+    mod sqlite {
+        use super::*;
+        use crate::storage::sqlite::SqliteDispatcher;
+
+        /// Moves the KB items of localhost into a SQLite storage using the given one for the VTs
+        fn sqlite(harness: TestScanHarness) -> TestScanHarness<SqliteDispatcher> {
+            let key = harness.host_key("localhost");
+            harness.map_storage(|dispatcher| {
+                let kbs = dispatcher.remove_kb(&key, None).expect("remove kbs");
+                let connection = rusqlite::Connection::open_in_memory().expect("connection");
+                let storage = SqliteDispatcher::new(connection, dispatcher).expect("sqlite");
+                for kb in kbs.unwrap_or_default() {
+                    storage.dispatch(&key, Field::KB(kb)).expect("store kb");
+                }
+                storage
+            })
+        }
+
+        #[tokio::test]
+        #[tracing_test::traced_test]
+        async fn required_keys() {
+            let vts = [
+                GenerateScript::with_required_keys("0", &["key/not"]).generate(),
+                GenerateScript::with_required_keys("1", &["key/exists"]).generate(),
+                GenerateScript::with_mandatory_keys("2", &["key/not"]).generate(),
+                GenerateScript::with_mandatory_keys("3", &["key/exists"]).generate(),
+                GenerateScript::with_excluded_keys("4", &["key/not"]).generate(),
+                GenerateScript::with_excluded_keys("5", &["key/exists"]).generate(),
+            ];
+            let harness = sqlite(make_test_harness(vts));
+            let (success, failure) = get_all_harness_results(&harness).await;
+            let oids = |x: Vec<ScriptResult>| {
+                let mut oids = x.into_iter().map(|x| x.oid).collect::<Vec<_>>();
+                oids.sort();
+                oids
+            };
+            assert_eq!(oids(success), ["1", "3", "4"]);
+            assert_eq!(oids(failure), ["0", "2", "5"]);
+        }
+
+        #[tokio::test]
+        #[tracing_test::traced_test]
+        async fn required_ports() {
+            let (success, failure) = get_all_harness_results(&sqlite(required_ports_setup())).await;
+            assert_eq!(success.len(), 1);
+            assert_eq!(failure.len(), 4);
+        }
+
+        #[tokio::test]
+        #[tracing_test::traced_test]
+        async fn kb_of_dependency() {
+            let (code, nvt) = GenerateScript::with_dependencies("0", &[]).generate();
+            let code = code.replace(
+                r#"log_message(data: "Hello world.");"#,
+                r#"set_kb_item(name: "key/set", value: "yes");"#,
+            );
+            let vts = [
+                (code, nvt),
+                GenerateScript {
+                    id: "1".into(),
+                    dependencies: vec!["0.nasl".into()],
+                    required_keys: vec!["key/set".into()],
+                    ..Default::default()
+                }
+                .generate(),
+            ];
+            let harness = sqlite(TestScanHarness::new(vts));
+            let (success, failure) = get_all_harness_results(&harness).await;
+            assert_eq!(success.len(), 2);
+            assert!(failure.is_empty());
+            let storage = harness.storage();
+            let key = harness.host_key("localhost");
+            let kbs = storage.dump_kb(&key).expect("kbs");
+            assert!(kbs.iter().any(|x| x.key == "key/set"));
+            assert!(kbs.iter().all(|x| x.key != HOST_FINISHED_KEY));
+            assert_eq!(
+                storage
+                    .retrieve(&key, Retrieve::KB(HOST_FINISHED_KEY.into()))
+                    .expect("marker")
+                    .count(),
+                1
+            );
+
+            storage
+                .scan_finished(&ContextKey::Scan(harness.scan().scan_id.clone(), None))
+                .expect("finish scan");
+            assert!(storage.dump_kb(&key).expect("kbs").is_empty());
+        }
     }
 }
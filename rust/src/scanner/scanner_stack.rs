@@ -7,10 +7,17 @@ use crate::storage::{DefaultDispatcher, Storage};
 
 use crate::scheduling::{ConcurrentVT, ConcurrentVTResult, VTError};
 
+use super::scan_runner::PlannedStage;
+
 pub trait Schedule: Iterator<Item = ConcurrentVTResult> + Sized {
     fn cache(self) -> Result<Vec<ConcurrentVT>, VTError> {
         self.collect()
     }
+
+    /// Evaluates the whole schedule and returns the planned stages with the VTs of each stage
+    fn materialize_plan(self) -> Result<Vec<PlannedStage>, VTError> {
+        self.map(|x| x.map(|x| PlannedStage::from(&x))).collect()
+    }
 }
 
 impl<T> Schedule for T where T: Iterator<Item = ConcurrentVTResult> {}
@@ -0,0 +1,196 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+//! Provides an in memory setup to run scans without a feed on disk.
+
+use std::collections::HashMap;
+
+use crate::models::{Scan, Target, VT};
+use crate::nasl::nasl_std_functions;
+use crate::nasl::syntax::{LoadError, Loader};
+use crate::nasl::utils::{Executor, IntoFunctionSet};
+use crate::scheduling::{ExecutionPlaner, VTError, WaveExecutionPlan};
+use crate::storage::item::{NVTField, Nvt};
+use crate::storage::{ContextKey, DefaultDispatcher, Dispatcher, Field, Storage};
+
+use super::{ScanRunner, Scanner};
+
+/// Loads the scripts of a [`TestScanHarness`] by their filename.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryLoader {
+    scripts: HashMap<String, String>,
+}
+
+impl Loader for InMemoryLoader {
+    fn load(&self, key: &str) -> Result<String, LoadError> {
+        self.scripts
+            .get(key)
+            .cloned()
+            .ok_or_else(|| LoadError::NotFound(key.to_owned()))
+    }
+
+    fn root_path(&self) -> Result<String, LoadError> {
+        Ok(String::default())
+    }
+}
+
+/// The scanner stack used by [`TestScanHarness`].
+pub type TestScannerStack<S = DefaultDispatcher> = (S, InMemoryLoader);
+
+/// Bundles storage, loader and executor to run scans over a given set of scripts.
+///
+/// Each script is given as its source code and the [`Nvt`] describing it. The
+/// Nvts are stored in a [`DefaultDispatcher`] and the source is loaded by the
+/// filename of the Nvt. The scan contains all scripts and targets `localhost`
+/// unless other hosts are set via [`TestScanHarness::with_hosts`]. The storage can
+/// be wrapped via [`TestScanHarness::map_storage`], e.g. to record the calls of it.
+///
+/// # Examples
+/// ```
+/// use scannerlib::scanner::TestScanHarness;
+/// use scannerlib::storage::item::Nvt;
+///
+/// let script = |id: &str, dependencies: &[&str]| {
+///     let nvt = Nvt {
+///         oid: id.to_string(),
+///         filename: format!("{id}.nasl"),
+///         dependencies: dependencies.iter().map(|x| x.to_string()).collect(),
+///         ..Default::default()
+///     };
+///     ("exit(0);".to_string(), nvt)
+/// };
+/// let harness = TestScanHarness::new([
+///     script("0", &[]),
+///     script("1", &["0.nasl"]),
+///     script("2", &["1.nasl"]),
+/// ]);
+/// let report = tokio::runtime::Runtime::new()
+///     .unwrap()
+///     .block_on(harness.runner().unwrap().run_to_completion());
+/// assert_eq!(report.total.succeeded, 3);
+/// assert!(report.errors.is_empty());
+/// ```
+pub struct TestScanHarness<S = DefaultDispatcher> {
+    storage: S,
+    loader: InMemoryLoader,
+    executor: Executor,
+    scan: Scan,
+}
+
+impl TestScanHarness {
+    /// Creates a new harness containing the given scripts.
+    pub fn new<I>(scripts: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Nvt)>,
+    {
+        let storage = DefaultDispatcher::new();
+        let mut loader = InMemoryLoader::default();
+        let mut vts = vec![];
+        for (code, nvt) in scripts {
+            vts.push(VT {
+                oid: nvt.oid.clone(),
+                parameters: vec![],
+            });
+            loader.scripts.insert(nvt.filename.clone(), code);
+            storage
+                .dispatch(
+                    &ContextKey::FileName(nvt.filename.clone()),
+                    Field::NVT(NVTField::Nvt(nvt)),
+                )
+                .expect("the in memory storage must accept Nvts");
+        }
+        let scan = Scan {
+            scan_id: "test".to_string(),
+            target: Target {
+                hosts: vec!["localhost".to_string()],
+                ..Default::default()
+            },
+            scan_preferences: vec![],
+            vts,
+        };
+        Self {
+            storage,
+            loader,
+            executor: nasl_std_functions(),
+            scan,
+        }
+    }
+}
+
+impl<S> TestScanHarness<S> {
+    /// Sets the hosts to be scanned.
+    pub fn with_hosts(mut self, hosts: &[&str]) -> Self {
+        self.scan.target.hosts = hosts.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    /// Adds the given functions to the executor running the scripts.
+    pub fn with_function_set<F>(mut self, set: F) -> Self
+    where
+        F: IntoFunctionSet + 'static,
+        <F as IntoFunctionSet>::State: Send + Sync,
+    {
+        self.executor.add_set(set);
+        self
+    }
+
+    /// Replaces the storage by the result of `f`, which is given the current storage
+    /// containing the Nvts.
+    pub fn map_storage<T>(self, f: impl FnOnce(S) -> T) -> TestScanHarness<T> {
+        TestScanHarness {
+            storage: f(self.storage),
+            loader: self.loader,
+            executor: self.executor,
+            scan: self.scan,
+        }
+    }
+
+    /// Returns the storage containing the Nvts as well as the KB items of the scan.
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the scan run by [`TestScanHarness::runner`].
+    pub fn scan(&self) -> &Scan {
+        &self.scan
+    }
+
+    /// Returns the scan to adjust e.g. the parameters of the VTs.
+    pub fn scan_mut(&mut self) -> &mut Scan {
+        &mut self.scan
+    }
+
+    /// Returns the key the KB items of the given host are stored under.
+    pub fn host_key(&self, host: &str) -> ContextKey {
+        ContextKey::Scan(self.scan.scan_id.clone(), Some(host.to_string()))
+    }
+}
+
+impl<S> TestScanHarness<S>
+where
+    S: Storage + Send + 'static,
+{
+    /// Creates a runner for the scan based on a wave execution plan.
+    pub fn runner(&self) -> Result<ScanRunner<'_, TestScannerStack<S>>, VTError> {
+        let schedule = self
+            .storage
+            .execution_plan::<WaveExecutionPlan>(&self.scan)?;
+        ScanRunner::new(
+            &self.storage,
+            &self.loader,
+            &self.executor,
+            schedule,
+            &self.scan,
+        )
+    }
+
+    /// Returns a [`Scanner`] over the storage, loader and executor as well as the scan to be
+    /// started on it.
+    pub fn into_scanner(self) -> (Scanner<TestScannerStack<S>>, Scan) {
+        (
+            Scanner::new(self.storage, self.loader, self.executor),
+            self.scan,
+        )
+    }
+}
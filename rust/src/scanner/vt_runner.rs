@@ -2,37 +2,165 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
-use crate::models::{Host, Parameter, Protocol, ScanId};
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::models::{self, Parameter, Protocol};
 use crate::nasl::syntax::{Loader, NaslValue};
 use crate::nasl::utils::context::Target;
 use crate::nasl::utils::{Executor, Register};
 use crate::scheduling::Stage;
-use crate::storage::item::Nvt;
+use crate::storage::item::{integer_list, Nvt, NvtPreference, PreferenceType};
 use crate::storage::{types::Primitive, Retriever, Storage};
-use crate::storage::{ContextKey, Field, Retrieve, StorageError};
-use futures::StreamExt;
+use crate::storage::{ContextKey, Dispatcher, Field, Retrieve, StorageError};
+use futures::FutureExt;
 use tracing::{error_span, trace, warn};
 
-use crate::nasl::interpreter::CodeInterpreter;
+use crate::nasl::interpreter::{CodeInterpreter, InterpretError, InterpretErrorKind};
 use crate::nasl::prelude::*;
 
+use super::scan_runner::ScanTarget;
 use super::ExecuteError;
 use super::{
     error::{ScriptResult, ScriptResultKind},
     ScannerStack,
 };
 
+/// Defines whether a filtered UDP port satisfies the required UDP ports of a VT.
+///
+/// A UDP port is filtered when its KB item `Ports/udp/<port>` contains `filtered`, as a missing
+/// response does not tell whether the port is open or not.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilteredPorts {
+    /// A filtered port is handled as open port
+    #[default]
+    Accept,
+    /// A filtered port prevents the VT from running with `PortFiltered`
+    Reject,
+}
+
+/// The state of a port as stored in the KB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PortState {
+    Open,
+    Closed,
+    Filtered,
+}
+
+impl From<&Primitive> for PortState {
+    fn from(value: &Primitive) -> Self {
+        match value.as_str().map(|x| x.trim()) {
+            Some(x) if x.eq_ignore_ascii_case("filtered") => PortState::Filtered,
+            _ if value.as_bool() => PortState::Open,
+            _ => PortState::Closed,
+        }
+    }
+}
+
+/// Forwards the fields of a script run and keeps a copy of the results sent by it
+///
+/// When a limit is set, results exceeding it are rejected, which aborts the script.
+struct MessageRecorder<'a> {
+    inner: &'a dyn Dispatcher,
+    limit: Option<usize>,
+    exceeded: AtomicBool,
+    messages: Mutex<Vec<models::Result>>,
+}
+
+impl<'a> MessageRecorder<'a> {
+    fn new(inner: &'a dyn Dispatcher, limit: Option<usize>) -> Self {
+        Self {
+            inner,
+            limit,
+            exceeded: AtomicBool::new(false),
+            messages: Mutex::new(vec![]),
+        }
+    }
+
+    /// Returns the limit when a result was rejected because of it
+    fn exceeded_limit(&self) -> Option<usize> {
+        self.limit.filter(|_| self.exceeded.load(Ordering::Relaxed))
+    }
+
+    /// Returns the error a result exceeding the limit is rejected with
+    fn rejection(limit: usize) -> StorageError {
+        StorageError::UnexpectedData(format!("the limit of {limit} results is exceeded"))
+    }
+
+    /// Returns true when the script was aborted by rejecting a result exceeding the limit
+    fn is_rejection(limit: usize, kind: &ScriptResultKind) -> bool {
+        let ScriptResultKind::Error(InterpretError {
+            kind: InterpretErrorKind::FunctionCallError(e),
+            ..
+        }) = kind
+        else {
+            return false;
+        };
+        matches!(
+            <&InternalError>::try_from(&e.kind),
+            Ok(InternalError::Storage(e)) if *e == Self::rejection(limit)
+        )
+    }
+
+    fn into_messages(self) -> Vec<models::Result> {
+        self.messages
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Dispatcher for MessageRecorder<'_> {
+    fn dispatch(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+        let Field::Result(message) = &scope else {
+            return self.inner.dispatch(key, scope);
+        };
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(limit) = self.limit.filter(|x| messages.len() >= *x) {
+            self.exceeded.store(true, Ordering::Relaxed);
+            return Err(Self::rejection(limit));
+        }
+        let message = message.as_ref().clone();
+        self.inner.dispatch(key, scope)?;
+        messages.push(message);
+        Ok(())
+    }
+
+    fn dispatch_replace(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+        self.inner.dispatch_replace(key, scope)
+    }
+
+    fn on_exit(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.inner.on_exit(key)
+    }
+
+    fn host_started(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.inner.host_started(key)
+    }
+
+    fn host_finished(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.inner.host_finished(key)
+    }
+
+    fn stage_finished(&self, key: &ContextKey, stage: Stage) -> Result<(), StorageError> {
+        self.inner.stage_finished(key, stage)
+    }
+}
+
 /// Runs a single VT to completion on a single host.
 pub struct VTRunner<'a, S: ScannerStack> {
     storage: &'a S::Storage,
     loader: &'a S::Loader,
     executor: &'a Executor,
 
-    target: &'a Host,
+    target: &'a ScanTarget,
     vt: &'a Nvt,
     stage: Stage,
     param: Option<&'a Vec<Parameter>>,
-    scan_id: &'a ScanId,
+    filtered_udp_ports: FilteredPorts,
+    max_results: Option<usize>,
+    max_kb_value_size: Option<usize>,
 }
 
 impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
@@ -41,12 +169,14 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
         storage: &'a Stack::Storage,
         loader: &'a Stack::Loader,
         executor: &'a Executor,
-        target: &'a Host,
+        target: &'a ScanTarget,
         vt: &'a Nvt,
         stage: Stage,
         param: Option<&'a Vec<Parameter>>,
-        scan_id: &'a ScanId,
-    ) -> Result<ScriptResult, ExecuteError> {
+        filtered_udp_ports: FilteredPorts,
+        max_results: Option<usize>,
+        max_kb_value_size: Option<usize>,
+    ) -> Result<Vec<ScriptResult>, ExecuteError> {
         let s = Self {
             storage,
             loader,
@@ -55,65 +185,110 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
             vt,
             stage,
             param,
-            scan_id,
+            filtered_udp_ports,
+            max_results,
+            max_kb_value_size,
         };
         s.execute().await
     }
 
-    fn parameter(
-        &self,
-        parameter: &Parameter,
-        _register: &mut Register,
-    ) -> Result<(), ExecuteError> {
-        // TODO: implement
-        Err(ExecuteError::Parameter(parameter.clone()))
+    /// Verifies the deprecation, the required, mandatory and excluded keys as well as the
+    /// required ports of a VT without running it.
+    ///
+    /// Returns the reason why the VT would not run.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check(
+        storage: &'a Stack::Storage,
+        loader: &'a Stack::Loader,
+        executor: &'a Executor,
+        target: &'a ScanTarget,
+        vt: &'a Nvt,
+        stage: Stage,
+        filtered_udp_ports: FilteredPorts,
+        max_kb_value_size: Option<usize>,
+    ) -> Result<(), ScriptResultKind> {
+        let s = Self {
+            storage,
+            loader,
+            executor,
+            target,
+            vt,
+            stage,
+            param: None,
+            filtered_udp_ports,
+            max_results: None,
+            max_kb_value_size,
+        };
+        s.check_runnable(vt)
     }
 
-    fn set_parameters(&mut self, register: &mut Register) -> Result<(), ExecuteError> {
-        if let Some(params) = &self.param {
-            for p in params.iter() {
-                self.parameter(p, register)?;
+    /// Returns the preferences of the VT with the values of the scan parameters
+    ///
+    /// A preference without a parameter keeps its default. Fails on parameters that are not a
+    /// preference of the VT or don't match its type.
+    fn preferences(&self) -> Result<Vec<(NvtPreference, NaslValue)>, ExecuteError> {
+        let params = self.param.map(|x| x.as_slice()).unwrap_or_default();
+        for parameter in params {
+            let preference = self
+                .vt
+                .preferences
+                .iter()
+                .find(|x| x.id() == Some(parameter.id.into()))
+                .ok_or_else(|| ExecuteError::Parameter(parameter.clone()))?;
+            if !preference.accepts(&parameter.value) {
+                return Err(ExecuteError::InvalidParameter(
+                    parameter.clone(),
+                    preference.class(),
+                ));
             }
         }
-        Ok(())
+        Ok(self
+            .vt
+            .preferences
+            .iter()
+            .map(|preference| {
+                let value = params
+                    .iter()
+                    .find(|x| preference.id() == Some(x.id.into()))
+                    .map(|x| x.value.as_str());
+                (preference.clone(), preference_value(preference, value))
+            })
+            .collect())
     }
 
-    fn check_key<A, B, C>(
+    fn check_key<A, B>(
         &self,
         key: &ContextKey,
         kb_key: &str,
         result_none: A,
         result_some: B,
-        result_err: C,
     ) -> Result<(), ScriptResultKind>
     where
         A: Fn() -> Option<ScriptResultKind>,
         B: Fn(Primitive) -> Option<ScriptResultKind>,
-        C: Fn(StorageError) -> Option<ScriptResultKind>,
     {
         let _span = error_span!("kb_item", %key, kb_key).entered();
-        let result = match self.storage.retrieve(key, Retrieve::KB(kb_key.to_string())) {
-            Ok(mut x) => {
-                let x = x.next();
-                if let Some(x) = x {
-                    match x {
-                        Field::KB(kb) => {
-                            trace!(value=?kb.value, "found");
-                            result_some(kb.value)
-                        }
-                        x => {
-                            trace!(field=?x, "found but it is not a KB item");
-                            result_none()
-                        }
-                    }
+        // a huge value is rejected instead of truncated, as a truncated value may be
+        // interpreted differently
+        let result = match self.storage.retrieve_kb(
+            key,
+            Retrieve::KB(kb_key.to_string()),
+            self.max_kb_value_size,
+        ) {
+            Ok(x) => {
+                if let Some(kb) = x.into_iter().next() {
+                    trace!(value=?kb.value, "found");
+                    result_some(kb.value)
                 } else {
                     trace!("not found");
                     result_none()
                 }
             }
             Err(e) => {
+                // A storage error must not be mistaken for a missing key, otherwise an
+                // unreachable storage would look like a host lacking that information.
                 warn!(error=%e, "storage error");
-                result_err(e)
+                Some(ScriptResultKind::StorageError(e.to_string()))
             }
         };
         match result {
@@ -122,6 +297,14 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
         }
     }
 
+    /// Verifies that the VT is not deprecated and that its keys and ports are available.
+    fn check_runnable(&self, vt: &Nvt) -> Result<(), ScriptResultKind> {
+        if vt.is_deprecated() {
+            return Err(ScriptResultKind::Deprecated);
+        }
+        self.check_keys(vt)
+    }
+
     fn check_keys(&self, vt: &Nvt) -> Result<(), ScriptResultKind> {
         let key = self.generate_key();
         let check_required_key = |k: &str| {
@@ -130,7 +313,6 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
                 k,
                 || Some(ScriptResultKind::MissingRequiredKey(k.into())),
                 |_| None,
-                |_| Some(ScriptResultKind::MissingRequiredKey(k.into())),
             )
         };
         for k in &vt.required_keys {
@@ -143,7 +325,6 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
                 k,
                 || Some(ScriptResultKind::MissingMandatoryKey(k.into())),
                 |_| None,
-                |_| Some(ScriptResultKind::MissingMandatoryKey(k.into())),
             )
         };
         for k in &vt.mandatory_keys {
@@ -156,7 +337,6 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
                 k,
                 || None,
                 |_| Some(ScriptResultKind::ContainsExcludedKey(k.into())),
-                |_| None,
             )
         };
         for k in &vt.excluded_keys {
@@ -164,19 +344,26 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
         }
 
         let check_port = |pt: Protocol, port: &str| {
+            if matches!(port, "*" | "") {
+                return self.check_any_port(&key, pt, port);
+            }
             let kbk = generate_port_kb_key(pt, port);
             self.check_key(
                 &key,
                 &kbk,
                 || Some(ScriptResultKind::MissingPort(pt, port.to_string())),
-                |v| {
-                    if v.into() {
-                        None
-                    } else {
+                |v| match (pt, PortState::from(&v)) {
+                    (Protocol::UDP, PortState::Filtered)
+                        if self.filtered_udp_ports == FilteredPorts::Reject =>
+                    {
+                        Some(ScriptResultKind::PortFiltered(pt, port.to_string()))
+                    }
+                    // filtered TCP ports are handled as before by the truthiness of the value
+                    (_, PortState::Closed) => {
                         Some(ScriptResultKind::MissingPort(pt, port.to_string()))
                     }
+                    _ => None,
                 },
-                |_| Some(ScriptResultKind::MissingPort(pt, port.to_string())),
             )
         };
         for k in &vt.required_ports {
@@ -189,55 +376,186 @@ impl<'a, Stack: ScannerStack> VTRunner<'a, Stack> {
         Ok(())
     }
 
-    // TODO: probably better to enhance ContextKey::Scan to contain target and scan_id?
+    /// Verifies that any port of the protocol is open
+    ///
+    /// Filtered ports are handled the same way as for a specific port.
+    fn check_any_port(
+        &self,
+        key: &ContextKey,
+        pt: Protocol,
+        port: &str,
+    ) -> Result<(), ScriptResultKind> {
+        let prefix = generate_port_kb_key(pt, "");
+        let _span = error_span!("kb_items", %key, prefix).entered();
+        let max = self.max_kb_value_size;
+        let items = match self
+            .storage
+            .retrieve_kb(key, Retrieve::KBPrefix(prefix), max)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                warn!(error=%e, "storage error");
+                return Err(ScriptResultKind::StorageError(e.to_string()));
+            }
+        };
+        let open = items
+            .into_iter()
+            .any(|kb| match (pt, PortState::from(&kb.value)) {
+                (Protocol::UDP, PortState::Filtered) => {
+                    self.filtered_udp_ports == FilteredPorts::Accept
+                }
+                (_, PortState::Closed) => false,
+                _ => true,
+            });
+        if open {
+            Ok(())
+        } else {
+            trace!("no open port found");
+            Err(ScriptResultKind::MissingPort(pt, port.to_string()))
+        }
+    }
+
     fn generate_key(&self) -> ContextKey {
-        ContextKey::Scan(self.scan_id.clone(), Some(self.target.clone()))
+        ContextKey::from(self.target)
     }
 
-    async fn get_result_kind(&self, code: &str, register: Register) -> ScriptResultKind {
-        if let Err(e) = self.check_keys(self.vt) {
-            return e;
+    /// Returns the result kinds of the script run as well as the messages sent by it
+    ///
+    /// When the script forked, every forked interpreter reaching the exit call produces an own
+    /// result kind.
+    async fn get_results(
+        &self,
+        code: &str,
+        preferences: Vec<(NvtPreference, NaslValue)>,
+    ) -> (Vec<ScriptResultKind>, Vec<models::Result>) {
+        if let Err(e) = self.check_runnable(self.vt) {
+            return (vec![e], vec![]);
+        }
+        let recorder = MessageRecorder::new(self.storage.as_dispatcher(), self.max_results);
+        // A panic, e.g. of a not yet implemented function, must only abort this script and not
+        // the whole scan. With panic=abort the process is aborted nevertheless.
+        let run = AssertUnwindSafe(self.get_result_kinds(code, preferences, &recorder));
+        let mut kinds = match run.catch_unwind().await {
+            Ok(kinds) => kinds,
+            Err(panic) => {
+                let message = panic_message(panic.as_ref());
+                warn!(oid = self.vt.oid, message, "script panicked");
+                vec![ScriptResultKind::Panic(message)]
+            }
+        };
+        if let Some(limit) = recorder.exceeded_limit() {
+            // the rejected result aborted the script with an error, other errors are kept
+            for kind in kinds
+                .iter_mut()
+                .filter(|x| MessageRecorder::is_rejection(limit, x))
+            {
+                *kind = ScriptResultKind::ResultLimitExceeded(limit);
+            }
         }
+        (kinds, recorder.into_messages())
+    }
+
+    async fn get_result_kinds(
+        &self,
+        code: &str,
+        preferences: Vec<(NvtPreference, NaslValue)>,
+        dispatcher: &dyn Dispatcher,
+    ) -> Vec<ScriptResultKind> {
         let mut target = Target::default();
-        target.set_target(self.target.clone());
+        target.set_target(self.target.kb_host());
 
         let context = Context::new(
             self.generate_key(),
             target,
-            self.storage.as_dispatcher(),
+            dispatcher,
             self.storage.as_retriever(),
             self.loader,
             self.executor,
-        );
-        let mut results = Box::pin(CodeInterpreter::new(code, register, &context).stream());
-        while let Some(r) = results.next().await {
+        )
+        .with_script_preferences(preferences);
+        let mut interpreter = CodeInterpreter::new(code, Register::default(), &context);
+        while let Some(r) = interpreter.next_result().await {
             match r {
-                Ok(NaslValue::Exit(x)) => return ScriptResultKind::ReturnCode(x),
-                Err(e) => return ScriptResultKind::Error(e),
+                Ok(NaslValue::Exit(x)) => {
+                    let mut kinds = vec![ScriptResultKind::ReturnCode(x)];
+                    // the remaining forks are evaluating the same exit statement
+                    while let Some(r) = interpreter.next_fork().await {
+                        match r {
+                            Ok(NaslValue::Exit(x)) => kinds.push(ScriptResultKind::Forked(x)),
+                            Err(e) => kinds.push(ScriptResultKind::Error(e)),
+                            Ok(x) => {
+                                trace!(statement_result=?x);
+                            }
+                        }
+                    }
+                    return kinds;
+                }
+                Err(e) => return vec![ScriptResultKind::Error(e)],
                 Ok(x) => {
                     trace!(statement_result=?x);
                 }
             }
+            // allows a timeout of the caller to abort the script between statements
+            tokio::task::yield_now().await;
         }
-        ScriptResultKind::ReturnCode(0)
+        vec![ScriptResultKind::ReturnCode(0)]
     }
 
-    async fn execute(mut self) -> Result<ScriptResult, ExecuteError> {
+    async fn execute(self) -> Result<Vec<ScriptResult>, ExecuteError> {
         let code = self.loader.load(&self.vt.filename)?;
-        let mut register = Register::default();
-        self.set_parameters(&mut register)?;
+        let preferences = self.preferences()?;
 
         // currently scans are limited to the target as well as the id.
         tracing::debug!("running");
-        let kind = self.get_result_kind(&code, register).await;
-        tracing::debug!(result=?kind, "finished");
-        Ok(ScriptResult {
-            oid: self.vt.oid.clone(),
-            filename: self.vt.filename.clone(),
-            stage: self.stage,
-            kind,
-            target: self.target.clone(),
-        })
+        let (kinds, messages) = self.get_results(&code, preferences).await;
+        tracing::debug!(results=?kinds, messages = messages.len(), "finished");
+        let mut messages = Some(messages);
+        Ok(kinds
+            .into_iter()
+            .map(|kind| ScriptResult {
+                oid: self.vt.oid.clone(),
+                filename: self.vt.filename.clone(),
+                stage: self.stage,
+                kind,
+                target: self.target.host.clone(),
+                messages: messages.take().unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+/// Converts the value of a scan parameter, or the default without one, into the value of the
+/// preference returned by `script_get_preference`
+///
+/// An integer becomes a number and a list of integers (see [`integer_list`]), e.g. of multiple
+/// ports, an array of numbers. The default of a radio contains all choices, of which the first
+/// one is used. As in openvas every other type, including entries, passwords and files, is kept
+/// as string.
+fn preference_value(preference: &NvtPreference, value: Option<&str>) -> NaslValue {
+    let value = value.unwrap_or_else(|| match preference.class() {
+        PreferenceType::Radio => preference.default().split(';').next().unwrap_or_default(),
+        _ => preference.default(),
+    });
+    match preference.class() {
+        PreferenceType::Integer => match (value.trim().parse(), integer_list(value)) {
+            (Ok(x), _) => NaslValue::Number(x),
+            (_, Some(x)) => NaslValue::Array(x.into_iter().map(NaslValue::Number).collect()),
+            _ => NaslValue::String(value.to_owned()),
+        },
+        PreferenceType::CheckBox
+        | PreferenceType::Entry
+        | PreferenceType::File
+        | PreferenceType::Password
+        | PreferenceType::Radio
+        | PreferenceType::SshLogin => NaslValue::String(value.to_owned()),
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(x), _) => x.to_string(),
+        (_, Some(x)) => x.clone(),
+        _ => "unknown panic".to_string(),
     }
 }
 
@@ -40,6 +40,8 @@ pub enum CliErrorKind {
     MissingArguments(Vec<String>),
     #[error("{0}")]
     Corrupt(String),
+    #[error("{findings} findings with a severity of at least {threshold}")]
+    SeverityExceeded { threshold: f32, findings: usize },
 }
 
 impl From<ExecuteError> for CliErrorKind {
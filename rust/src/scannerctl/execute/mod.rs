@@ -2,15 +2,21 @@
 //
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
-use std::{fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
 
 use clap::{arg, value_parser, Arg, ArgAction, Command};
 use futures::StreamExt;
 use scannerlib::feed::{HashSumNameLoader, Update};
-use scannerlib::models::Scan;
+use scannerlib::models::{ResultType, Scan};
 use scannerlib::nasl::{nasl_std_functions, FSPluginLoader};
-use scannerlib::scanner::ScanRunner;
+use scannerlib::openvas::Severity;
+use scannerlib::scanner::{ScanRunner, ScriptResult};
 use scannerlib::scheduling::{ExecutionPlaner, WaveExecutionPlan};
+use scannerlib::storage::{DefaultDispatcher, Retriever};
 use tracing::{info, warn, warn_span};
 
 use crate::{interpret, CliError, CliErrorKind, Db};
@@ -56,7 +62,7 @@ async fn scan(args: &clap::ArgMatches) -> Result<(), CliError> {
         .get_one::<PathBuf>("path")
         .expect("A feed path is required to run a scan")
         .clone();
-    let storage = scannerlib::storage::DefaultDispatcher::new();
+    let storage = DefaultDispatcher::new();
     info!("loading feed. This may take a while.");
 
     let loader = FSPluginLoader::new(feed);
@@ -82,12 +88,28 @@ async fn scan(args: &clap::ArgMatches) -> Result<(), CliError> {
         }
     } else {
         let executor = nasl_std_functions();
-        let runner: ScanRunner<(_, _)> =
+        let mut runner: ScanRunner<(_, _)> =
             ScanRunner::new(&storage, &loader, &executor, schedule, &scan).unwrap();
+        if let Some(max_hosts) = args.get_one::<usize>("max-hosts") {
+            runner = runner.with_max_hosts(*max_hosts);
+        }
+        if let Some(max_vts) = args.get_one::<usize>("max-vts") {
+            runner = runner.with_max_vts(*max_vts);
+        }
+        let mut findings = match args.get_one::<f32>("fail-on-severity") {
+            Some(threshold) => Some(Findings::new(&storage, *threshold).map_err(|e| CliError {
+                filename: "".to_string(),
+                kind: e.into(),
+            })?),
+            None => None,
+        };
         let mut results = Box::pin(runner.stream());
         while let Some(x) = results.next().await {
             match x {
                 Ok(x) => {
+                    if let Some(findings) = &mut findings {
+                        findings.add(&x);
+                    }
                     let _span =
                         warn_span!("script_result", filename=x.filename, oid=x.oid, stage=%x.stage)
                             .entered();
@@ -102,11 +124,73 @@ async fn scan(args: &clap::ArgMatches) -> Result<(), CliError> {
                 }
             }
         }
+        if let Some(findings) = findings.filter(|x| !x.seen.is_empty()) {
+            return Err(CliError {
+                filename: "".to_string(),
+                kind: CliErrorKind::SeverityExceeded {
+                    threshold: findings.threshold,
+                    findings: findings.seen.len(),
+                },
+            });
+        }
     }
 
     Ok(())
 }
 
+/// Identity of a finding: OID, host, port and message
+type FindingKey = (String, Option<String>, Option<i16>, Option<String>);
+
+/// Collects the alarms of a scan whose VT has a severity score of at least `threshold`.
+///
+/// Log and error messages are not considered to be findings, as they have no severity in a report.
+/// Like the deduplication of the result collector, an alarm with the same OID, host, port and
+/// message as an already collected one is counted once.
+struct Findings {
+    threshold: f32,
+    scores: HashMap<String, f32>,
+    seen: HashSet<FindingKey>,
+}
+
+impl Findings {
+    fn new(
+        storage: &DefaultDispatcher,
+        threshold: f32,
+    ) -> Result<Self, scannerlib::storage::StorageError> {
+        let scores = storage
+            .vts()?
+            .filter_map(|vt| Some((vt.oid.clone(), Severity::of(&vt)?.score?)))
+            .collect();
+        Ok(Self {
+            threshold,
+            scores,
+            seen: HashSet::new(),
+        })
+    }
+
+    fn add(&mut self, result: &ScriptResult) {
+        if !self
+            .scores
+            .get(&result.oid)
+            .is_some_and(|x| *x >= self.threshold)
+        {
+            return;
+        }
+        for message in result
+            .messages
+            .iter()
+            .filter(|x| x.r_type == ResultType::Alarm)
+        {
+            self.seen.insert((
+                result.oid.clone(),
+                message.ip_address.clone(),
+                message.port,
+                message.message.clone(),
+            ));
+        }
+    }
+}
+
 async fn script(args: &clap::ArgMatches) -> Option<Result<(), CliError>> {
     let feed = args.get_one::<PathBuf>("path").cloned();
     let script = args
@@ -114,12 +198,14 @@ async fn script(args: &clap::ArgMatches) -> Option<Result<(), CliError>> {
         .cloned()
         .expect("script is set to required");
     let target = args.get_one::<String>("target").cloned();
+    let dump_kb = args.get_flag("dump-kb");
     Some(
         interpret::run(
             &Db::InMemory,
             feed.clone(),
             &script.to_string(),
             target.clone(),
+            dump_kb,
         )
         .await,
     )
@@ -141,7 +227,8 @@ When ID is used than a valid feed path must be given within the path parameter."
                             .value_parser(value_parser!(PathBuf)),
                     )
                     .arg(Arg::new("script").required(true))
-                    .arg(arg!(-t --target <HOST> "Target to scan").required(false)),
+                    .arg(arg!(-t --target <HOST> "Target to scan").required(false))
+                    .arg(arg!(--"dump-kb" "Prints all KB items after the script is finished").required(false).action(ArgAction::SetTrue)),
             )
             .subcommand(
                 Command::new("scan")
@@ -155,6 +242,9 @@ When ID is used than a valid feed path must be given within the path parameter."
                     )
                     .arg(arg!(--schedule "Prints just the schedule without executing the scan").required(false).action(ArgAction::SetTrue))
                     .arg(arg!(-i --input "Parses scan json from stdin.").required(false).action(ArgAction::SetTrue))
+                    .arg(arg!(--"max-hosts" <NUMBER> "Scans only the first NUMBER hosts of the target").required(false).value_parser(value_parser!(usize)))
+                    .arg(arg!(--"max-vts" <NUMBER> "Runs only the first NUMBER VTs of the schedule").required(false).value_parser(value_parser!(usize)))
+                    .arg(arg!(--"fail-on-severity" <LEVEL> "Exits with code 3 when a finding has a severity of at least LEVEL").required(false).value_parser(value_parser!(f32)))
                     .arg(Arg::new("json").required(false).value_parser(value_parser!(PathBuf)))
             )
             // this is here for downwards compatible reasons and should be moved to the script
@@ -165,6 +255,7 @@ When ID is used than a valid feed path must be given within the path parameter."
                     .value_parser(value_parser!(PathBuf)),
             )
             .arg(Arg::new("script").required(false))
-            .arg(arg!(-t --target <HOST> "Target to scan").required(false)),
+            .arg(arg!(-t --target <HOST> "Target to scan").required(false))
+            .arg(arg!(--"dump-kb" "Prints all KB items after the script is finished").required(false).action(ArgAction::SetTrue)),
     ))
 }
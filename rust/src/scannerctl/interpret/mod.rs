@@ -33,6 +33,7 @@ struct Run<L, S> {
     context_builder: ContextFactory<L, S>,
     target: String,
     scan_id: String,
+    dump_kb: bool,
 }
 
 struct RunBuilder<L, S> {
@@ -40,6 +41,7 @@ struct RunBuilder<L, S> {
     storage: S,
     target: String,
     scan_id: String,
+    dump_kb: bool,
 }
 
 impl Default for RunBuilder<NoOpLoader, DefaultDispatcher> {
@@ -49,6 +51,7 @@ impl Default for RunBuilder<NoOpLoader, DefaultDispatcher> {
             loader: NoOpLoader::default(),
             target: String::default(),
             scan_id: "scannerctl".to_string(),
+            dump_kb: false,
         }
     }
 }
@@ -64,6 +67,7 @@ where
             storage: s,
             target: self.target,
             scan_id: self.scan_id,
+            dump_kb: self.dump_kb,
         }
     }
 
@@ -73,6 +77,7 @@ where
             storage: self.storage,
             target: self.target,
             scan_id: self.scan_id,
+            dump_kb: self.dump_kb,
         }
     }
 
@@ -86,11 +91,18 @@ where
         self
     }
 
+    /// Prints all KB items of the scan after the script is finished.
+    pub fn dump_kb(mut self, dump_kb: bool) -> RunBuilder<L, S> {
+        self.dump_kb = dump_kb;
+        self
+    }
+
     pub fn build(self) -> Run<L, S> {
         Run {
             context_builder: ContextFactory::new(self.loader, self.storage),
             scan_id: self.scan_id,
             target: self.target,
+            dump_kb: self.dump_kb,
         }
     }
 }
@@ -124,14 +136,22 @@ where
         }
     }
 
+    fn print_kb(&self, key: &ContextKey) -> Result<(), CliErrorKind> {
+        if self.dump_kb {
+            for kb in self.context_builder.storage.dump_kb(key)? {
+                println!("{}: {}", kb.key, kb.value);
+            }
+        }
+        Ok(())
+    }
+
     async fn run(&self, script: &str) -> Result<(), CliErrorKind> {
         let target = match self.target.is_empty() {
             true => None,
             false => Some(self.target.clone()),
         };
-        let context = self
-            .context_builder
-            .build(ContextKey::Scan(self.scan_id.clone(), target));
+        let key = ContextKey::Scan(self.scan_id.clone(), target);
+        let context = self.context_builder.build(key.clone());
         let register = RegisterBuilder::build();
         let code = self.load(script)?;
         let mut results = CodeInterpreter::new(&code, register, &context).stream();
@@ -153,14 +173,17 @@ where
                 }
             };
             match r {
-                NaslValue::Exit(rc) => std::process::exit(rc as i32),
+                NaslValue::Exit(rc) => {
+                    self.print_kb(&key)?;
+                    std::process::exit(rc as i32)
+                }
                 _ => {
                     tracing::debug!("=> {r:?}", r = r);
                 }
             }
         }
 
-        Ok(())
+        self.print_kb(&key)
     }
 }
 
@@ -201,10 +224,12 @@ pub async fn run(
     feed: Option<PathBuf>,
     script: &str,
     target: Option<String>,
+    dump_kb: bool,
 ) -> Result<(), CliError> {
     let builder = RunBuilder::default()
         .target(target.unwrap_or_default())
-        .scan_id(format!("scannerctl-{script}"));
+        .scan_id(format!("scannerctl-{script}"))
+        .dump_kb(dump_kb);
     let result = match (db, feed) {
         (Db::Redis(url), None) => {
             builder
@@ -218,7 +243,7 @@ pub async fn run(
             let storage = create_redis_storage(url);
             let loader = FSPluginLoader::new(path);
             load_feed_by_exec(&storage, &loader).await?;
-            let builder = RunBuilder::default().loader(loader);
+            let builder = builder.loader(loader);
             builder.storage(storage).build().run(script).await
         }
         (Db::InMemory, Some(path)) => {
@@ -231,7 +256,7 @@ pub async fn run(
                 load_feed_by_exec(&storage, &loader).await?
             }
 
-            let builder = RunBuilder::default().loader(loader);
+            let builder = builder.loader(loader);
             builder.storage(storage).build().run(script).await
         }
     };
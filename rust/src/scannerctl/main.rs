@@ -79,6 +79,11 @@ async fn main() {
                 tracing::warn!("script error, {e}");
                 std::process::exit(1);
             }
+            // distinct from script errors so that CI pipelines can tell them apart
+            CliErrorKind::SeverityExceeded { .. } => {
+                tracing::warn!("{}", e.kind);
+                std::process::exit(3);
+            }
             _ => panic!("{e}"),
         },
     }
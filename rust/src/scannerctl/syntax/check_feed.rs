@@ -0,0 +1,85 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use scannerlib::nasl::syntax::{load_non_utf8_path, parse, LoadError, SyntaxError};
+use walkdir::WalkDir;
+
+use super::location;
+use crate::{CliError, CliErrorKind};
+
+fn errors(path: &Path) -> Result<Vec<SyntaxError>, LoadError> {
+    let code = load_non_utf8_path(path)?;
+    Ok(parse(&code).filter_map(|x| x.err()).collect())
+}
+
+// Parses the scripts on the given number of threads and returns the errors in the order of the
+// scripts regardless of which thread parsed which script.
+fn parse_all(scripts: &[PathBuf], workers: usize) -> Vec<Result<Vec<SyntaxError>, LoadError>> {
+    let next = AtomicUsize::new(0);
+    let mut results = std::thread::scope(|s| {
+        let handles = (0..workers.max(1))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        match scripts.get(i) {
+                            Some(path) => results.push((i, errors(path))),
+                            None => return results,
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|x| x.join().expect("parser thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, x)| x).collect()
+}
+
+/// Parses every NASL script within a feed on `workers` threads and prints each syntax error.
+///
+/// The parser continues after an error, so that all errors of a script are shown. The output
+/// is ordered by the path of the scripts and therefore independent of the number of workers.
+/// When a script failed to parse the process exits with 1 after printing a summary.
+pub fn run(path: &Path, workers: usize) -> Result<(), CliError> {
+    let scripts = WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|x| x == "nasl"))
+        .map(|e| e.into_path())
+        .collect::<Vec<_>>();
+    let mut failed: Vec<(&PathBuf, SyntaxError)> = vec![];
+    for (path, errors) in scripts.iter().zip(parse_all(&scripts, workers)) {
+        let errors = errors.map_err(|e| CliError {
+            kind: CliErrorKind::LoadError(e),
+            filename: path.to_string_lossy().to_string(),
+        })?;
+        for err in &errors {
+            eprintln!("{}:{}: {err}", path.to_string_lossy(), location(err));
+        }
+        if let Some(err) = errors.into_iter().next() {
+            failed.push((path, err));
+        }
+    }
+    println!(
+        "checked: {} files; failed: {} files",
+        scripts.len(),
+        failed.len()
+    );
+    for (path, err) in &failed {
+        println!("{}:{}", path.to_string_lossy(), location(err));
+    }
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
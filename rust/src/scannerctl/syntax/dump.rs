@@ -0,0 +1,41 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::path::Path;
+
+use scannerlib::nasl::syntax::{load_non_utf8_path, parse};
+
+use super::location;
+use crate::{CliError, CliErrorKind};
+
+/// Prints the statements of a NASL file either as debug output or as JSON.
+///
+/// Every syntax error is printed with its line and column, the first one is returned.
+pub fn run(path: &Path, json: bool) -> Result<(), CliError> {
+    let cli_error = |kind| CliError {
+        kind,
+        filename: path.to_string_lossy().to_string(),
+    };
+    let code = load_non_utf8_path(path).map_err(|e| cli_error(e.into()))?;
+    let mut statements = vec![];
+    let mut first_error = None;
+    for result in parse(&code) {
+        match result {
+            Ok(stmt) => statements.push(stmt),
+            Err(err) => {
+                eprintln!("{}:{}: {err}", path.to_string_lossy(), location(&err));
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    if let Some(err) = first_error {
+        return Err(cli_error(CliErrorKind::SyntaxError(err)));
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&statements)?);
+    } else {
+        statements.iter().for_each(|stmt| println!("{stmt:#?}"));
+    }
+    Ok(())
+}
@@ -5,12 +5,42 @@
 use std::path::PathBuf;
 
 use clap::{arg, value_parser, Arg, ArgAction, Command};
+use scannerlib::nasl::syntax::SyntaxError;
 
 use crate::{add_verbose, CliError};
 
 pub mod check;
+pub mod check_feed;
+pub mod dump;
+
+/// Returns the line and column of a syntax error
+fn location(err: &SyntaxError) -> String {
+    match err.as_token() {
+        Some(token) => format!("{}:{}", token.line_column.0, token.line_column.1),
+        None => "EOF".to_owned(),
+    }
+}
 
 pub async fn run(root: &clap::ArgMatches) -> Option<Result<(), CliError>> {
+    if let Some((args, _)) = crate::get_args_set_logging(root, "dump-ast") {
+        let path = match args.get_one::<PathBuf>("path") {
+            Some(path) => path,
+            _ => unreachable!("path is set to required"),
+        };
+        let json = args.get_one::<bool>("json").cloned().unwrap_or_default();
+        return Some(dump::run(path, json));
+    }
+    if let Some((args, _)) = crate::get_args_set_logging(root, "check-feed") {
+        let path = match args.get_one::<PathBuf>("path") {
+            Some(path) => path,
+            _ => unreachable!("path is set to required"),
+        };
+        let workers = args
+            .get_one::<usize>("jobs")
+            .cloned()
+            .unwrap_or_else(num_cpus::get);
+        return Some(check_feed::run(path, workers));
+    }
     let (args, verbose) = crate::get_args_set_logging(root, "syntax")?;
     let path = match args.get_one::<PathBuf>("path").cloned() {
         Some(path) => path,
@@ -36,4 +66,32 @@ pub fn extend_args(cmd: Command) -> Command {
                     .action(ArgAction::SetTrue),
             ),
     ))
+    .subcommand(add_verbose(
+        Command::new("dump-ast")
+            .about("Prints the parsed statements of a NASL file.")
+            .arg(
+                Arg::new("path")
+                    .required(true)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                arg!(--json "Prints the statements as JSON.")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+            ),
+    ))
+    .subcommand(add_verbose(
+        Command::new("check-feed")
+            .about("Verifies that every NASL script of a feed parses.")
+            .arg(
+                Arg::new("path")
+                    .required(true)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                arg!(-j --jobs <NUMBER> "Number of threads parsing scripts, defaults to the number of CPUs.")
+                    .required(false)
+                    .value_parser(value_parser!(usize)),
+            ),
+    ))
 }
@@ -0,0 +1,259 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::{
+    models::{Scan, VT},
+    storage::{
+        item::{NVTField, NVTKey},
+        ContextKey, Field, Retrieve, Retriever, StorageError,
+    },
+};
+
+use super::{ConcurrentVT, ConcurrentVTResult, ExecutionPlan, ExecutionPlaner, VTError};
+
+/// What a plan is computed from: the kind of plan and the VTs with their parameters
+///
+/// The VTs and parameters are sorted, so that scans listing them in another order share a plan.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct PlanInput {
+    kind: &'static str,
+    vts: Vec<VT>,
+}
+
+impl PlanInput {
+    fn of<E>(scan: &Scan) -> Self {
+        let mut vts = scan.vts.clone();
+        for vt in vts.iter_mut() {
+            vt.parameters.sort();
+        }
+        vts.sort();
+        Self {
+            kind: std::any::type_name::<E>(),
+            vts,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CachedPlans {
+    feed_version: Option<String>,
+    plans: HashMap<PlanInput, Vec<ConcurrentVT>>,
+}
+
+/// Caches the execution plans of scans by their VTs
+///
+/// Scans of the same VTs with the same parameters, e.g. scans of different hosts, reuse the plan
+/// that was computed for the first of them instead of resolving the VTs and their dependencies
+/// again. As the dependencies are part of the feed, all plans are dropped when the feed version
+/// of the retriever changes. Changes of the VTs without a new feed version require a [`Self::clear`].
+#[derive(Debug, Default)]
+pub struct ScheduleCache {
+    cached: Mutex<CachedPlans>,
+}
+
+impl ScheduleCache {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the plan of the scan, which is only computed when it is not cached yet
+    ///
+    /// Plans that failed to be computed are not cached.
+    pub fn execution_plan<E, R>(
+        &self,
+        retriever: &R,
+        scan: &Scan,
+    ) -> Result<impl Iterator<Item = ConcurrentVTResult>, VTError>
+    where
+        E: ExecutionPlan,
+        R: Retriever + ?Sized,
+    {
+        let feed_version = retriever
+            .retrieve(&ContextKey::default(), Retrieve::NVT(Some(NVTKey::Version)))?
+            .find_map(|x| match x {
+                Field::NVT(NVTField::Version(x)) => Some(x),
+                _ => None,
+            });
+        let input = PlanInput::of::<E>(scan);
+        {
+            let mut cached = self.cached.lock().map_err(StorageError::from)?;
+            if cached.feed_version != feed_version {
+                tracing::debug!(?feed_version, "feed changed, dropping cached plans");
+                cached.plans.clear();
+                cached.feed_version = feed_version.clone();
+            }
+            if let Some(plan) = cached.plans.get(&input) {
+                tracing::trace!(vts = input.vts.len(), "using cached plan");
+                return Ok(plan.clone().into_iter().map(Ok));
+            }
+        }
+        let plan = retriever
+            .execution_plan::<E>(scan)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut cached = self.cached.lock().map_err(StorageError::from)?;
+        // the feed may have changed while computing the plan
+        if cached.feed_version == feed_version {
+            cached.plans.insert(input, plan.clone());
+        }
+        Ok(plan.into_iter().map(Ok))
+    }
+
+    /// Drops all cached plans
+    pub fn clear(&self) {
+        if let Ok(mut cached) = self.cached.lock() {
+            cached.plans.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::models::{Parameter, Scan, Target, VT};
+    use crate::scheduling::{ScheduleCache, Stage, WaveExecutionPlan};
+    use crate::storage::item::{NVTField, Nvt};
+    use crate::storage::{
+        ContextKey, DefaultDispatcher, Dispatcher, Field, FieldKeyResult, Retrieve, Retriever,
+        StorageError,
+    };
+
+    /// Counts the lookups of VTs, which are done for each computation of a plan
+    #[derive(Default)]
+    struct CountingRetriever(DefaultDispatcher, AtomicUsize);
+
+    impl Retriever for CountingRetriever {
+        fn retrieve(
+            &self,
+            key: &ContextKey,
+            scope: Retrieve,
+        ) -> Result<Box<dyn Iterator<Item = Field>>, StorageError> {
+            self.0.retrieve(key, scope)
+        }
+
+        fn retrieve_by_field(&self, field: Field, scope: Retrieve) -> FieldKeyResult {
+            self.0.retrieve_by_field(field, scope)
+        }
+
+        fn retrieve_by_fields(&self, field: Vec<Field>, scope: Retrieve) -> FieldKeyResult {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.retrieve_by_fields(field, scope)
+        }
+    }
+
+    fn scan(host: &str) -> Scan {
+        Scan {
+            target: Target {
+                hosts: vec![host.to_owned()],
+                ..Default::default()
+            },
+            vts: vec![VT {
+                oid: "1".to_owned(),
+                parameters: vec![],
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn feed() -> [Nvt; 2] {
+        [
+            Nvt {
+                oid: "0".to_owned(),
+                filename: "/0".to_owned(),
+                ..Default::default()
+            },
+            Nvt {
+                oid: "1".to_owned(),
+                filename: "/1".to_owned(),
+                dependencies: vec!["/0".to_owned()],
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn counting_retriever() -> CountingRetriever {
+        let retriever = CountingRetriever::default();
+        for nvt in feed() {
+            retriever
+                .0
+                .dispatch(&ContextKey::default(), nvt.into())
+                .expect("should store");
+        }
+        retriever
+    }
+
+    #[test]
+    fn plan_is_computed_once() {
+        let retriever = counting_retriever();
+        let feed = feed();
+        let cache = ScheduleCache::new();
+        let plan = |host| {
+            cache
+                .execution_plan::<WaveExecutionPlan, _>(&retriever, &scan(host))
+                .expect("plan")
+                .collect::<Result<Vec<_>, _>>()
+                .expect("plan")
+        };
+        let expected = vec![
+            (Stage::End, vec![(feed[0].clone(), None)]),
+            (Stage::End, vec![(feed[1].clone(), Some(vec![]))]),
+        ];
+        assert_eq!(plan("first.host"), expected);
+        let lookups = retriever.1.load(Ordering::SeqCst);
+        assert!(lookups > 0);
+        assert_eq!(plan("second.host"), expected);
+        assert_eq!(retriever.1.load(Ordering::SeqCst), lookups);
+
+        // a new feed version invalidates the cached plans
+        retriever
+            .0
+            .dispatch(
+                &ContextKey::default(),
+                Field::NVT(NVTField::Version("2".to_owned())),
+            )
+            .expect("should store");
+        assert_eq!(plan("first.host"), expected);
+        assert_eq!(retriever.1.load(Ordering::SeqCst), 2 * lookups);
+    }
+
+    #[test]
+    fn plan_is_looked_up_by_vts_and_parameters() {
+        let retriever = counting_retriever();
+        let cache = ScheduleCache::new();
+        let scan_of = |vts: &[(&str, &[(u16, &str)])]| Scan {
+            vts: vts
+                .iter()
+                .map(|(oid, parameters)| VT {
+                    oid: oid.to_string(),
+                    parameters: parameters
+                        .iter()
+                        .map(|(id, value)| Parameter {
+                            id: *id,
+                            value: value.to_string(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let lookups = |scan: &Scan| {
+            cache
+                .execution_plan::<WaveExecutionPlan, _>(&retriever, scan)
+                .expect("plan")
+                .for_each(drop);
+            retriever.1.load(Ordering::SeqCst)
+        };
+        let first = lookups(&scan_of(&[("0", &[]), ("1", &[(1, "a"), (2, "b")])]));
+        // the order of VTs and parameters does not matter
+        assert_eq!(
+            lookups(&scan_of(&[("1", &[(2, "b"), (1, "a")]), ("0", &[])])),
+            first
+        );
+        // other parameters need another plan
+        assert!(lookups(&scan_of(&[("0", &[]), ("1", &[(1, "a"), (2, "c")])])) > first);
+    }
+}
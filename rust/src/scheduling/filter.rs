@@ -0,0 +1,240 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::ConcurrentVTResult;
+
+/// Restricts a schedule to the VTs of an OID allowlist.
+///
+/// Dependencies of an allowed VT are kept as well, even when they are not part of the
+/// allowlist, as otherwise the allowed VT could not run as intended. The order of the
+/// inner schedule is preserved and stages without any remaining VT are skipped.
+///
+/// As dependencies are scheduled before the VTs depending on them, the whole inner schedule
+/// is read on the first call of `next`.
+pub struct FilteredSchedule<S> {
+    inner: Option<S>,
+    allowed: HashSet<String>,
+    filtered: VecDeque<ConcurrentVTResult>,
+}
+
+impl<S> FilteredSchedule<S>
+where
+    S: Iterator<Item = ConcurrentVTResult>,
+{
+    /// Creates a new FilteredSchedule of the given schedule only returning the given OIDs and
+    /// their dependencies.
+    pub fn new<I, T>(inner: S, oids: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            inner: Some(inner),
+            allowed: oids.into_iter().map(Into::into).collect(),
+            filtered: VecDeque::new(),
+        }
+    }
+
+    fn filter(&mut self, inner: S) {
+        let schedule = inner.collect::<Vec<_>>();
+        let vts = schedule
+            .iter()
+            .filter_map(|x| x.as_ref().ok())
+            .flat_map(|(_, vts)| vts.iter().map(|(vt, _)| vt));
+        let by_filename = vts
+            .clone()
+            .map(|vt| (vt.filename.as_str(), vt))
+            .collect::<HashMap<_, _>>();
+        let mut unprocessed = vts
+            .filter(|vt| self.allowed.contains(&vt.oid))
+            .collect::<Vec<_>>();
+        let mut keep = HashSet::new();
+        while let Some(vt) = unprocessed.pop() {
+            if keep.insert(vt.oid.clone()) {
+                unprocessed.extend(
+                    vt.dependencies
+                        .iter()
+                        .filter_map(|x| by_filename.get(x.as_str()).copied()),
+                );
+            }
+        }
+        self.filtered = schedule
+            .into_iter()
+            .filter_map(|x| match x {
+                Ok((stage, vts)) => {
+                    let vts = vts
+                        .into_iter()
+                        .filter(|(vt, _)| keep.contains(&vt.oid))
+                        .collect::<Vec<_>>();
+                    (!vts.is_empty()).then_some(Ok((stage, vts)))
+                }
+                Err(e) => Some(Err(e)),
+            })
+            .collect();
+    }
+}
+
+impl<S> Iterator for FilteredSchedule<S>
+where
+    S: Iterator<Item = ConcurrentVTResult>,
+{
+    type Item = ConcurrentVTResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(inner) = self.inner.take() {
+            self.filter(inner);
+        }
+        self.filtered.pop_front()
+    }
+}
+
+/// Defines how VTs depending on an excluded VT are handled by an [`ExcludeSchedule`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExcludedDependency {
+    /// VTs depending directly or indirectly on an excluded VT are excluded as well
+    #[default]
+    Skip,
+    /// VTs depending on an excluded VT are run without it
+    Run,
+}
+
+/// Removes the VTs of an OID denylist from a schedule.
+///
+/// What happens with VTs depending on an excluded one is defined by [`ExcludedDependency`].
+/// Each VT skipped because of an excluded dependency is logged with the reason. The order of
+/// the inner schedule is preserved and stages without any remaining VT are skipped.
+///
+/// As dependencies are scheduled before the VTs depending on them, the whole inner schedule
+/// is read on the first call of `next`.
+pub struct ExcludeSchedule<S> {
+    inner: Option<S>,
+    excluded: HashSet<String>,
+    dependent: ExcludedDependency,
+    filtered: VecDeque<ConcurrentVTResult>,
+}
+
+impl<S> ExcludeSchedule<S>
+where
+    S: Iterator<Item = ConcurrentVTResult>,
+{
+    /// Creates a new ExcludeSchedule of the given schedule without the given OIDs.
+    pub fn new<I, T>(inner: S, oids: I, dependent: ExcludedDependency) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            inner: Some(inner),
+            excluded: oids.into_iter().map(Into::into).collect(),
+            dependent,
+            filtered: VecDeque::new(),
+        }
+    }
+
+    fn filter(&mut self, inner: S) {
+        // filenames of excluded VTs, a dependency is always scheduled before its dependents
+        let mut excluded_files = HashSet::new();
+        let mut filtered = VecDeque::new();
+        for x in inner {
+            let (stage, vts) = match x {
+                Ok(x) => x,
+                Err(e) => {
+                    filtered.push_back(Err(e));
+                    continue;
+                }
+            };
+            let vts = vts
+                .into_iter()
+                .filter(|(vt, _)| {
+                    if self.excluded.contains(&vt.oid) {
+                        excluded_files.insert(vt.filename.clone());
+                        return false;
+                    }
+                    if self.dependent == ExcludedDependency::Run {
+                        return true;
+                    }
+                    match vt.dependencies.iter().find(|x| excluded_files.contains(*x)) {
+                        Some(dependency) => {
+                            tracing::info!(
+                                oid = vt.oid,
+                                dependency,
+                                "skipped, the dependency is excluded"
+                            );
+                            excluded_files.insert(vt.filename.clone());
+                            false
+                        }
+                        None => true,
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !vts.is_empty() {
+                filtered.push_back(Ok((stage, vts)));
+            }
+        }
+        self.filtered = filtered;
+    }
+}
+
+impl<S> Iterator for ExcludeSchedule<S>
+where
+    S: Iterator<Item = ConcurrentVTResult>,
+{
+    type Item = ConcurrentVTResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(inner) = self.inner.take() {
+            self.filter(inner);
+        }
+        self.filtered.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scheduling::{
+        ConcurrentVTResult, ExcludeSchedule, ExcludedDependency, FilteredSchedule, Stage,
+    };
+    use crate::storage::item::Nvt;
+
+    /// Schedules the VTs 0, 1 and 2, each depending on the previous one
+    fn schedule() -> impl Iterator<Item = ConcurrentVTResult> {
+        let nvt = |oid: &str, dependencies: &[&str]| Nvt {
+            oid: oid.to_string(),
+            filename: format!("{oid}.nasl"),
+            dependencies: dependencies.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        };
+        [nvt("0", &[]), nvt("1", &["0.nasl"]), nvt("2", &["1.nasl"])]
+            .into_iter()
+            .map(|vt| Ok((Stage::End, vec![(vt, None)])))
+    }
+
+    fn oids(schedule: impl Iterator<Item = ConcurrentVTResult>) -> Vec<String> {
+        schedule
+            .flat_map(|x| x.expect("stage").1)
+            .map(|(vt, _)| vt.oid)
+            .collect()
+    }
+
+    #[test]
+    fn filtered_schedule() {
+        let filtered = |oid: &str| oids(FilteredSchedule::new(schedule(), [oid]));
+        assert_eq!(filtered("0"), ["0"]);
+        // dependencies are kept in their order
+        assert_eq!(filtered("2"), ["0", "1", "2"]);
+        assert!(filtered("unknown").is_empty());
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn exclude_schedule() {
+        let excluded = |dependent| oids(ExcludeSchedule::new(schedule(), ["1"], dependent));
+        // 2 depends on 1 and is therefore skipped as well
+        assert_eq!(excluded(ExcludedDependency::Skip), ["0"]);
+        assert!(logs_contain("skipped, the dependency is excluded"));
+        assert_eq!(excluded(ExcludedDependency::Run), ["0", "2"]);
+    }
+}
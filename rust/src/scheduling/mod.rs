@@ -3,12 +3,14 @@
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
 //! This module contains traits and implementations for scheduling a scan.
+mod cache;
+mod filter;
 mod wave;
 
 use std::{collections::HashMap, fmt::Display};
 
 use crate::{
-    models::{Parameter, Scan},
+    models::{HostExpansionError, Parameter, Scan},
     storage::{
         item::{NVTField, Nvt},
         Field, Retrieve, Retriever, StorageError,
@@ -16,6 +18,8 @@ use crate::{
 };
 use thiserror::Error;
 
+pub use cache::ScheduleCache;
+pub use filter::{ExcludeSchedule, ExcludedDependency, FilteredSchedule};
 pub use wave::WaveExecutionPlan;
 
 /// Error cases for VTFetcher
@@ -33,6 +37,9 @@ pub enum VTError {
     #[error("not found: {0}")]
     /// Not found
     NotFound(#[from] crate::nasl::syntax::LoadError),
+    #[error("invalid target: {0}")]
+    /// The hosts of the target cannot be expanded
+    InvalidTarget(#[from] HostExpansionError),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
@@ -225,15 +232,19 @@ where
         let mut vts = Vec::new();
         let mut unknown_dependencies = Vec::new();
         let mut known_dependencies = HashMap::new();
-        for (i, x) in self
+        for x in self
             .retrieve_by_fields(oids, Retrieve::NVT(None))?
             .filter_map(|(_, f)| match f {
                 Field::NVT(NVTField::Nvt(x)) => Some(x),
                 _ => None,
             })
-            .enumerate()
         {
-            let params: Option<Vec<Parameter>> = scan.vts.get(i).map(|x| x.parameters.clone());
+            // the VTs are not necessarily returned in the order of the scan
+            let params: Option<Vec<Parameter>> = scan
+                .vts
+                .iter()
+                .find(|v| v.oid == x.oid)
+                .map(|x| x.parameters.clone());
             unknown_dependencies.extend(
                 x.dependencies
                     .iter()
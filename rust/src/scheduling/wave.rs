@@ -136,7 +136,12 @@ impl Iterator for WaveExecutionPlan {
 
     fn next(&mut self) -> Option<Self::Item> {
         let results = self.data.pop_front();
-        results.map(|x| Ok(x.into_values().collect::<Vec<_>>()))
+        results.map(|x| {
+            let mut vts = x.into_iter().collect::<Vec<_>>();
+            // sorted by filename so that the same feed always results in the same plan
+            vts.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Ok(vts.into_iter().map(|(_, vt)| vt).collect())
+        })
     }
 }
 
@@ -374,6 +374,40 @@ impl NvtPreference {
     pub fn default(&self) -> &str {
         self.default.as_ref()
     }
+
+    /// Returns true when the value of a scan parameter matches the type of the preference
+    ///
+    /// A checkbox accepts `yes` or `no`, an integer a number within the range of an i64 or a
+    /// list of those (see [`integer_list`]) and a radio one of its choices, which are the default
+    /// value separated by `;`.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self.class {
+            PreferenceType::CheckBox => matches!(value, "yes" | "no"),
+            PreferenceType::Integer => {
+                value.trim().parse::<i64>().is_ok() || integer_list(value).is_some()
+            }
+            PreferenceType::Radio => self.default.split(';').any(|x| x == value),
+            PreferenceType::Entry
+            | PreferenceType::File
+            | PreferenceType::Password
+            | PreferenceType::SshLogin => true,
+        }
+    }
+}
+
+/// Parses a list of integers, e.g. multiple ports, of the value of an integer preference
+///
+/// The list is accepted either as JSON array (e.g. `[22, 80, 443]`) or as comma separated
+/// numbers (e.g. `22, 80, 443`).
+pub fn integer_list(value: &str) -> Option<Vec<i64>> {
+    let trimmed = value.trim();
+    if trimmed.starts_with('[') {
+        return serde_json::from_str(trimmed).ok();
+    }
+    if !trimmed.contains(',') {
+        return None;
+    }
+    trimmed.split(',').map(|x| x.trim().parse().ok()).collect()
 }
 
 impl From<(&str, &str, &str, &str)> for NvtPreference {
@@ -508,6 +542,27 @@ impl Display for Nvt {
 }
 
 impl Nvt {
+    /// Returns the timeout set via `script_timeout`
+    ///
+    /// A timeout of 0 means that the default timeout of the scan is used, in that case None is
+    /// returned.
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        self.preferences
+            .iter()
+            .find(|x| x.id == Some(0) && x.name == "timeout")
+            .and_then(|x| x.default.parse::<u64>().ok())
+            .filter(|x| *x > 0)
+            .map(std::time::Duration::from_secs)
+    }
+
+    /// Returns true when the nvt is marked as deprecated via `script_tag(name: "deprecated")`
+    pub fn is_deprecated(&self) -> bool {
+        matches!(
+            self.tag.get(&TagKey::Deprecated),
+            Some(TagValue::Boolean(true))
+        )
+    }
+
     /// Returns Err with the feed_version if it is a version Ok otherwise
     pub fn set_from_field(&mut self, field: NVTField) -> Result<(), String> {
         match field {
@@ -855,6 +910,9 @@ pub trait ItemDispatcher {
     fn dispatch_advisory(&self, _: &str, _: Option<NotusAdvisory>) -> Result<(), StorageError>;
 }
 
+/// The last KB item dispatched per KB key of a ContextKey
+type KbCache = HashMap<ContextKey, HashMap<String, Kb>>;
+
 /// Collects the information while being in a description run and calls the dispatch method
 /// on exit.
 pub struct PerItemDispatcher<S>
@@ -862,6 +920,7 @@ where
     S: ItemDispatcher,
 {
     nvt: Arc<Mutex<Option<Nvt>>>,
+    kb_cache: Option<Mutex<KbCache>>,
     dispatcher: S,
 }
 
@@ -873,10 +932,52 @@ where
     pub fn new(dispatcher: S) -> Self {
         Self {
             nvt: Arc::new(Mutex::new(None)),
+            kb_cache: None,
             dispatcher,
         }
     }
 
+    /// Skips KB items that are identical to the last one dispatched for the same KB key
+    ///
+    /// This avoids redundant writes when e.g. a service is detected repeatedly. The cache of a
+    /// ContextKey is invalidated when its host finished or its KB items are removed.
+    pub fn with_kb_deduplication(mut self) -> Self {
+        self.kb_cache = Some(Mutex::new(HashMap::new()));
+        self
+    }
+
+    fn store_kb(&self, key: &ContextKey, kb: Kb) -> Result<(), StorageError> {
+        let Some(cache) = &self.kb_cache else {
+            return self.dispatcher.dispatch_kb(key, kb);
+        };
+        // the lock is held while dispatching, so that concurrent dispatches of the same item
+        // cannot both pass the check
+        let mut cache = cache.lock()?;
+        let last = cache.entry(key.clone()).or_default();
+        if last.get(&kb.key) == Some(&kb) {
+            return Ok(());
+        }
+        self.dispatcher.dispatch_kb(key, kb.clone())?;
+        last.insert(kb.key.clone(), kb);
+        Ok(())
+    }
+
+    fn invalidate_kbs(&self, key: &ContextKey) -> Result<(), StorageError> {
+        if let Some(cache) = &self.kb_cache {
+            let mut cache = cache.lock()?;
+            match key {
+                // removes the KB items of all targets of a scan
+                ContextKey::Scan(id, None) => {
+                    cache.retain(|k, _| !matches!(k, ContextKey::Scan(kid, _) if kid == id))
+                }
+                key => {
+                    cache.remove(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn store_nvt_field(&self, f: NVTField) -> Result<(), StorageError> {
         let mut data = Arc::as_ref(&self.nvt)
             .lock()
@@ -918,7 +1019,7 @@ where
     fn dispatch(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
         match scope {
             Field::NVT(nvt) => self.store_nvt_field(nvt),
-            Field::KB(kb) => self.dispatcher.dispatch_kb(key, kb),
+            Field::KB(kb) => self.store_kb(key, kb),
             Field::NotusAdvisory(adv) => self.dispatcher.dispatch_advisory(key.as_ref(), *adv),
             Field::Result(result) => self.dispatch(key, Field::Result(result)),
         }
@@ -927,7 +1028,7 @@ where
     fn dispatch_replace(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
         match scope {
             Field::NVT(nvt) => self.store_nvt_field(nvt),
-            Field::KB(kb) => self.dispatcher.dispatch_kb(key, kb),
+            Field::KB(kb) => self.store_kb(key, kb),
             Field::NotusAdvisory(adv) => self.dispatcher.dispatch_advisory(key.as_ref(), *adv),
             Field::Result(result) => self.dispatch(key, Field::Result(result)),
         }
@@ -942,6 +1043,10 @@ where
         *data = None;
         Ok(())
     }
+
+    fn host_finished(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.invalidate_kbs(key)
+    }
 }
 
 impl<S> Retriever for PerItemDispatcher<S>
@@ -978,6 +1083,7 @@ where
         key: &ContextKey,
         kb_key: Option<String>,
     ) -> Result<Option<Vec<Kb>>, StorageError> {
+        self.invalidate_kbs(key)?;
         self.dispatcher.remove_kb(key, kb_key)
     }
 
@@ -1008,6 +1114,32 @@ mod tests {
         };
     }
 
+    #[test]
+    fn preference_accepts() {
+        use super::{NvtPreference, PreferenceType};
+        let preference = |class, default: &str| NvtPreference {
+            id: Some(1),
+            class,
+            name: "test".to_owned(),
+            default: default.to_owned(),
+        };
+        let radio = preference(PreferenceType::Radio, "first;second");
+        assert!(radio.accepts("second"));
+        assert!(!radio.accepts("third"));
+        let integer = preference(PreferenceType::Integer, "0");
+        assert!(integer.accepts("-42"));
+        assert!(!integer.accepts("forty-two"));
+        assert!(!integer.accepts("99999999999999999999"));
+        assert!(integer.accepts("22, 80, 443"));
+        assert!(integer.accepts("[22, 80, 443]"));
+        assert!(!integer.accepts(r#"["22", 80]"#));
+        assert!(!integer.accepts("22, http"));
+        let checkbox = preference(PreferenceType::CheckBox, "no");
+        assert!(checkbox.accepts("yes"));
+        assert!(!checkbox.accepts("true"));
+        assert!(preference(PreferenceType::Entry, "").accepts("anything"));
+    }
+
     assert_tag_key! {
         affected => Affected,
         creation_date => CreationDate,
@@ -147,6 +147,13 @@ where
                     .filter(move |x| x.key == s)
                     .map(|x| storage::Field::KB(x.clone()))
             }),
+            storage::Retrieve::KBPrefix(s) => Box::new({
+                let kbs = self.kbs.lock().map_err(StorageError::from)?;
+                let kbs = kbs.clone();
+                kbs.into_iter()
+                    .filter(move |x| x.key.starts_with(&s))
+                    .map(storage::Field::KB)
+            }),
         })
     }
 
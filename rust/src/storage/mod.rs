@@ -7,6 +7,8 @@
 pub mod infisto;
 pub mod json;
 pub mod redis;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 pub mod item;
 mod retrieve;
@@ -17,15 +19,17 @@ pub use retrieve::*;
 
 use item::NVTField;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
     io,
+    ops::Bound,
     sync::{Arc, PoisonError, RwLock},
 };
 use thiserror::Error;
 use types::Primitive;
 
 use crate::models::{self, VulnerabilityData};
+use crate::scheduling::Stage;
 
 /// The identifier of a Scan
 ///
@@ -192,6 +196,14 @@ pub enum StorageError {
     #[error("Not found: {0}")]
     /// Not found variant
     NotFound(String),
+    /// A KB value is larger than the caller is willing to read.
+    #[error("Value of {key} exceeds the limit of {max} bytes")]
+    ValueTooLarge {
+        /// The key of the KB item
+        key: String,
+        /// The limit in bytes
+        max: usize,
+    },
 }
 
 impl<S> From<PoisonError<S>> for StorageError {
@@ -255,6 +267,29 @@ pub trait Dispatcher: Sync + Send {
     /// Some database require a cleanup therefore this method is called when a script finishes.
     fn on_exit(&self, key: &ContextKey) -> Result<(), StorageError>;
 
+    /// Is called before the first script of a host is run.
+    ///
+    /// The key contains the scan id as well as the host. It allows a storage to e.g. record the
+    /// start time or allocate state per host. Each call is followed by a `host_finished` call
+    /// with the same key.
+    fn host_started(&self, _key: &ContextKey) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Is called after the last script of a host finished.
+    fn host_finished(&self, _key: &ContextKey) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Is called after the last script of a set of concurrent scripts finished on a host.
+    ///
+    /// It allows a storage to e.g. flush the results of a host incrementally. A stage that is
+    /// split into multiple sets because of dependencies finishes once per set. The last set of
+    /// a host finishes before `host_finished` is called.
+    fn stage_finished(&self, _key: &ContextKey, _stage: Stage) -> Result<(), StorageError> {
+        Ok(())
+    }
+
     /// Retries a dispatch for the amount of retries when a retrievable error occurs.
     fn retry_dispatch(
         &self,
@@ -319,6 +354,18 @@ where
         self.as_ref().on_exit(key)
     }
 
+    fn host_started(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.as_ref().host_started(key)
+    }
+
+    fn host_finished(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.as_ref().host_finished(key)
+    }
+
+    fn stage_finished(&self, key: &ContextKey, stage: Stage) -> Result<(), StorageError> {
+        self.as_ref().stage_finished(key, stage)
+    }
+
     fn retry_dispatch(
         &self,
         retries: usize,
@@ -360,6 +407,15 @@ where
         self.as_ref().retrieve(key, scope)
     }
 
+    fn retrieve_kb(
+        &self,
+        key: &ContextKey,
+        scope: Retrieve,
+        max_size: Option<usize>,
+    ) -> Result<Vec<Kb>, StorageError> {
+        self.as_ref().retrieve_kb(key, scope, max_size)
+    }
+
     fn retrieve_by_field(&self, field: Field, scope: Retrieve) -> FieldKeyResult {
         self.as_ref().retrieve_by_field(field, scope)
     }
@@ -410,8 +466,9 @@ where
 /// Kbs are bound to a scan_id and a kb_key.
 ///
 /// To make lookups easier KB items are fetched by a scan_id, followed by the kb key this should
-/// make required_key verifications relatively simple.
-type Kbs = HashMap<ContextKey, HashMap<String, Vec<Kb>>>;
+/// make required_key verifications relatively simple. The kb keys are sorted, so that a prefix
+/// can be looked up as range.
+type Kbs = HashMap<ContextKey, BTreeMap<String, Vec<Kb>>>;
 
 /// Vts are using a relative file path as a key. This should make includes, script_dependency
 /// lookups relative simple.
@@ -489,7 +546,7 @@ impl DefaultDispatcher {
                 scan_entry.insert(kb.key.clone(), vec![kb]);
             }
         } else {
-            let mut scan_entry = HashMap::new();
+            let mut scan_entry = BTreeMap::new();
             scan_entry.insert(kb.key.clone(), vec![kb]);
             data.insert(ck, scan_entry);
         }
@@ -505,7 +562,7 @@ impl DefaultDispatcher {
                 scan_entry.insert(kb.key.clone(), vec![kb]);
             }
         } else {
-            let mut scan_entry = HashMap::new();
+            let mut scan_entry = BTreeMap::new();
             scan_entry.insert(kb.key.clone(), vec![kb]);
             data.insert(ck.clone(), scan_entry);
         }
@@ -698,6 +755,19 @@ impl Retriever for DefaultDispatcher {
                 }
                 Ok(Box::new(vec![].into_iter()))
             }
+            Retrieve::KBPrefix(prefix) => {
+                let kbs = self.kbs.as_ref().read()?;
+                let kbs = kbs
+                    .get(key)
+                    .into_iter()
+                    .flat_map(|x| {
+                        x.range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+                    })
+                    .take_while(|(k, _)| k.starts_with(&prefix))
+                    .flat_map(|(_, x)| x.iter().cloned().map(Field::KB))
+                    .collect::<Vec<_>>();
+                Ok(Box::new(kbs.into_iter()))
+            }
             Retrieve::NotusAdvisory(x) => {
                 let data = self.advisories.as_ref().read()?.clone();
                 match x {
@@ -801,7 +871,7 @@ impl Retriever for DefaultDispatcher {
                 tracing::warn!(kb=?x, "currently it is assumed that notus advisories are handled as vt, please use Retrieve::NVT for now.");
                 Ok(Box::new(vec![].into_iter()))
             }
-            Retrieve::KB(x) => {
+            Retrieve::KB(x) | Retrieve::KBPrefix(x) => {
                 // are there use cases to get a KB outside of a scan?
                 tracing::warn!(
                     kb = x,
@@ -828,6 +898,66 @@ mod tests {
     use super::NVTField::*;
     use super::*;
 
+    #[test]
+    fn kb_prefix() -> Result<(), StorageError> {
+        let storage = DefaultDispatcher::default();
+        let key = ContextKey::Scan("sid".into(), Some("test.host".into()));
+        for kb_key in [
+            "Ports/tcp/80",
+            "Ports/udp/161",
+            "Ports/tcp",
+            "Ports/tcp/22",
+            "Host/os",
+        ] {
+            storage.dispatch(&key, Field::KB((kb_key, 1).into()))?;
+        }
+        let kbs = storage
+            .retrieve(&key, Retrieve::KBPrefix("Ports/tcp/".into()))?
+            .map(|x| match x {
+                Field::KB(kb) => kb.key,
+                x => panic!("unexpected field {x:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(kbs, ["Ports/tcp/22", "Ports/tcp/80"]);
+        let other = ContextKey::Scan("sid".into(), Some("other.host".into()));
+        assert_eq!(
+            storage
+                .retrieve(&other, Retrieve::KBPrefix("Ports/".into()))?
+                .count(),
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn dump_kb() -> Result<(), StorageError> {
+        let storage = DefaultDispatcher::default();
+        let key = ContextKey::Scan("sid".into(), Some("test.host".into()));
+        let other = ContextKey::Scan("sid".into(), Some("other.host".into()));
+        storage.dispatch(&key, Field::KB(("Host/os", "linux").into()))?;
+        storage.dispatch(&key, Field::KB(("Ports/tcp/22", 1).into()))?;
+        storage.dispatch(&key, Field::KB(("Ports/tcp/80", 1).into()))?;
+        storage.dispatch(&key, Field::KB(("Ports/tcp/80", 2).into()))?;
+        storage.dispatch(&key, Field::KB(("internal/host_finished", 1).into()))?;
+        storage.dispatch(&other, Field::KB(("Ports/tcp/443", 1).into()))?;
+        let kbs = storage
+            .dump_kb(&key)?
+            .into_iter()
+            .map(|kb| (kb.key, kb.value.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kbs,
+            [
+                ("Host/os".to_owned(), "linux".to_owned()),
+                ("Ports/tcp/22".to_owned(), "1".to_owned()),
+                ("Ports/tcp/80".to_owned(), "1".to_owned()),
+                ("Ports/tcp/80".to_owned(), "2".to_owned()),
+            ]
+        );
+        assert_eq!(storage.dump_kb(&other)?.len(), 1);
+        Ok(())
+    }
+
     #[test]
     pub fn default_storage() -> Result<(), StorageError> {
         let storage = DefaultDispatcher::default();
@@ -3,12 +3,15 @@
 // SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
 
 use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use std::time::Duration;
 
 use super::dberror::DbError;
 use super::dberror::RedisStorageResult;
@@ -188,7 +191,74 @@ pub trait RedisWrapper {
     fn lindex(&mut self, key: &str, index: isize) -> RedisStorageResult<String>;
     fn lrange(&mut self, key: &str, start: isize, end: isize) -> RedisStorageResult<Vec<String>>;
     fn keys(&mut self, pattern: &str) -> RedisStorageResult<Vec<String>>;
+    /// Returns the keys matching the pattern.
+    ///
+    /// Unlike `keys` the keyspace is iterated incrementally via `SCAN`, so
+    /// that redis is not blocked while a large database is searched.
+    fn scan_keys(&mut self, pattern: &str) -> RedisStorageResult<Vec<String>>;
     fn pop(&mut self, pattern: &str) -> RedisStorageResult<Vec<String>>;
+    fn expire(&mut self, key: &str, ttl: Duration) -> RedisStorageResult<()>;
+
+    /// Pushes the values of all items to their keys in the given order.
+    ///
+    /// When a push fails a `DbError::BatchFailed` containing the position of
+    /// the failed item is returned.
+    fn lpush_many(&mut self, items: &[(String, Vec<Vec<u8>>)]) -> RedisStorageResult<()> {
+        for (index, (key, values)) in items.iter().enumerate() {
+            self.lpush(key, values).map_err(|e| DbError::BatchFailed {
+                index,
+                key: key.clone(),
+                reason: e.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Escapes the glob characters of `prefix` and returns a pattern matching all
+/// keys starting with it.
+pub fn prefix_pattern(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('*');
+    pattern
+}
+
+/// Pushes all items within a single transaction to avoid a round-trip per item.
+///
+/// As redis does not report which command of a transaction failed, the type of
+/// each key is checked afterwards to find the item that could not be pushed
+/// when a key holds another type than a list.
+fn lpush_pipelined<C: ConnectionLike>(
+    con: &mut C,
+    items: &[(String, Vec<Vec<u8>>)],
+) -> RedisStorageResult<()> {
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for (key, values) in items {
+        pipe.lpush(key, values).ignore();
+    }
+    let err = match pipe.query::<()>(con) {
+        Ok(()) => return Ok(()),
+        Err(err) if err.code() == Some("WRONGTYPE") => err,
+        Err(err) => return Err(err.into()),
+    };
+    for (index, (key, _)) in items.iter().enumerate() {
+        let kind: String = Cmd::new().arg("TYPE").arg(key).query(con)?;
+        if kind != "list" {
+            return Err(DbError::BatchFailed {
+                index,
+                key: key.clone(),
+                reason: err.to_string(),
+            });
+        }
+    }
+    Err(err.into())
 }
 
 impl RedisWrapper for RedisCtx {
@@ -255,6 +325,31 @@ impl RedisWrapper for RedisCtx {
         Ok(ret)
     }
 
+    fn scan_keys(&mut self, pattern: &str) -> RedisStorageResult<Vec<String>> {
+        let ret = self
+            .kb
+            .as_mut()
+            .expect("Valid redis connection")
+            .scan_match(pattern)?
+            .collect();
+        Ok(ret)
+    }
+
+    ///Wrapper function to avoid accessing kb member directly.
+    #[inline(always)]
+    fn expire(&mut self, key: &str, ttl: Duration) -> RedisStorageResult<()> {
+        Cmd::new()
+            .arg("PEXPIRE")
+            .arg(key)
+            .arg(ttl.as_millis() as u64)
+            .query::<()>(self.kb.as_mut().expect("Valid redis connection"))?;
+        Ok(())
+    }
+
+    fn lpush_many(&mut self, items: &[(String, Vec<Vec<u8>>)]) -> RedisStorageResult<()> {
+        lpush_pipelined(self.kb.as_mut().expect("Valid redis connection"), items)
+    }
+
     fn pop(&mut self, key: &str) -> RedisStorageResult<Vec<String>> {
         let ret: (Vec<String>,) = redis::pipe()
             .cmd("LRANGE")
@@ -579,9 +674,83 @@ pub trait RedisAddNvt: RedisWrapper {
 
 impl RedisAddNvt for RedisCtx {}
 
+/// TLS settings for `rediss://` connections.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedisTlsConfig {
+    /// CA certificate in PEM format to verify the server with. The system roots are used when not set.
+    pub ca_cert: Option<PathBuf>,
+    /// Client certificate in PEM format for mutual TLS
+    pub client_cert: Option<PathBuf>,
+    /// Private key in PEM format belonging to the client certificate
+    pub client_key: Option<PathBuf>,
+}
+
+/// Describes how to connect to redis.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedisConfig {
+    /// Complete url including the used protocol e.g.: `"unix:///run/redis/redis-server.sock"`.
+    ///
+    /// Urls with the scheme `rediss://` are connected via TLS.
+    pub url: String,
+    /// TLS settings, only used for `rediss://` urls
+    pub tls: RedisTlsConfig,
+}
+
+impl From<&str> for RedisConfig {
+    fn from(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            tls: RedisTlsConfig::default(),
+        }
+    }
+}
+
+impl RedisConfig {
+    fn is_tls(&self) -> bool {
+        self.url.starts_with("rediss://")
+    }
+
+    fn client_tls(&self) -> RedisStorageResult<Option<ClientTlsConfig>> {
+        match (&self.tls.client_cert, &self.tls.client_key) {
+            (Some(cert), Some(key)) => Ok(Some(ClientTlsConfig {
+                client_cert: read_pem(cert)?,
+                client_key: read_pem(key)?,
+            })),
+            (None, None) => Ok(None),
+            _ => Err(DbError::ConfigurationError(
+                "client certificate and client key must be set together".to_string(),
+            )),
+        }
+    }
+
+    /// Creates a client, using TLS when the url scheme is `rediss://`.
+    pub fn client(&self) -> RedisStorageResult<Client> {
+        if !self.is_tls() {
+            return Ok(Client::open(self.url.as_str())?);
+        }
+        let certificates = TlsCertificates {
+            client_tls: self.client_tls()?,
+            root_cert: self.tls.ca_cert.as_ref().map(read_pem).transpose()?,
+        };
+        Ok(Client::build_with_tls(self.url.as_str(), certificates)?)
+    }
+}
+
+fn read_pem(path: &PathBuf) -> RedisStorageResult<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| DbError::ConfigurationError(format!("unable to read {}: {e}", path.display())))
+}
+
 impl RedisCtx {
     pub fn open(address: &str, selector: &[NameSpaceSelector]) -> RedisStorageResult<Self> {
-        let client = redis::Client::open(address)?;
+        Self::open_with_config(&RedisConfig::from(address), selector)
+    }
+
+    pub fn open_with_config(
+        config: &RedisConfig,
+        selector: &[NameSpaceSelector],
+    ) -> RedisStorageResult<Self> {
+        let client = config.client()?;
 
         let mut kb = client.get_connection()?;
         for s in selector {
@@ -662,7 +831,8 @@ where
     R: RedisWrapper + RedisAddNvt + RedisAddAdvisory + RedisGetNvt,
 {
     cache: Arc<Mutex<R>>,
-    kbs: Arc<Mutex<Vec<Kb>>>,
+    /// KB items are kept per ContextKey so that scans against the same host don't share them
+    kbs: Arc<Mutex<HashMap<ContextKey, Vec<Kb>>>>,
 }
 
 impl<R: RedisWrapper + RedisAddNvt + RedisAddAdvisory + RedisGetNvt> CacheDispatcher<R> {
@@ -686,7 +856,7 @@ impl CacheDispatcher<RedisCtx> {
 
         Ok(CacheDispatcher {
             cache: Arc::new(Mutex::new(rctx)),
-            kbs: Arc::new(Mutex::new(Vec::new())),
+            kbs: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
@@ -729,9 +899,9 @@ where
         cache.rpush(CACHE_KEY, &[&version]).map_err(|e| e.into())
     }
 
-    fn dispatch_kb(&self, _: &ContextKey, kb: Kb) -> Result<(), StorageError> {
+    fn dispatch_kb(&self, key: &ContextKey, kb: Kb) -> Result<(), StorageError> {
         let mut kbs = self.kbs.lock().map_err(StorageError::from)?;
-        kbs.push(kb);
+        kbs.entry(key.clone()).or_default().push(kb);
         Ok(())
     }
     fn dispatch_advisory(&self, key: &str, adv: Option<NotusAdvisory>) -> Result<(), StorageError> {
@@ -746,7 +916,7 @@ where
 {
     fn retrieve(
         &self,
-        _: &ContextKey,
+        key: &ContextKey,
         scope: Retrieve,
     ) -> Result<Box<dyn Iterator<Item = Field>>, StorageError> {
         Ok(match scope {
@@ -755,10 +925,15 @@ where
             }
             Retrieve::KB(s) => Box::new({
                 let kbs = self.kbs.lock().map_err(StorageError::from)?;
-                let kbs = kbs.clone();
+                let kbs = kbs.get(key).cloned().unwrap_or_default();
+                kbs.into_iter().filter(move |x| x.key == s).map(Field::KB)
+            }),
+            Retrieve::KBPrefix(s) => Box::new({
+                let kbs = self.kbs.lock().map_err(StorageError::from)?;
+                let kbs = kbs.get(key).cloned().unwrap_or_default();
                 kbs.into_iter()
-                    .filter(move |x| x.key == s)
-                    .map(move |x| Field::KB(x.clone()))
+                    .filter(move |x| x.key.starts_with(&s))
+                    .map(Field::KB)
             }),
         })
     }
@@ -780,12 +955,42 @@ impl<S> storage::Remover for CacheDispatcher<S>
 where
     S: RedisWrapper + RedisAddNvt + RedisAddAdvisory + RedisGetNvt + Send,
 {
+    /// Removes KB items of a ContextKey.
+    ///
+    /// A `ContextKey::Scan` without a target removes the KB items of all
+    /// targets of that scan, which is used to clean up on `scan_finished`.
     fn remove_kb(
         &self,
-        _key: &ContextKey,
-        _kb_key: Option<String>,
+        key: &ContextKey,
+        kb_key: Option<String>,
     ) -> Result<Option<Vec<Kb>>, StorageError> {
-        unimplemented!()
+        let mut kbs = self.kbs.lock().map_err(StorageError::from)?;
+        let keys: Vec<ContextKey> = match key {
+            ContextKey::Scan(id, None) => kbs
+                .keys()
+                .filter(|k| matches!(k, ContextKey::Scan(kid, _) if kid == id))
+                .cloned()
+                .collect(),
+            _ => vec![key.clone()],
+        };
+        let mut removed = Vec::new();
+        for k in keys {
+            match &kb_key {
+                None => removed.extend(kbs.remove(&k).unwrap_or_default()),
+                Some(kb_key) => {
+                    if let Some(items) = kbs.get_mut(&k) {
+                        let (matching, rest) = items.drain(..).partition(|x| &x.key == kb_key);
+                        *items = rest;
+                        removed.extend::<Vec<Kb>>(matching);
+                    }
+                }
+            }
+        }
+        Ok(if removed.is_empty() {
+            None
+        } else {
+            Some(removed)
+        })
     }
 
     fn remove_result(
@@ -799,16 +1004,20 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::sync::mpsc::{self, Sender, TryRecvError};
     use std::sync::{Arc, Mutex};
 
-    use super::super::dberror::RedisStorageResult;
-    use super::{CacheDispatcher, RedisAddAdvisory, RedisAddNvt, RedisGetNvt, RedisWrapper};
+    use super::super::dberror::{DbError, RedisStorageResult};
+    use super::{
+        lpush_pipelined, prefix_pattern, CacheDispatcher, NameSpaceSelector, RedisAddAdvisory,
+        RedisAddNvt, RedisConfig, RedisCtx, RedisGetNvt, RedisTlsConfig, RedisWrapper,
+    };
     use crate::storage::item::NVTField::*;
     use crate::storage::item::PerItemDispatcher;
     use crate::storage::item::{NvtPreference, NvtRef, PreferenceType, TagKey, TagValue, ACT};
-    use crate::storage::Field::NVT;
-    use crate::storage::{ContextKey, Dispatcher};
+    use crate::storage::Field::{self, NVT};
+    use crate::storage::{ContextKey, Dispatcher, Retrieve, Retriever, Storage};
 
     #[derive(Clone)]
     struct FakeRedis {
@@ -839,10 +1048,17 @@ mod tests {
         fn keys(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
             Ok(Vec::new())
         }
+        fn scan_keys(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
+            Ok(Vec::new())
+        }
         fn pop(&mut self, _: &str) -> RedisStorageResult<Vec<String>> {
             Ok(Vec::new())
         }
 
+        fn expire(&mut self, _: &str, _: std::time::Duration) -> RedisStorageResult<()> {
+            Ok(())
+        }
+
         fn lrange(&mut self, _: &str, _: isize, _: isize) -> RedisStorageResult<Vec<String>> {
             Ok(Vec::new())
         }
@@ -852,6 +1068,147 @@ mod tests {
     impl RedisAddAdvisory for FakeRedis {}
     impl RedisGetNvt for FakeRedis {}
 
+    #[test]
+    fn kb_items_are_scoped_per_scan() {
+        let (sender, _rx) = mpsc::channel();
+        let dispatcher = PerItemDispatcher::new(CacheDispatcher {
+            cache: Arc::new(Mutex::new(FakeRedis { sender })),
+            kbs: Arc::new(Mutex::new(HashMap::new())),
+        });
+        let scan_a = ContextKey::Scan("a".into(), Some("127.0.0.1".into()));
+        let scan_b = ContextKey::Scan("b".into(), Some("127.0.0.1".into()));
+        dispatcher
+            .dispatch(&scan_a, Field::KB(("Ports/tcp/20", 1).into()))
+            .unwrap();
+        dispatcher
+            .dispatch(&scan_b, Field::KB(("Ports/tcp/22", 1).into()))
+            .unwrap();
+
+        let kbs = |key: &ContextKey, kb_key: &str| {
+            dispatcher
+                .retrieve(key, Retrieve::KB(kb_key.to_string()))
+                .unwrap()
+                .count()
+        };
+        assert_eq!(kbs(&scan_a, "Ports/tcp/20"), 1);
+        assert_eq!(kbs(&scan_a, "Ports/tcp/22"), 0);
+        let prefixed = dispatcher
+            .retrieve(&scan_a, Retrieve::KBPrefix("Ports/tcp/".into()))
+            .unwrap()
+            .count();
+        assert_eq!(prefixed, 1);
+        assert_eq!(kbs(&scan_b, "Ports/tcp/20"), 0);
+        assert_eq!(kbs(&scan_b, "Ports/tcp/22"), 1);
+
+        dispatcher
+            .scan_finished(&ContextKey::Scan("a".into(), None))
+            .unwrap();
+        assert_eq!(kbs(&scan_a, "Ports/tcp/20"), 0);
+        assert_eq!(kbs(&scan_b, "Ports/tcp/22"), 1);
+    }
+
+    #[test]
+    fn kb_deduplication() {
+        let (sender, _rx) = mpsc::channel();
+        let dispatcher = PerItemDispatcher::new(CacheDispatcher {
+            cache: Arc::new(Mutex::new(FakeRedis { sender })),
+            kbs: Arc::new(Mutex::new(HashMap::new())),
+        })
+        .with_kb_deduplication();
+        let key = ContextKey::Scan("a".into(), Some("127.0.0.1".into()));
+        let dispatch = |value: i64| {
+            dispatcher
+                .dispatch(&key, Field::KB(("Services/www", value).into()))
+                .unwrap()
+        };
+        let kbs = || {
+            dispatcher
+                .retrieve(&key, Retrieve::KB("Services/www".to_string()))
+                .unwrap()
+                .count()
+        };
+        dispatch(80);
+        dispatch(80);
+        assert_eq!(kbs(), 1);
+        dispatch(443);
+        assert_eq!(kbs(), 2);
+
+        // the removed items must be stored again
+        dispatcher
+            .scan_finished(&ContextKey::Scan("a".into(), None))
+            .unwrap();
+        dispatch(443);
+        assert_eq!(kbs(), 1);
+    }
+
+    #[test]
+    fn escapes_prefix_pattern() {
+        assert_eq!(prefix_pattern("Ports/tcp/"), "Ports/tcp/*");
+        assert_eq!(prefix_pattern("a*b?[c]\\"), "a\\*b\\?\\[c\\]\\\\*");
+        assert_eq!(prefix_pattern(""), "*");
+    }
+
+    #[test]
+    fn plain_url_ignores_tls_config() {
+        let config = RedisConfig {
+            url: "redis://localhost:6379".to_string(),
+            tls: RedisTlsConfig {
+                ca_cert: Some("/does/not/exist.pem".into()),
+                ..Default::default()
+            },
+        };
+        assert!(config.client().is_ok());
+    }
+
+    #[test]
+    fn tls_requires_client_cert_and_key() {
+        let config = RedisConfig {
+            url: "rediss://localhost:6379".to_string(),
+            tls: RedisTlsConfig {
+                client_cert: Some("client.pem".into()),
+                ..Default::default()
+            },
+        };
+        assert!(matches!(
+            config.client(),
+            Err(DbError::ConfigurationError(_))
+        ));
+    }
+
+    #[test]
+    fn tls_reports_missing_ca() {
+        let config = RedisConfig {
+            url: "rediss://localhost:6379".to_string(),
+            tls: RedisTlsConfig {
+                ca_cert: Some("/does/not/exist.pem".into()),
+                ..Default::default()
+            },
+        };
+        assert!(matches!(
+            config.client(),
+            Err(DbError::ConfigurationError(_))
+        ));
+    }
+
+    /// Requires a TLS enabled redis, e.g.:
+    /// `REDIS_TLS_URL=rediss://localhost:6380 REDIS_TLS_CA=ca.pem cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn tls_round_trip() {
+        let config = RedisConfig {
+            url: std::env::var("REDIS_TLS_URL").expect("REDIS_TLS_URL must be set"),
+            tls: RedisTlsConfig {
+                ca_cert: std::env::var("REDIS_TLS_CA").ok().map(Into::into),
+                client_cert: std::env::var("REDIS_TLS_CERT").ok().map(Into::into),
+                client_key: std::env::var("REDIS_TLS_KEY").ok().map(Into::into),
+            },
+        };
+        let mut ctx = RedisCtx::open_with_config(&config, &[NameSpaceSelector::Free]).unwrap();
+        ctx.set_value("tls/test", "value").unwrap();
+        assert_eq!(ctx.value("tls/test").unwrap(), "value");
+        ctx.delete_namespace().unwrap();
+    }
+
     #[test]
     fn transform_nvt() {
         let commands = [
@@ -900,7 +1257,7 @@ mod tests {
         let (sender, rx) = mpsc::channel();
         let fr = FakeRedis { sender };
         let cache = Arc::new(Mutex::new(fr));
-        let kbs = Arc::new(Mutex::new(Vec::new()));
+        let kbs = Arc::new(Mutex::new(HashMap::new()));
         let rcache = CacheDispatcher { cache, kbs };
         let dispatcher = PerItemDispatcher::new(rcache);
         let key = ContextKey::FileName("test.nasl".to_string());
@@ -947,4 +1304,101 @@ mod tests {
         }
         assert_eq!(results, 4);
     }
+
+    /// Counts the round-trips to redis and stores the send commands
+    #[derive(Default)]
+    struct CountingConnection {
+        round_trips: usize,
+        commands: Vec<u8>,
+        wrong_type: Option<&'static str>,
+        error: Option<&'static [u8]>,
+    }
+
+    impl redis::ConnectionLike for CountingConnection {
+        fn req_packed_command(&mut self, cmd: &[u8]) -> redis::RedisResult<redis::Value> {
+            self.round_trips += 1;
+            let cmd = String::from_utf8_lossy(cmd);
+            match self.wrong_type {
+                Some(key) if cmd.contains(key) => Ok(redis::Value::Status("string".into())),
+                _ => Ok(redis::Value::Status("list".into())),
+            }
+        }
+
+        fn req_packed_commands(
+            &mut self,
+            cmd: &[u8],
+            offset: usize,
+            _: usize,
+        ) -> redis::RedisResult<Vec<redis::Value>> {
+            self.round_trips += 1;
+            self.commands.extend_from_slice(cmd);
+            if let Some(error) = self.error {
+                redis::parse_redis_value(error)?;
+            }
+            // the reply of EXEC, the replies of MULTI and the queued commands are skipped
+            Ok(vec![redis::Value::Bulk(vec![
+                redis::Value::Int(1);
+                offset - 1
+            ])])
+        }
+
+        fn get_db(&self) -> i64 {
+            0
+        }
+
+        fn check_connection(&mut self) -> bool {
+            true
+        }
+
+        fn is_open(&self) -> bool {
+            true
+        }
+    }
+
+    const WRONGTYPE: &[u8] =
+        b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+
+    fn batch(n: usize) -> Vec<(String, Vec<Vec<u8>>)> {
+        (0..n)
+            .map(|i| (format!("key/{i}"), vec![i.to_string().into_bytes()]))
+            .collect()
+    }
+
+    #[test]
+    fn lpush_many_uses_a_single_pipeline() {
+        let mut con = CountingConnection::default();
+        let items = batch(100);
+        lpush_pipelined(&mut con, &items).unwrap();
+        assert_eq!(con.round_trips, 1);
+        let commands = String::from_utf8(con.commands).unwrap();
+        assert!(commands.starts_with("*1\r\n$5\r\nMULTI\r\n"));
+        let positions: Vec<_> = items
+            .iter()
+            .map(|(key, _)| commands.find(&format!("{key}\r\n")).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|x| x[0] < x[1]));
+    }
+
+    #[test]
+    fn lpush_many_reports_failed_item() {
+        let mut con = CountingConnection {
+            wrong_type: Some("key/2\r\n"),
+            error: Some(WRONGTYPE),
+            ..Default::default()
+        };
+        let result = lpush_pipelined(&mut con, &batch(5));
+        assert!(matches!(result, Err(DbError::BatchFailed { index: 2, .. })));
+    }
+
+    #[test]
+    fn lpush_many_checks_types_only_on_wrongtype() {
+        let mut con = CountingConnection {
+            error: Some(b"-ERR max number of clients reached\r\n"),
+            ..Default::default()
+        };
+        let result = lpush_pipelined(&mut con, &batch(5));
+        assert!(result.is_err());
+        assert!(!matches!(result, Err(DbError::BatchFailed { .. })));
+        assert_eq!(con.round_trips, 1);
+    }
 }
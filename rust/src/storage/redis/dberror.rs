@@ -35,6 +35,13 @@ pub enum DbError {
     /// Cannot find a DB to use; redis must be cleaned up to free available slots.
     #[error("No DB available.")]
     NoAvailDbErr,
+    /// A write within a batch failed
+    #[error("Batch write failed at item {index} ({key}): {reason}")]
+    BatchFailed {
+        index: usize,
+        key: String,
+        reason: String,
+    },
 }
 
 impl From<RedisError> for DbError {
@@ -70,7 +77,8 @@ impl From<DbError> for StorageError {
             | DbError::ConfigurationError(_)
             | DbError::PoisonedLock(_)
             | DbError::IoError(_)
-            | DbError::NoAvailDbErr => StorageError::Dirty(err.to_string()),
+            | DbError::NoAvailDbErr
+            | DbError::BatchFailed { .. } => StorageError::Dirty(err.to_string()),
             DbError::ConnectionLost(_) => StorageError::ConnectionLost(err.to_string()),
             DbError::Retry(_) => StorageError::Retry(err.to_string()),
             DbError::LibraryError(_) => StorageError::UnexpectedData(err.to_string()),
@@ -13,6 +13,7 @@ pub use connector::NameSpaceSelector;
 pub use connector::FEEDUPDATE_SELECTOR;
 pub use connector::NOTUSUPDATE_SELECTOR;
 pub use connector::{
-    CacheDispatcher, RedisAddAdvisory, RedisAddNvt, RedisCtx, RedisGetNvt, RedisWrapper,
+    prefix_pattern, CacheDispatcher, RedisAddAdvisory, RedisAddNvt, RedisConfig, RedisCtx,
+    RedisGetNvt, RedisTlsConfig, RedisWrapper,
 };
 pub use dberror::{DbError, RedisStorageResult};
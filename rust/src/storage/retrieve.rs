@@ -6,10 +6,13 @@ use crate::{
     models,
     storage::{
         item::{NVTField, NVTKey, Nvt},
-        ContextKey, Field, StorageError,
+        ContextKey, Field, Kb, StorageError,
     },
 };
 
+/// Prefix of KB items used by the scanner itself, e.g. to mark finished hosts.
+pub const INTERNAL_KB_PREFIX: &str = "internal/";
+
 /// Retrieve command for a given Field
 ///
 /// Defines what kind of information needs to be gathered.
@@ -19,6 +22,10 @@ pub enum Retrieve {
     NVT(Option<NVTKey>),
     /// Knowledge Base item
     KB(String),
+    /// Knowledge Base items whose key starts with the given prefix
+    ///
+    /// E.g. `Ports/tcp/` returns the state of all known TCP ports.
+    KBPrefix(String),
     /// Metadata of the Notus advisory
     NotusAdvisory(Option<String>),
     /// Result
@@ -32,7 +39,7 @@ impl Retrieve {
     pub fn scope(&self) -> &str {
         match self {
             Retrieve::NVT(_) => "nvt",
-            Retrieve::KB(_) => "kb",
+            Retrieve::KB(_) | Retrieve::KBPrefix(_) => "kb",
             Retrieve::NotusAdvisory(_) => "notus",
             Retrieve::Result(_) => "result",
         }
@@ -85,6 +92,14 @@ impl Retrieve {
                 }
             }
 
+            Retrieve::KBPrefix(s) => {
+                if let Field::KB(kb) = field {
+                    kb.key.starts_with(s)
+                } else {
+                    false
+                }
+            }
+
             Retrieve::NotusAdvisory(_) => matches!(field, Field::NotusAdvisory(_)),
             Retrieve::Result(None) => matches!(field, Field::Result(_)),
             Retrieve::Result(Some(id)) => {
@@ -163,6 +178,46 @@ pub trait Retriever: Send + Sync {
         ))
     }
 
+    /// Returns all KB items stored under the given scan or host key
+    ///
+    /// This is meant for debugging purposes, e.g. to inspect what a script has set.
+    /// Items of the scanner itself, see [`INTERNAL_KB_PREFIX`], are left out.
+    fn dump_kb(&self, key: &ContextKey) -> Result<Vec<Kb>, StorageError> {
+        Ok(self
+            .retrieve(key, Retrieve::KBPrefix(String::new()))?
+            .filter_map(|x| match x {
+                Field::KB(kb) if !kb.key.starts_with(INTERNAL_KB_PREFIX) => Some(kb),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Returns the KB items of a KB or KBPrefix scope
+    ///
+    /// When a value is larger than `max_size` bytes, see
+    /// [`Primitive::size`](crate::storage::types::Primitive::size), it fails with
+    /// [`StorageError::ValueTooLarge`]. The default implementation can only check this after
+    /// reading the items, storages that know the size beforehand should override it.
+    fn retrieve_kb(
+        &self,
+        key: &ContextKey,
+        scope: Retrieve,
+        max_size: Option<usize>,
+    ) -> Result<Vec<Kb>, StorageError> {
+        self.retrieve(key, scope)?
+            .filter_map(|x| match x {
+                Field::KB(kb) => Some(kb),
+                _ => None,
+            })
+            .map(|kb| match max_size {
+                Some(max) if kb.value.size() > max => {
+                    Err(StorageError::ValueTooLarge { key: kb.key, max })
+                }
+                _ => Ok(kb),
+            })
+            .collect()
+    }
+
     /// Returns result with the given id
     fn result(&self, key: &ContextKey, id: usize) -> Result<Option<models::Result>, StorageError> {
         Ok(self
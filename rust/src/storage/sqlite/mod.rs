@@ -0,0 +1,490 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+
+#![doc = include_str!("README.md")]
+
+use std::{path::Path, sync::Mutex};
+
+use rusqlite::{params, types::Value, Connection, ErrorCode, OptionalExtension};
+
+use crate::models;
+use crate::scheduling::Stage;
+use crate::storage::{
+    types::Primitive, ContextKey, DefaultDispatcher, Dispatcher, Field, FieldKeyResult, Kb,
+    Remover, Retrieve, Retriever, StorageError,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS kb (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    context TEXT NOT NULL,
+    name TEXT NOT NULL,
+    host TEXT,
+    key TEXT NOT NULL,
+    type TEXT NOT NULL,
+    value,
+    expire INTEGER
+);
+CREATE INDEX IF NOT EXISTS kb_lookup ON kb (context, name, host, key);
+PRAGMA case_sensitive_like = ON;
+";
+
+const SELECT: &str = "SELECT key, type, value, expire FROM kb";
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(value: rusqlite::Error) -> Self {
+        match &value {
+            rusqlite::Error::SqliteFailure(e, _)
+                if matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) =>
+            {
+                StorageError::Retry(format!("{value}"))
+            }
+            rusqlite::Error::SqliteFailure(..) => StorageError::Dirty(format!("{value}")),
+            _ => StorageError::UnexpectedData(format!("{value}")),
+        }
+    }
+}
+
+/// Returns the columns identifying a context key.
+fn context(key: &ContextKey) -> (&str, &str, Option<&str>) {
+    match key {
+        ContextKey::Scan(id, host) => ("scan", id.as_str(), host.as_deref()),
+        ContextKey::FileName(name) => ("file", name.as_str(), None),
+    }
+}
+
+/// Returns the type and the column value of a KB value.
+///
+/// Arrays and dicts are stored as json, within them the distinction between data and arrays
+/// of numbers gets lost.
+fn encode(value: &Primitive) -> Result<(&'static str, Value), StorageError> {
+    fn json<T: serde::Serialize>(x: &T) -> Result<Value, StorageError> {
+        serde_json::to_string(x)
+            .map(Value::Text)
+            .map_err(|e| StorageError::UnexpectedData(format!("{e}")))
+    }
+    Ok(match value {
+        Primitive::String(x) => ("string", Value::Text(x.clone())),
+        Primitive::Data(x) => ("data", Value::Blob(x.clone())),
+        Primitive::Number(x) => ("number", Value::Integer(*x)),
+        Primitive::Boolean(x) => ("boolean", Value::Integer(*x as i64)),
+        Primitive::Null => ("null", Value::Null),
+        Primitive::Array(x) => ("array", json(x)?),
+        Primitive::Dict(x) => ("dict", json(x)?),
+    })
+}
+
+/// Restores a KB value from its type and column value.
+fn decode(kind: &str, value: Value) -> Result<Primitive, StorageError> {
+    let unexpected = |value: &Value| {
+        StorageError::UnexpectedData(format!("{value:?} is not a valid {kind} KB value"))
+    };
+    Ok(match (kind, value) {
+        ("string", Value::Text(x)) => Primitive::String(x),
+        ("data", Value::Blob(x)) => Primitive::Data(x),
+        ("number", Value::Integer(x)) => Primitive::Number(x),
+        ("boolean", Value::Integer(x)) => Primitive::Boolean(x != 0),
+        ("null", Value::Null) => Primitive::Null,
+        ("array", Value::Text(x)) => Primitive::Array(
+            serde_json::from_str(&x).map_err(|_| unexpected(&Value::Text(x.clone())))?,
+        ),
+        ("dict", Value::Text(x)) => Primitive::Dict(
+            serde_json::from_str(&x).map_err(|_| unexpected(&Value::Text(x.clone())))?,
+        ),
+        (_, value) => return Err(unexpected(&value)),
+    })
+}
+
+/// Escapes the wildcards of a prefix to be used within `LIKE` with `\` as escape character.
+fn like_prefix(prefix: &str) -> String {
+    let escaped = prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("{escaped}%")
+}
+
+type KbRow = (String, String, Value, Option<i64>);
+
+fn row(row: &rusqlite::Row) -> rusqlite::Result<KbRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+}
+
+fn kb((key, kind, value, expire): KbRow) -> Result<Kb, StorageError> {
+    Ok(Kb {
+        key,
+        value: decode(&kind, value)?,
+        expire: expire.map(|x| x as u64),
+    })
+}
+
+/// Stores KB items within a SQLite database.
+///
+/// All other fields are handled by the inner storage.
+pub struct SqliteDispatcher<S = DefaultDispatcher> {
+    connection: Mutex<Connection>,
+    inner: S,
+}
+
+impl SqliteDispatcher<DefaultDispatcher> {
+    /// Opens or creates the database at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StorageError> {
+        Self::new(Connection::open(path)?, DefaultDispatcher::new())
+    }
+
+    /// Creates a database that only lives as long as the dispatcher.
+    pub fn in_memory() -> Result<Self, StorageError> {
+        Self::new(Connection::open_in_memory()?, DefaultDispatcher::new())
+    }
+}
+
+impl<S> SqliteDispatcher<S> {
+    /// Creates the KB table if necessary and uses the inner storage for all other fields.
+    pub fn new(connection: Connection, inner: S) -> Result<Self, StorageError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            inner,
+        })
+    }
+
+    /// Returns the storage handling all fields but KB items.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn insert(&self, key: &ContextKey, kb: Kb, replace: bool) -> Result<(), StorageError> {
+        let (context, name, host) = context(key);
+        let (kind, value) = encode(&kb.value)?;
+        let expire = kb.expire.map(|x| x as i64);
+        let mut connection = self.connection.lock()?;
+        let transaction = connection.transaction()?;
+        if replace {
+            transaction.execute(
+                "DELETE FROM kb WHERE context = ?1 AND name = ?2 AND host IS ?3 AND key = ?4",
+                params![context, name, host, kb.key],
+            )?;
+        }
+        // like the DefaultDispatcher the same value is only stored once per key
+        transaction.execute(
+            "INSERT INTO kb (context, name, host, key, type, value, expire)
+            SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7
+            WHERE NOT EXISTS (
+                SELECT 1 FROM kb WHERE context = ?1 AND name = ?2 AND host IS ?3 AND key = ?4
+                AND type = ?5 AND value IS ?6
+            )",
+            params![context, name, host, kb.key, kind, value, expire],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn select(
+        &self,
+        key: &ContextKey,
+        scope: &Retrieve,
+        max_size: Option<usize>,
+    ) -> Result<Vec<Kb>, StorageError> {
+        let (context, name, host) = context(key);
+        let (condition, pattern) = match scope {
+            Retrieve::KB(x) => ("key = ?4", x.clone()),
+            Retrieve::KBPrefix(x) => ("key LIKE ?4 ESCAPE '\\'", like_prefix(x)),
+            _ => return Ok(vec![]),
+        };
+        let connection = self.connection.lock()?;
+        if let Some(max) = max_size {
+            // strings and data are checked before reading them, the encoded size of the other
+            // types differs from Primitive::size so they are checked after decoding
+            let oversized = connection
+                .prepare_cached(&format!(
+                    "SELECT key FROM kb WHERE context = ?1 AND name = ?2 AND host IS ?3
+                    AND {condition} AND type IN ('string', 'data')
+                    AND length(CAST(value AS BLOB)) > ?5 LIMIT 1"
+                ))?
+                .query_row(params![context, name, host, pattern, max as i64], |x| {
+                    x.get::<_, String>(0)
+                })
+                .optional()?;
+            if let Some(key) = oversized {
+                return Err(StorageError::ValueTooLarge { key, max });
+            }
+        }
+        let mut statement = connection.prepare_cached(&format!(
+            "{SELECT} WHERE context = ?1 AND name = ?2 AND host IS ?3 AND {condition}
+            ORDER BY key, id"
+        ))?;
+        let rows = statement
+            .query_map(params![context, name, host, pattern], row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|x| match (kb(x)?, max_size) {
+                (kb, Some(max)) if kb.value.size() > max => {
+                    Err(StorageError::ValueTooLarge { key: kb.key, max })
+                }
+                (kb, _) => Ok(kb),
+            })
+            .collect()
+    }
+}
+
+impl<S> Dispatcher for SqliteDispatcher<S>
+where
+    S: Dispatcher,
+{
+    fn dispatch(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+        match scope {
+            Field::KB(kb) => self.insert(key, kb, false),
+            scope => self.inner.dispatch(key, scope),
+        }
+    }
+
+    fn dispatch_replace(&self, key: &ContextKey, scope: Field) -> Result<(), StorageError> {
+        match scope {
+            Field::KB(kb) => self.insert(key, kb, true),
+            scope => self.inner.dispatch_replace(key, scope),
+        }
+    }
+
+    fn on_exit(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.inner.on_exit(key)
+    }
+
+    fn host_started(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.inner.host_started(key)
+    }
+
+    fn host_finished(&self, key: &ContextKey) -> Result<(), StorageError> {
+        self.inner.host_finished(key)
+    }
+
+    fn stage_finished(&self, key: &ContextKey, stage: Stage) -> Result<(), StorageError> {
+        self.inner.stage_finished(key, stage)
+    }
+}
+
+impl<S> Retriever for SqliteDispatcher<S>
+where
+    S: Retriever,
+{
+    fn retrieve(
+        &self,
+        key: &ContextKey,
+        scope: Retrieve,
+    ) -> Result<Box<dyn Iterator<Item = Field>>, StorageError> {
+        match scope {
+            Retrieve::KB(_) | Retrieve::KBPrefix(_) => Ok(Box::new(
+                self.select(key, &scope, None)?.into_iter().map(Field::KB),
+            )),
+            scope => self.inner.retrieve(key, scope),
+        }
+    }
+
+    fn retrieve_kb(
+        &self,
+        key: &ContextKey,
+        scope: Retrieve,
+        max_size: Option<usize>,
+    ) -> Result<Vec<Kb>, StorageError> {
+        self.select(key, &scope, max_size)
+    }
+
+    fn retrieve_by_field(&self, field: Field, scope: Retrieve) -> FieldKeyResult {
+        self.inner.retrieve_by_field(field, scope)
+    }
+
+    fn retrieve_by_fields(&self, field: Vec<Field>, scope: Retrieve) -> FieldKeyResult {
+        self.inner.retrieve_by_fields(field, scope)
+    }
+}
+
+impl<S> Remover for SqliteDispatcher<S>
+where
+    S: Remover,
+{
+    /// Removes the KB items of a key, a key without a host removes the items of all hosts of the
+    /// scan.
+    fn remove_kb(
+        &self,
+        key: &ContextKey,
+        kb_key: Option<String>,
+    ) -> Result<Option<Vec<Kb>>, StorageError> {
+        let (context, name, host) = context(key);
+        let connection = self.connection.lock()?;
+        let mut statement = connection.prepare_cached(
+            "DELETE FROM kb
+            WHERE context = ?1 AND name = ?2 AND (?3 IS NULL OR host = ?3)
+            AND (?4 IS NULL OR key = ?4)
+            RETURNING key, type, value, expire",
+        )?;
+        let rows = statement
+            .query_map(params![context, name, host, kb_key], row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let removed = rows.into_iter().map(kb).collect::<Result<Vec<_>, _>>()?;
+        Ok(if removed.is_empty() {
+            None
+        } else {
+            Some(removed)
+        })
+    }
+
+    fn remove_result(
+        &self,
+        key: &ContextKey,
+        result_id: Option<usize>,
+    ) -> Result<Option<Vec<models::Result>>, StorageError> {
+        self.inner.remove_result(key, result_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{
+        types::Primitive, ContextKey, Dispatcher, Field, Remover, Retrieve, Retriever, Storage,
+        StorageError,
+    };
+
+    use super::SqliteDispatcher;
+
+    fn key(host: &str) -> ContextKey {
+        ContextKey::Scan("sid".into(), Some(host.into()))
+    }
+
+    fn keys(storage: &SqliteDispatcher, key: &ContextKey, scope: Retrieve) -> Vec<String> {
+        storage
+            .retrieve(key, scope)
+            .unwrap()
+            .map(|x| match x {
+                Field::KB(kb) => kb.key,
+                x => panic!("unexpected field {x:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kb_prefix() -> Result<(), StorageError> {
+        let storage = SqliteDispatcher::in_memory()?;
+        for kb_key in [
+            "Ports/tcp/80",
+            "Ports/udp/161",
+            "Ports/tcp",
+            "Ports/tcp/22",
+            "ports/tcp/21",
+            "Ports%tcp/23",
+            "Host/os",
+        ] {
+            storage.dispatch(&key("test.host"), Field::KB((kb_key, 1).into()))?;
+        }
+        assert_eq!(
+            keys(
+                &storage,
+                &key("test.host"),
+                Retrieve::KBPrefix("Ports/tcp/".into())
+            ),
+            ["Ports/tcp/22", "Ports/tcp/80"]
+        );
+        assert_eq!(
+            keys(&storage, &key("test.host"), Retrieve::KB("Host/os".into())),
+            ["Host/os"]
+        );
+        assert!(keys(
+            &storage,
+            &key("other.host"),
+            Retrieve::KBPrefix("Ports/".into())
+        )
+        .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn values() -> Result<(), StorageError> {
+        let storage = SqliteDispatcher::in_memory()?;
+        let values = [
+            Primitive::String("linux".into()),
+            Primitive::Data(vec![0, 1, 2]),
+            Primitive::Number(-1),
+            Primitive::Boolean(true),
+            Primitive::Null,
+            Primitive::Array(vec![Primitive::String("a".into()), Primitive::Number(1)]),
+            Primitive::Dict(vec![("a".into(), Primitive::Number(1))]),
+        ];
+        for value in values.iter().cloned() {
+            storage.dispatch(&key("test.host"), Field::KB(("value", value).into()))?;
+        }
+        // the same value is only stored once
+        storage.dispatch(&key("test.host"), Field::KB(("value", -1).into()))?;
+        let stored = storage
+            .dump_kb(&key("test.host"))?
+            .into_iter()
+            .map(|x| x.value)
+            .collect::<Vec<_>>();
+        assert_eq!(stored, values);
+
+        storage.dispatch_replace(&key("test.host"), Field::KB(("value", 2).into()))?;
+        let stored = storage.dump_kb(&key("test.host"))?;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].value, Primitive::Number(2));
+        Ok(())
+    }
+
+    #[test]
+    fn retrieve_kb_max_size() -> Result<(), StorageError> {
+        let storage = SqliteDispatcher::in_memory()?;
+        storage.dispatch(&key("test.host"), Field::KB(("Ports/tcp/22", 1).into()))?;
+        storage.dispatch(
+            &key("test.host"),
+            Field::KB(("Ports/tcp/80", "x".repeat(9)).into()),
+        )?;
+        storage.dispatch(
+            &key("test.host"),
+            Field::KB(("Ports/udp/53", Primitive::Array(vec!["x".repeat(9).into()])).into()),
+        )?;
+        let retrieve = |scope: &str, max| {
+            storage.retrieve_kb(&key("test.host"), Retrieve::KBPrefix(scope.into()), max)
+        };
+        assert_eq!(retrieve("Ports/", None)?.len(), 3);
+        assert_eq!(retrieve("Ports/", Some(9))?.len(), 3);
+        assert_eq!(retrieve("Ports/tcp/22", Some(8))?.len(), 1);
+        assert!(matches!(
+            retrieve("Ports/tcp/", Some(8)),
+            Err(StorageError::ValueTooLarge { key, max: 8 }) if key == "Ports/tcp/80"
+        ));
+        assert!(matches!(
+            retrieve("Ports/udp/", Some(8)),
+            Err(StorageError::ValueTooLarge { key, max: 8 }) if key == "Ports/udp/53"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn scan_finished() -> Result<(), StorageError> {
+        let storage = SqliteDispatcher::in_memory()?;
+        for host in ["a.host", "b.host"] {
+            storage.dispatch(&key(host), Field::KB(("Host/os", "linux").into()))?;
+            storage.dispatch(&key(host), Field::KB(("Ports/tcp/22", 1).into()))?;
+        }
+        let removed = storage.remove_kb(&key("a.host"), Some("Ports/tcp/22".into()))?;
+        assert_eq!(removed.map(|x| x.len()), Some(1));
+        assert_eq!(storage.dump_kb(&key("a.host"))?.len(), 1);
+        assert_eq!(storage.dump_kb(&key("b.host"))?.len(), 2);
+
+        storage.scan_finished(&ContextKey::Scan("sid".into(), None))?;
+        assert!(storage.dump_kb(&key("a.host"))?.is_empty());
+        assert!(storage.dump_kb(&key("b.host"))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn persists() -> Result<(), StorageError> {
+        let path = std::env::temp_dir().join(format!("kb-{}.sqlite", std::process::id()));
+        {
+            let storage = SqliteDispatcher::open(&path)?;
+            storage.dispatch(&key("test.host"), Field::KB(("Host/os", "linux").into()))?;
+        }
+        let storage = SqliteDispatcher::open(&path)?;
+        let stored = storage.dump_kb(&key("test.host"));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(stored?[0].value, Primitive::String("linux".into()));
+        Ok(())
+    }
+}
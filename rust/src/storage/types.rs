@@ -31,6 +31,67 @@ pub enum Primitive {
     Null,
 }
 
+impl Primitive {
+    /// Returns the truthiness of the value
+    ///
+    /// Null, false, 0 as well as empty collections are false. Strings and data are false when
+    /// they are empty or `0` after trimming surrounding whitespace, everything else is true.
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Primitive::String(_) | Primitive::Data(_) => match self.as_str() {
+                Some(x) => !matches!(x.trim(), "" | "0"),
+                // data that is not valid UTF-8 can neither be empty nor 0
+                None => true,
+            },
+            Primitive::Array(x) => !x.is_empty(),
+            Primitive::Dict(x) => !x.is_empty(),
+            Primitive::Boolean(x) => *x,
+            Primitive::Number(x) => *x != 0,
+            Primitive::Null => false,
+        }
+    }
+
+    /// Returns the value as number
+    ///
+    /// Booleans are converted to 0 and 1, strings and data are parsed after trimming
+    /// surrounding whitespace. Returns None for all other values or when parsing fails.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Primitive::Number(x) => Some(*x),
+            Primitive::Boolean(x) => Some(*x as i64),
+            Primitive::String(_) | Primitive::Data(_) => self.as_str()?.trim().parse().ok(),
+            Primitive::Array(_) | Primitive::Dict(_) | Primitive::Null => None,
+        }
+    }
+
+    /// Returns the size of the value in bytes
+    ///
+    /// The size of strings and data is their length, of numbers 8 and of booleans 1 byte.
+    /// Collections are the sum of their elements including the keys of a dict.
+    pub fn size(&self) -> usize {
+        match self {
+            Primitive::String(x) => x.len(),
+            Primitive::Data(x) => x.len(),
+            Primitive::Number(_) => std::mem::size_of::<i64>(),
+            Primitive::Boolean(_) => 1,
+            Primitive::Array(x) => x.iter().map(Primitive::size).sum(),
+            Primitive::Dict(x) => x.iter().map(|(k, v)| k.len() + v.size()).sum(),
+            Primitive::Null => 0,
+        }
+    }
+
+    /// Returns the value as string slice
+    ///
+    /// Returns None when the value is neither a string nor valid UTF-8 data.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Primitive::String(x) => Some(x),
+            Primitive::Data(x) => std::str::from_utf8(x).ok(),
+            _ => None,
+        }
+    }
+}
+
 impl From<Vec<u8>> for Primitive {
     fn from(s: Vec<u8>) -> Self {
         Self::Data(s)
@@ -87,15 +148,7 @@ impl From<HashMap<String, Primitive>> for Primitive {
 
 impl From<Primitive> for bool {
     fn from(value: Primitive) -> Self {
-        match value {
-            Primitive::String(string) => !string.is_empty() && string != "0",
-            Primitive::Array(v) => !v.is_empty(),
-            Primitive::Data(v) => !v.is_empty(),
-            Primitive::Boolean(boolean) => boolean,
-            Primitive::Null => false,
-            Primitive::Number(number) => number != 0,
-            Primitive::Dict(v) => !v.is_empty(),
-        }
+        value.as_bool()
     }
 }
 
@@ -161,3 +214,61 @@ impl std::fmt::Display for Primitive {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Primitive;
+
+    #[test]
+    fn as_bool() {
+        assert!(Primitive::Number(1).as_bool());
+        assert!(!Primitive::Number(0).as_bool());
+        assert!(Primitive::String("1".into()).as_bool());
+        assert!(Primitive::String("no".into()).as_bool());
+        assert!(!Primitive::String("0".into()).as_bool());
+        assert!(!Primitive::String(" 0\n".into()).as_bool());
+        assert!(!Primitive::String("".into()).as_bool());
+        assert!(!Primitive::String("  ".into()).as_bool());
+        assert!(Primitive::String("00".into()).as_bool());
+        assert!(!Primitive::Data(b"0".to_vec()).as_bool());
+        assert!(Primitive::Data(vec![0]).as_bool());
+        assert!(Primitive::Boolean(true).as_bool());
+        assert!(!Primitive::Null.as_bool());
+        assert!(!Primitive::Array(vec![]).as_bool());
+        assert!(Primitive::Array(vec![Primitive::Null]).as_bool());
+    }
+
+    #[test]
+    fn as_i64() {
+        assert_eq!(Primitive::Number(-3).as_i64(), Some(-3));
+        assert_eq!(Primitive::Boolean(true).as_i64(), Some(1));
+        assert_eq!(Primitive::String("1".into()).as_i64(), Some(1));
+        assert_eq!(Primitive::String(" 42 ".into()).as_i64(), Some(42));
+        assert_eq!(Primitive::Data(b"0".to_vec()).as_i64(), Some(0));
+        assert_eq!(Primitive::String("".into()).as_i64(), None);
+        assert_eq!(Primitive::String("1a".into()).as_i64(), None);
+        assert_eq!(Primitive::Null.as_i64(), None);
+        assert_eq!(Primitive::Array(vec![1.into()]).as_i64(), None);
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(Primitive::String("abc".into()).size(), 3);
+        assert_eq!(Primitive::Data(vec![0; 5]).size(), 5);
+        assert_eq!(Primitive::Number(1).size(), 8);
+        assert_eq!(Primitive::Null.size(), 0);
+        assert_eq!(Primitive::Array(vec!["ab".into(), true.into()]).size(), 3);
+        assert_eq!(
+            Primitive::Dict(vec![("key".into(), "value".into())]).size(),
+            8
+        );
+    }
+
+    #[test]
+    fn as_str() {
+        assert_eq!(Primitive::String(" a ".into()).as_str(), Some(" a "));
+        assert_eq!(Primitive::Data(b"a".to_vec()).as_str(), Some("a"));
+        assert_eq!(Primitive::Data(vec![0xff]).as_str(), None);
+        assert_eq!(Primitive::Number(1).as_str(), None);
+    }
+}
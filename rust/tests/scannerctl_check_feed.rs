@@ -0,0 +1,94 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+use std::{fs, path::PathBuf, process::Command};
+
+fn feed(name: &str, scripts: &[(&str, &str)]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(path.join("sub")).unwrap();
+    for (filename, code) in scripts {
+        fs::write(path.join(filename), code).unwrap();
+    }
+    path
+}
+
+#[test]
+fn broken_script() {
+    let path = feed(
+        "check-feed-broken",
+        &[
+            ("valid.nasl", "a = 1;\n"),
+            ("sub/broken.nasl", "a = );\nb = 2;\nc = ];\n"),
+            ("ignored.txt", "a = ;\n"),
+        ],
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_scannerctl"))
+        .arg("check-feed")
+        .arg(&path)
+        .output()
+        .expect("scannerctl to be executable");
+    fs::remove_dir_all(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors = stderr
+        .lines()
+        .filter(|x| x.contains("broken.nasl:"))
+        .collect::<Vec<_>>();
+    // the parser continues after the first error
+    assert!(errors[0].contains("broken.nasl:1:"), "{stderr}");
+    assert!(
+        errors.iter().any(|x| x.contains("broken.nasl:3:")),
+        "{stderr}"
+    );
+    assert!(
+        !errors.iter().any(|x| x.contains("broken.nasl:2:")),
+        "{stderr}"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("checked: 2 files; failed: 1 files"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("valid.nasl"), "{stdout}");
+}
+
+#[test]
+fn workers() {
+    let scripts = (0..32)
+        .map(|i| match i % 3 {
+            0 => (format!("{i}.nasl"), format!("a = ({i};\nb = ];\n")),
+            _ => (format!("{i}.nasl"), format!("a = {i};\n")),
+        })
+        .collect::<Vec<_>>();
+    let scripts = scripts
+        .iter()
+        .map(|(name, code)| (name.as_str(), code.as_str()))
+        .collect::<Vec<_>>();
+    let path = feed("check-feed-workers", &scripts);
+    let check = |jobs: &str| {
+        Command::new(env!("CARGO_BIN_EXE_scannerctl"))
+            .args(["check-feed", "--jobs", jobs])
+            .arg(&path)
+            .output()
+            .expect("scannerctl to be executable")
+    };
+    let (single, multiple) = (check("1"), check("4"));
+    fs::remove_dir_all(&path).unwrap();
+    assert_eq!(single.status.code(), Some(1));
+    assert_eq!(single.status, multiple.status);
+    assert_eq!(single.stdout, multiple.stdout);
+    assert_eq!(single.stderr, multiple.stderr);
+}
+
+#[test]
+fn valid_feed() {
+    let path = feed("check-feed-valid", &[("valid.nasl", "a = 1;\n")]);
+    let output = Command::new(env!("CARGO_BIN_EXE_scannerctl"))
+        .arg("check-feed")
+        .arg(&path)
+        .output()
+        .expect("scannerctl to be executable");
+    fs::remove_dir_all(&path).unwrap();
+    assert!(output.status.success());
+}
@@ -0,0 +1,39 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+use std::process::{Command, Output};
+
+fn dump_ast(file: &str, args: &[&str]) -> Output {
+    let path = format!("{}/tests/data/{file}", env!("CARGO_MANIFEST_DIR"));
+    Command::new(env!("CARGO_BIN_EXE_scannerctl"))
+        .arg("dump-ast")
+        .args(args)
+        .arg(path)
+        .output()
+        .expect("scannerctl to be executable")
+}
+
+#[test]
+fn valid() {
+    let output = dump_ast("dump-ast-valid.nasl", &[]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Assign"), "{stdout}");
+    assert!(stdout.contains("Call"), "{stdout}");
+}
+
+#[test]
+fn valid_json() {
+    let output = dump_ast("dump-ast-valid.nasl", &["--json"]);
+    assert!(output.status.success());
+    let statements: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(statements.as_array().map(|x| x.len()), Some(2));
+}
+
+#[test]
+fn invalid() {
+    let output = dump_ast("dump-ast-invalid.nasl", &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("dump-ast-invalid.nasl:5:"), "{stderr}");
+}
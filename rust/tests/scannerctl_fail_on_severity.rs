@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: 2025 Greenbone AG
+//
+// SPDX-License-Identifier: GPL-2.0-or-later WITH x11vnc-openssl-exception
+use std::{fs, path::PathBuf, process::Command};
+
+use sha2::{Digest, Sha256};
+
+const SCAN: &str = r#"{
+  "scan_id": "fail-on-severity",
+  "target": { "hosts": ["127.0.0.1"], "ports": [] },
+  "vts": [{ "oid": "1.3.6.1.4.1.25623.1.0.1" }, { "oid": "1.3.6.1.4.1.25623.1.0.2" }]
+}"#;
+
+fn script(oid: &str, vector: &str, message: &str) -> String {
+    format!(
+        r#"if (description)
+{{
+  script_oid("{oid}");
+  script_version("2023-02-23T13:33:44+0000");
+  script_tag(name:"cvss_base_vector", value:"{vector}");
+  script_name("{oid}");
+  script_category(ACT_GATHER_INFO);
+  script_family("Test");
+  exit(0);
+}}
+{message}(data: "finding");
+"#
+    )
+}
+
+/// Creates a feed with a security message of severity 7.5 and a log message of severity 10.0.
+fn feed(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    let files = [
+        ("plugin_feed_info.inc", "PLUGIN_SET = \"1\";\n".to_owned()),
+        (
+            "alarm.nasl",
+            script(
+                "1.3.6.1.4.1.25623.1.0.1",
+                "AV:N/AC:L/Au:N/C:P/I:P/A:P",
+                "security_message",
+            ),
+        ),
+        (
+            "log.nasl",
+            script(
+                "1.3.6.1.4.1.25623.1.0.2",
+                "AV:N/AC:L/Au:N/C:C/I:C/A:C",
+                "log_message",
+            ),
+        ),
+    ];
+    let mut sums = String::new();
+    for (filename, code) in files {
+        fs::write(path.join(filename), &code).unwrap();
+        let hash = hex::encode(Sha256::digest(code.as_bytes()));
+        sums.push_str(&format!("{hash}  ./{filename}\n"));
+    }
+    fs::write(path.join("sha256sums"), sums).unwrap();
+    fs::write(path.join("scan.json"), SCAN).unwrap();
+    path
+}
+
+fn scan(name: &str, threshold: &str) -> Option<i32> {
+    let path = feed(name);
+    let output = Command::new(env!("CARGO_BIN_EXE_scannerctl"))
+        .args(["execute", "scan", "--fail-on-severity", threshold, "-p"])
+        .arg(&path)
+        .arg(path.join("scan.json"))
+        .output()
+        .expect("scannerctl to be executable");
+    fs::remove_dir_all(&path).unwrap();
+    output.status.code()
+}
+
+#[test]
+fn above_threshold() {
+    assert_eq!(scan("fail-on-severity-above", "7.5"), Some(3));
+}
+
+#[test]
+fn below_threshold() {
+    // the log message of severity 10.0 is not a finding
+    assert_eq!(scan("fail-on-severity-below", "8.0"), Some(0));
+}